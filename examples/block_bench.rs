@@ -0,0 +1,53 @@
+//! Per-sample vs block processing timings
+//!
+//! Run with `cargo run --release --example block_bench`
+
+use dsp_playground::biquad;
+use std::time::{Duration, Instant};
+
+const PASSES: usize = 200;
+
+fn main() {
+    let mut reader = hound::WavReader::open("tests/assets/white_noise_mono.wav").unwrap();
+    let input: Vec<f32> = reader
+        .samples::<i16>()
+        .map(|s| s.unwrap() as f32 / i16::MAX as f32)
+        .collect();
+    let mut output = vec![0.0f32; input.len()];
+
+    println!("{} passes over {} samples (f32)", PASSES, input.len());
+
+    let per_sample = time(|| {
+        let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+        for (sin, sout) in input.iter().zip(output.iter_mut()) {
+            *sout = process.process(sin);
+        }
+    });
+    println!("process (per sample): {:?}", per_sample);
+
+    let block = time(|| {
+        let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+        process.process_block(&input, &mut output);
+    });
+    println!("process_block:        {:?}", block);
+
+    let mut buffer = input.clone();
+    let in_place = time(|| {
+        let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+        process.process_in_place(&mut buffer);
+    });
+    println!("process_in_place:     {:?}", in_place);
+
+    println!(
+        "speedup: {:.2}x",
+        per_sample.as_secs_f64() / block.as_secs_f64()
+    );
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..PASSES {
+        f();
+    }
+    start.elapsed()
+}
@@ -0,0 +1,86 @@
+//! Scalar vs SIMD timings of independent biquads, one per channel
+//!
+//! Both on the same planar buffers, the processes built outside of the timings.
+//! Run with `cargo run --release --example simd_bench`
+
+use dsp_playground::biquad;
+use dsp_playground::simd::{Backend, SimdProcess};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const PASSES: usize = 50;
+const CHANNELS: usize = 8;
+
+fn main() {
+    let mut reader = hound::WavReader::open("tests/assets/white_noise_mono.wav").unwrap();
+    let noise: Vec<f64> = reader
+        .samples::<i16>()
+        .map(|s| s.unwrap() as f64 / i16::MAX as f64)
+        .collect();
+    let channels: Vec<Vec<f64>> = (0..CHANNELS)
+        .map(|c| {
+            noise
+                .iter()
+                .cycle()
+                .skip(c)
+                .take(noise.len())
+                .cloned()
+                .collect()
+        })
+        .collect();
+    let inputs: Vec<&[f64]> = channels.iter().map(|c| &c[..]).collect();
+    let params = vec![biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6; CHANNELS];
+
+    println!(
+        "{} passes over {} frames of {} planar channels (f64)",
+        PASSES,
+        noise.len(),
+        CHANNELS
+    );
+
+    let mut buffers = vec![vec![0.0; noise.len()]; CHANNELS];
+    let mut processes: Vec<biquad::Process> = params
+        .iter()
+        .map(|params| {
+            biquad::Process::with_topology(*params, biquad::Topology::TransposedDirectFormII)
+        })
+        .collect();
+    let scalar = time(|| {
+        for ((process, input), output) in processes
+            .iter_mut()
+            .zip(inputs.iter())
+            .zip(buffers.iter_mut())
+        {
+            process.process_block(black_box(*input), output);
+        }
+        black_box(&buffers);
+    });
+    println!("biquad::Process per channel: {:?}", scalar);
+
+    for backend in [Backend::Scalar, Backend::Sse2, Backend::Avx].iter() {
+        if !backend.is_supported() {
+            println!("{:?}: not supported", backend);
+            continue;
+        }
+        let mut process = SimdProcess::with_backend(&params, *backend);
+        let simd = time(|| {
+            let mut outputs: Vec<&mut [f64]> = buffers.iter_mut().map(|b| &mut b[..]).collect();
+            process.process_planar(black_box(&inputs), &mut outputs);
+            black_box(&outputs);
+        });
+        println!(
+            "{:?}: {:?}, speedup: {:.2}x",
+            backend,
+            simd,
+            scalar.as_secs_f64() / simd.as_secs_f64()
+        );
+    }
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..PASSES {
+        f();
+    }
+    start.elapsed()
+}
@@ -2,11 +2,19 @@
 //! 
 //! Credits: https://www.earlevel.com/main/2012/11/26/biquad-c-source-code/
 
+#![allow(clippy::redundant_field_names)]
+
+use crate::complex::Complex;
+use crate::error::FilterError;
 use crate::filter;
+use crate::processor::{self, Processor};
+use crate::sample::{IntegerSample, Quantizer};
 use std::f64::consts::PI;
-use std::i16;
 
-#[derive(std::cmp::PartialEq, std::fmt::Debug)]
+/// Moved to the [`sample`](crate::sample) module, with the other sample formats
+pub use crate::sample::FloatOfMax1;
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
 pub struct Params {
     pub a0: f64,
     pub a1: f64,
@@ -16,23 +24,131 @@ pub struct Params {
 }
 
 impl Params {
+    /**
+     * Infallible design: invalid params are clamped (see [`filter::Params::clamped`])
+     *
+     * With fs <= 0 or a NaN fc, the filter is a passthrough.
+     * See [`try_from_audio_filter_params`](Params::try_from_audio_filter_params)
+     * to reject them instead.
+     */
     pub fn from_audio_filter_params(
         filter_params: filter::Params,
         filter_type: filter::Type,
         fs: i32,
     ) -> Params {
-        match filter_type {
-            filter::Type::LowPass => low_pass(filter_params, fs),
-            filter::Type::HighPass => high_pass(filter_params, fs),
-            filter::Type::BandPass => band_pass(filter_params, fs),
-            filter::Type::Notch => notch(filter_params, fs),
-            filter::Type::Peak => peak(filter_params, fs),
-            filter::Type::LowShelf => low_shelf(filter_params, fs),
-            filter::Type::HighShelf => high_shelf(filter_params, fs),
+        if fs <= 0 || filter_params.fc.is_nan() {
+            return Params::default();
         }
+        design(filter_params.clamped(fs), filter_type, fs)
+    }
+
+    pub fn try_from_audio_filter_params(
+        filter_params: filter::Params,
+        filter_type: filter::Type,
+        fs: i32,
+    ) -> Result<Params, FilterError> {
+        filter_params.validate(fs)?;
+        Ok(design(filter_params, filter_type, fs))
+    }
+
+    /**
+     * Validated coefficients: finite, and a stable filter
+     */
+    pub fn try_new(a0: f64, a1: f64, a2: f64, b1: f64, b2: f64) -> Result<Params, FilterError> {
+        let params = Params { a0, a1, a2, b1, b2 };
+        params.validate()?;
+        Ok(params)
+    }
+
+    pub fn validate(&self) -> Result<(), FilterError> {
+        let coefficients = [self.a0, self.a1, self.a2, self.b1, self.b2];
+        if !coefficients.iter().all(|c| c.is_finite()) {
+            return Err(FilterError::NonFiniteCoefficient);
+        }
+        if !self.is_stable() {
+            return Err(FilterError::Unstable {
+                pole_magnitude: self.pole_magnitude(),
+            });
+        }
+        Ok(())
+    }
+
+    /**
+     * The roots r of a0 + a1 z^-1 + a2 z^-2 = a0 (1 - r1 z^-1)(1 - r2 z^-1)
+     *
+     * Leading zero coefficients are delays: a0 = 0 leaves a single zero (or none).
+     */
+    pub fn zeros(&self) -> Vec<Complex> {
+        match [self.a0, self.a1, self.a2] {
+            [a0, a1, a2] if a0 != 0.0 => quadratic_roots(a0, a1, a2),
+            [_, a1, a2] if a1 != 0.0 => quadratic_roots(a1, a2, 0.0),
+            _ => vec![],
+        }
+    }
+
+    /**
+     * The roots p of 1 + b1 z^-1 + b2 z^-2 = (1 - p1 z^-1)(1 - p2 z^-1)
+     *
+     * A single pole for first order sections (b2 = 0).
+     */
+    pub fn poles(&self) -> Vec<Complex> {
+        quadratic_roots(1.0, self.b1, self.b2)
+    }
+
+    /**
+     * Magnitude of the pole closest to (or furthest outside of) the unit circle
+     */
+    pub fn pole_magnitude(&self) -> f64 {
+        self.poles().iter().fold(0.0, |max, p| p.norm().max(max))
+    }
+
+    /**
+     * All the poles strictly inside the unit circle
+     *
+     * The stability triangle: |b2| < 1 and |b1| < 1 + b2.
+     */
+    pub fn is_stable(&self) -> bool {
+        self.b2.abs() < 1.0 && self.b1.abs() < 1.0 + self.b2
+    }
+
+    /**
+     * Stable, with no zero outside of the unit circle
+     *
+     * Zeros on the circle (as the low/high pass ones at Nyquist/DC) are accepted.
+     */
+    pub fn is_minimum_phase(&self) -> bool {
+        self.is_stable() && self.zeros().iter().all(|z| z.norm() <= 1.0 + 1e-12)
     }
 }
 
+fn design(filter_params: filter::Params, filter_type: filter::Type, fs: i32) -> Params {
+    match filter_type {
+        filter::Type::LowPass => low_pass(filter_params, fs),
+        filter::Type::HighPass => high_pass(filter_params, fs),
+        filter::Type::BandPass => band_pass(filter_params, fs),
+        filter::Type::Notch => notch(filter_params, fs),
+        filter::Type::Peak => peak(filter_params, fs),
+        filter::Type::LowShelf => low_shelf(filter_params, fs),
+        filter::Type::HighShelf => high_shelf(filter_params, fs),
+    }
+}
+
+/// Roots of c0 + c1 x^-1 + c2 x^-2, with c0 != 0 (a single one if c2 = 0)
+fn quadratic_roots(c0: f64, c1: f64, c2: f64) -> Vec<Complex> {
+    if c2 == 0.0 {
+        return if c1 == 0.0 {
+            vec![]
+        } else {
+            vec![Complex::from(-c1 / c0)]
+        };
+    }
+    let discriminant = Complex::from(c1 * c1 - 4.0 * c0 * c2).sqrt();
+    vec![
+        (discriminant - c1) / (2.0 * c0),
+        (-discriminant - c1) / (2.0 * c0),
+    ]
+}
+
 fn low_pass(filter_params: filter::Params, fs: i32) -> Params {
     let fc = filter_params.fc / fs as f64;
     let k = (PI * fc).tan();
@@ -191,6 +307,27 @@ pub const LOWPASS_FC_1000_Q_0_7071_GAIN_6: Params = Params {
     b2: 0.8175108129889816,
 };
 
+/**
+ * Biquad realization structure
+ *
+ * All the topologies compute the same transfer function but differ in
+ * their numerical behaviour and in the number of state variables.
+ * See https://www.earlevel.com/main/2003/02/28/biquads/
+ * <pre>direct form I is usually the best choice for fixed point, and transposed direct form II for floating point.</pre>
+ */
+#[derive(Clone, Copy, Default, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Topology {
+    /// 4 state variables: the past 2 inputs and the past 2 outputs
+    #[default]
+    DirectFormI,
+    /// 2 state variables, but the internal node can overflow/clip
+    DirectFormII,
+    /// 4 state variables, poles before zeros
+    TransposedDirectFormI,
+    /// 2 state variables, the preferred choice for floating point
+    TransposedDirectFormII,
+}
+
 #[derive(Default)]
 struct Samples {
     sin: f64,
@@ -199,20 +336,82 @@ struct Samples {
     sin_2: f64,
     sout_1: f64,
     sout_2: f64,
+    // internal state of the direct form II and the transposed forms
+    s_1: f64,
+    s_2: f64,
+    s_3: f64,
+    s_4: f64,
 }
 
 pub struct Process {
-    pub params: Params,
+    params: Params,
+    topology: Topology,
     samples: Samples,
+    /// What the params were designed from, to redesign them at another sample rate
+    design: Option<(filter::Params, filter::Type)>,
 }
 
 impl Process {
+    /**
+     * Direct Form I processing
+     */
     pub fn new(params: Params) -> Self {
+        Self::with_topology(params, Topology::default())
+    }
+
+    pub fn with_topology(params: Params, topology: Topology) -> Self {
         Self {
             params: params,
+            topology,
             samples: Samples::default(),
+            design: None,
         }
     }
+
+    /**
+     * Direct Form I processing, designed as [`Params::from_audio_filter_params`]
+     *
+     * Unlike the processes of raw coefficients, it is redesigned by
+     * [`Processor::set_sample_rate`].
+     */
+    pub fn from_audio_filter_params(
+        filter_params: filter::Params,
+        filter_type: filter::Type,
+        fs: i32,
+    ) -> Self {
+        let mut process = Self::new(Params::from_audio_filter_params(
+            filter_params,
+            filter_type,
+            fs,
+        ));
+        process.design = Some((filter_params, filter_type));
+        process
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /**
+     * Replacing the coefficients, keeping the filter memory
+     *
+     * The process is then one of raw coefficients: kept by [`Processor::set_sample_rate`].
+     */
+    pub fn set_params(&mut self, params: Params) {
+        self.params = params;
+        self.design = None;
+    }
+
+    /**
+     * Clearing the filter memory
+     */
+    pub fn reset(&mut self) {
+        self.samples = Samples::default();
+    }
 }
 
 impl Default for Params {
@@ -227,62 +426,111 @@ impl Default for Params {
     }
 }
 
-/**
- * Representing samples in float from -1.0 to 1.0 range
- *
- * Internal representation is in f64.
- * For example, for an int8 range that goes from -128 to 127
- * - -127 will give -1.0
- * - +127 will give +1.0
- */
-pub trait FloatOfMax1<T> {
-    fn to_f64(&self) -> f64;
-    fn from_f64(&self, x: f64) -> T;
-}
-
-impl FloatOfMax1<f64> for f64 {
-    fn to_f64(&self) -> f64 {
-        *self
-    }
-
-    fn from_f64(&self, x: f64) -> f64 {
-        x
-    }
-}
+impl Process {
+    /**
+     * Processing one sample
+     *
+     * Input sample can be of any format of the [`sample`](crate::sample) module
+     */
+    pub fn process<T>(&mut self, sample: &dyn FloatOfMax1<T>) -> T {
+        let out = self.tick(sample.to_f64());
 
-impl FloatOfMax1<f32> for f32 {
-    fn to_f64(&self) -> f64 {
-        *self as f64
+        // the sample is not really used here.. but we need to just for it's type
+        sample.from_f64(out)
     }
 
-    fn from_f64(&self, x: f64) -> f32 {
-        x as f32
+    /**
+     * Processing one sample in the internal f64 representation
+     */
+    pub fn tick(&mut self, x: f64) -> f64 {
+        match self.topology {
+            Topology::DirectFormI => self.tick_df1(x),
+            Topology::DirectFormII => self.tick_df2(x),
+            Topology::TransposedDirectFormI => self.tick_tdf1(x),
+            Topology::TransposedDirectFormII => self.tick_tdf2(x),
+        }
     }
-}
 
-impl FloatOfMax1<i16> for i16 {
-    fn to_f64(&self) -> f64 {
-        (*self as f64) / i16::MAX as f64
+    /**
+     * Processing a block of samples
+     *
+     * Monomorphized over the sample type, so there is no dynamic dispatch per sample.
+     * Integer samples are only rounded: see [`Process::process_block_quantized`].
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block<T: FloatOfMax1<T>>(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        match self.topology {
+            Topology::DirectFormI => {
+                for (sin, sout) in input.iter().zip(output.iter_mut()) {
+                    *sout = sin.from_f64(self.tick_df1(sin.to_f64()));
+                }
+            }
+            Topology::DirectFormII => {
+                for (sin, sout) in input.iter().zip(output.iter_mut()) {
+                    *sout = sin.from_f64(self.tick_df2(sin.to_f64()));
+                }
+            }
+            Topology::TransposedDirectFormI => {
+                for (sin, sout) in input.iter().zip(output.iter_mut()) {
+                    *sout = sin.from_f64(self.tick_tdf1(sin.to_f64()));
+                }
+            }
+            Topology::TransposedDirectFormII => {
+                for (sin, sout) in input.iter().zip(output.iter_mut()) {
+                    *sout = sin.from_f64(self.tick_tdf2(sin.to_f64()));
+                }
+            }
+        }
     }
 
-    fn from_f64(&self, x: f64) -> i16 {
-        (x * (i16::MAX as f64)) as i16
+    /**
+     * Processing a block of integer samples with the scaling and dither of the quantizer
+     *
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block_quantized<T: IntegerSample>(
+        &mut self,
+        input: &[T],
+        output: &mut [T],
+        quantizer: &mut Quantizer,
+    ) {
+        quantizer.process_block(input, output, |x| self.tick(x));
     }
-}
 
-impl Process {
     /**
-     * Processing one sample
-     *
-     * Input sample can be i16 or f64
-     * TODO: use type 2? read that it's better for floating point calculations
-     * see https://www.earlevel.com/main/2003/02/28/biquads/
-     * <pre>direct form I is usually the best choice for fixed point, and transposed direct form II for floating point.</pre>
+     * Processing a block of samples, overwriting them with the filtered output
      */
-    pub fn process<T>(&mut self, sample: &dyn FloatOfMax1<T>) -> T {
+    pub fn process_in_place<T: FloatOfMax1<T>>(&mut self, samples: &mut [T]) {
+        match self.topology {
+            Topology::DirectFormI => {
+                for s in samples.iter_mut() {
+                    *s = s.from_f64(self.tick_df1(s.to_f64()));
+                }
+            }
+            Topology::DirectFormII => {
+                for s in samples.iter_mut() {
+                    *s = s.from_f64(self.tick_df2(s.to_f64()));
+                }
+            }
+            Topology::TransposedDirectFormI => {
+                for s in samples.iter_mut() {
+                    *s = s.from_f64(self.tick_tdf1(s.to_f64()));
+                }
+            }
+            Topology::TransposedDirectFormII => {
+                for s in samples.iter_mut() {
+                    *s = s.from_f64(self.tick_tdf2(s.to_f64()));
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn tick_df1(&mut self, x: f64) -> f64 {
         let samples = &mut self.samples;
         let params = &self.params;
-        samples.sin = sample.to_f64();
+        samples.sin = x;
 
         // biquad calculation
         let direct = samples.sin * params.a0;
@@ -302,7 +550,256 @@ impl Process {
         samples.sout_2 = samples.sout_1;
         samples.sout_1 = out;
 
-        // the sample is not really used here.. but we need to just for it's type
-        sample.from_f64(out)
+        out
+    }
+
+    #[inline(always)]
+    fn tick_df2(&mut self, x: f64) -> f64 {
+        let samples = &mut self.samples;
+        let params = &self.params;
+
+        // s_1, s_2: the past values of the internal (poles) node
+        let w = x - params.b1 * samples.s_1 - params.b2 * samples.s_2;
+        let out = params.a0 * w + params.a1 * samples.s_1 + params.a2 * samples.s_2;
+
+        samples.s_2 = samples.s_1;
+        samples.s_1 = w;
+
+        out
+    }
+
+    #[inline(always)]
+    fn tick_tdf1(&mut self, x: f64) -> f64 {
+        let samples = &mut self.samples;
+        let params = &self.params;
+
+        // poles: s_1, s_2
+        let w = x + samples.s_1;
+        samples.s_1 = samples.s_2 - params.b1 * w;
+        samples.s_2 = -params.b2 * w;
+
+        // zeros: s_3, s_4
+        let out = params.a0 * w + samples.s_3;
+        samples.s_3 = params.a1 * w + samples.s_4;
+        samples.s_4 = params.a2 * w;
+
+        out
+    }
+
+    #[inline(always)]
+    fn tick_tdf2(&mut self, x: f64) -> f64 {
+        let samples = &mut self.samples;
+        let params = &self.params;
+
+        let out = params.a0 * x + samples.s_1;
+        samples.s_1 = params.a1 * x - params.b1 * out + samples.s_2;
+        samples.s_2 = params.a2 * x - params.b2 * out;
+
+        out
+    }
+}
+
+impl filter::Filter for Process {
+    fn tick(&mut self, x: f64) -> f64 {
+        Process::tick(self, x)
+    }
+}
+
+impl Processor for Process {
+    fn reset(&mut self) {
+        Process::reset(self)
+    }
+
+    fn set_sample_rate(&mut self, fs: i32) {
+        if let Some((filter_params, filter_type)) = self.design {
+            self.params = Params::from_audio_filter_params(filter_params, filter_type, fs);
+        }
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(1, inputs, outputs);
+        Process::process_block(self, inputs[0], outputs[0]);
+    }
+
+    fn tail(&self) -> processor::Tail {
+        processor::decay_tail(self.params.pole_magnitude())
+    }
+}
+
+/**
+ * Independent filters for several channels: each one has its own memory
+ *
+ * The channels share the same params unless set one by one,
+ * see [`channel_mut`](MultichannelProcess::channel_mut).
+ */
+pub struct MultichannelProcess {
+    channels: Vec<Process>,
+}
+
+impl MultichannelProcess {
+    /**
+     * Direct Form I processing, the same params for all the channels
+     */
+    pub fn new(params: Params, channels: usize) -> Self {
+        Self::with_topology(params, Topology::default(), channels)
+    }
+
+    pub fn with_topology(params: Params, topology: Topology, channels: usize) -> Self {
+        Self {
+            channels: (0..channels)
+                .map(|_| Process::with_topology(params, topology))
+                .collect(),
+        }
+    }
+
+    /**
+     * As [`Process::from_audio_filter_params`], redesigned by [`Processor::set_sample_rate`]
+     */
+    pub fn from_audio_filter_params(
+        filter_params: filter::Params,
+        filter_type: filter::Type,
+        fs: i32,
+        channels: usize,
+    ) -> Self {
+        Self {
+            channels: (0..channels)
+                .map(|_| Process::from_audio_filter_params(filter_params, filter_type, fs))
+                .collect(),
+        }
+    }
+
+    /**
+     * Direct Form I processing, with params per channel
+     */
+    pub fn with_channel_params(params: Vec<Params>) -> Self {
+        Self {
+            channels: params.into_iter().map(Process::new).collect(),
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /**
+     * Same params for all the channels
+     */
+    pub fn set_params(&mut self, params: Params) {
+        for channel in self.channels.iter_mut() {
+            channel.set_params(params);
+        }
+    }
+
+    /**
+     * Access to a single channel, for example to set its own params
+     */
+    pub fn channel_mut(&mut self, channel: usize) -> Option<&mut Process> {
+        self.channels.get_mut(channel)
+    }
+
+    /**
+     * Clearing the memory of all the channels
+     */
+    pub fn reset(&mut self) {
+        for channel in self.channels.iter_mut() {
+            channel.reset();
+        }
+    }
+
+    /**
+     * Processing planar buffers: one input and one output slice per channel
+     *
+     * Panics if the number of slices is not the channel count, or if their lengths differ.
+     */
+    pub fn process_planar<T: FloatOfMax1<T>>(&mut self, inputs: &[&[T]], outputs: &mut [&mut [T]]) {
+        assert_eq!(
+            inputs.len(),
+            self.channels(),
+            "expected {} input channels",
+            self.channels()
+        );
+        assert_eq!(
+            outputs.len(),
+            self.channels(),
+            "expected {} output channels",
+            self.channels()
+        );
+        for ((channel, input), output) in self
+            .channels
+            .iter_mut()
+            .zip(inputs.iter())
+            .zip(outputs.iter_mut())
+        {
+            channel.process_block(input, output);
+        }
+    }
+
+    /**
+     * Processing interleaved frames: one sample per channel, channel after channel
+     *
+     * Panics if the lengths differ or are not a multiple of the channel count.
+     */
+    pub fn process_interleaved<T: FloatOfMax1<T>>(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        self.assert_frames(input.len());
+        let count = self.channels();
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            for (sin, sout) in input
+                .iter()
+                .skip(index)
+                .step_by(count)
+                .zip(output.iter_mut().skip(index).step_by(count))
+            {
+                *sout = sin.from_f64(channel.tick(sin.to_f64()));
+            }
+        }
+    }
+
+    pub fn process_interleaved_in_place<T: FloatOfMax1<T>>(&mut self, samples: &mut [T]) {
+        self.assert_frames(samples.len());
+        let count = self.channels();
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            for s in samples.iter_mut().skip(index).step_by(count) {
+                *s = s.from_f64(channel.tick(s.to_f64()));
+            }
+        }
+    }
+
+    fn assert_frames(&self, length: usize) {
+        assert!(
+            self.channels.is_empty() || length.is_multiple_of(self.channels()),
+            "interleaved length {} is not a multiple of {} channels",
+            length,
+            self.channels()
+        );
+    }
+}
+
+impl Processor for MultichannelProcess {
+    fn reset(&mut self) {
+        MultichannelProcess::reset(self)
+    }
+
+    fn set_sample_rate(&mut self, fs: i32) {
+        for channel in self.channels.iter_mut() {
+            channel.set_sample_rate(fs);
+        }
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(self.channels(), inputs, outputs);
+        self.process_planar(inputs, outputs);
+    }
+
+    fn tail(&self) -> processor::Tail {
+        self.channels
+            .iter()
+            .fold(processor::Tail::Samples(0), |tail, channel| {
+                tail.max(channel.tail())
+            })
+    }
+
+    fn channels(&self) -> usize {
+        MultichannelProcess::channels(self)
     }
 }
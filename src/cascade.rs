@@ -0,0 +1,223 @@
+//! Cascaded biquads (second order sections)
+//!
+//! Several biquad sections running in series, each one with its own state.
+//! Higher order filters are built as a cascade of second order sections.
+
+use crate::biquad;
+use crate::error::FilterError;
+use crate::filter;
+use crate::processor::{self, Processor};
+use crate::sample::{FloatOfMax1, IntegerSample, Quantizer};
+
+/// Block size used internally when processing non-f64 samples
+const BLOCK_SIZE: usize = 64;
+
+pub struct Cascade {
+    /// Overall (linear) gain, applied after the last section
+    pub gain: f64,
+    sections: Vec<biquad::Process>,
+}
+
+impl Cascade {
+    /**
+     * Direct Form I sections, with unity overall gain
+     */
+    pub fn new(sections: Vec<biquad::Params>) -> Self {
+        Self::with_topology(sections, biquad::Topology::default())
+    }
+
+    pub fn with_topology(sections: Vec<biquad::Params>, topology: biquad::Topology) -> Self {
+        Self {
+            gain: 1.0,
+            sections: sections
+                .into_iter()
+                .map(|params| biquad::Process::with_topology(params, topology))
+                .collect(),
+        }
+    }
+
+    /**
+     * One section per (params, type) pair, in the given order
+     *
+     * The sections are redesigned by [`Processor::set_sample_rate`].
+     */
+    pub fn from_audio_filter_params(filters: &[(filter::Params, filter::Type)], fs: i32) -> Self {
+        Self {
+            gain: 1.0,
+            sections: filters
+                .iter()
+                .map(|(params, filter_type)| {
+                    biquad::Process::from_audio_filter_params(*params, *filter_type, fs)
+                })
+                .collect(),
+        }
+    }
+
+    /**
+     * As [`from_audio_filter_params`](Cascade::from_audio_filter_params),
+     * failing on the first invalid params
+     */
+    pub fn try_from_audio_filter_params(
+        filters: &[(filter::Params, filter::Type)],
+        fs: i32,
+    ) -> Result<Self, FilterError> {
+        let sections = filters
+            .iter()
+            .map(|(params, filter_type)| {
+                biquad::Params::try_from_audio_filter_params(*params, *filter_type, fs)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(sections))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = &biquad::Params> {
+        self.sections.iter().map(|section| section.params())
+    }
+
+    /**
+     * Access to a single section, for example to update its params
+     */
+    pub fn section_mut(&mut self, index: usize) -> Option<&mut biquad::Process> {
+        self.sections.get_mut(index)
+    }
+
+    pub fn gain_db(&self) -> f64 {
+        20.0 * self.gain.abs().log10()
+    }
+
+    pub fn set_gain_db(&mut self, gain_db: f64) {
+        self.gain = 10.0f64.powf(gain_db / 20.0);
+    }
+
+    /**
+     * Clearing the memory of all the sections
+     */
+    pub fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+    }
+
+    /**
+     * Processing one sample
+     */
+    pub fn process<T>(&mut self, sample: &dyn FloatOfMax1<T>) -> T {
+        sample.from_f64(self.tick(sample.to_f64()))
+    }
+
+    /**
+     * Processing one sample in the internal f64 representation
+     */
+    pub fn tick(&mut self, x: f64) -> f64 {
+        let mut out = x;
+        for section in self.sections.iter_mut() {
+            out = section.tick(out);
+        }
+        out * self.gain
+    }
+
+    /**
+     * Processing a block of samples
+     *
+     * The intermediate results stay in f64 between the sections.
+     * Integer samples are only rounded: see [`Cascade::process_block_quantized`].
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block<T: FloatOfMax1<T>>(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        let mut buffer = [0.0f64; BLOCK_SIZE];
+        for (sin, sout) in input.chunks(BLOCK_SIZE).zip(output.chunks_mut(BLOCK_SIZE)) {
+            let buffer = &mut buffer[..sin.len()];
+            for (b, s) in buffer.iter_mut().zip(sin.iter()) {
+                *b = s.to_f64();
+            }
+            self.process_f64_in_place(buffer);
+            for ((o, s), b) in sout.iter_mut().zip(sin.iter()).zip(buffer.iter()) {
+                *o = s.from_f64(*b);
+            }
+        }
+    }
+
+    /**
+     * Processing a block of integer samples with the scaling and dither of the quantizer
+     *
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block_quantized<T: IntegerSample>(
+        &mut self,
+        input: &[T],
+        output: &mut [T],
+        quantizer: &mut Quantizer,
+    ) {
+        quantizer.process_block(input, output, |x| self.tick(x));
+    }
+
+    /**
+     * Processing a block of samples, overwriting them with the filtered output
+     */
+    pub fn process_in_place<T: FloatOfMax1<T>>(&mut self, samples: &mut [T]) {
+        let mut buffer = [0.0f64; BLOCK_SIZE];
+        for chunk in samples.chunks_mut(BLOCK_SIZE) {
+            let buffer = &mut buffer[..chunk.len()];
+            for (b, s) in buffer.iter_mut().zip(chunk.iter()) {
+                *b = s.to_f64();
+            }
+            self.process_f64_in_place(buffer);
+            for (s, b) in chunk.iter_mut().zip(buffer.iter()) {
+                *s = s.from_f64(*b);
+            }
+        }
+    }
+
+    // section by section over the whole buffer
+    fn process_f64_in_place(&mut self, buffer: &mut [f64]) {
+        for section in self.sections.iter_mut() {
+            section.process_in_place(buffer);
+        }
+        if self.gain != 1.0 {
+            for b in buffer.iter_mut() {
+                *b *= self.gain;
+            }
+        }
+    }
+}
+
+impl filter::Filter for Cascade {
+    fn tick(&mut self, x: f64) -> f64 {
+        Cascade::tick(self, x)
+    }
+}
+
+impl Processor for Cascade {
+    fn reset(&mut self) {
+        Cascade::reset(self)
+    }
+
+    fn set_sample_rate(&mut self, fs: i32) {
+        for section in self.sections.iter_mut() {
+            section.set_sample_rate(fs);
+        }
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(1, inputs, outputs);
+        outputs[0].copy_from_slice(inputs[0]);
+        self.process_f64_in_place(outputs[0]);
+    }
+
+    fn tail(&self) -> processor::Tail {
+        self.sections
+            .iter()
+            .fold(processor::Tail::Samples(0), |tail, section| {
+                tail.then(section.tail())
+            })
+    }
+}
@@ -0,0 +1,458 @@
+//! Character filters: analog synth filters with saturation
+//!
+//! Inspired by the Filther JSFX, https://joepvanlier.github.io/FiltherManual/
+//! Zero delay feedback models (see Vadim Zavalishin, The Art of VA Filter Design,
+//! and Will Pirkle's Korg35 application note): the loop is solved linearly
+//! at each sample, then the signal in the loop goes through tanh.
+//!
+//! All of them share the [`CharacterFilter`] controls:
+//! - cutoff in Hz
+//! - resonance from 0 to 1
+//! - drive, the gain into the saturation (compensated at the output): below 1 is
+//!   almost linear, above 1 saturates more and more.
+
+use crate::filter;
+use crate::processor::{self, Processor};
+use std::f64::consts::PI;
+
+/// Highest cutoff, relative to fs
+const MAX_RELATIVE_CUTOFF: f64 = 0.45;
+const MIN_DRIVE: f64 = 0.01;
+const MAX_DRIVE: f64 = 100.0;
+/// Feedback gain at full resonance of the Sallen-Key filters (self oscillating over 2)
+const MAX_FEEDBACK: f64 = 2.1;
+/// Damping at full resonance of the SEM (no self oscillation)
+const MIN_DAMPING: f64 = 0.05;
+
+/**
+ * Controls shared by the character filters, swappable behind a `Box<dyn CharacterFilter>`
+ */
+pub trait CharacterFilter: filter::Filter {
+    fn cutoff(&self) -> f64;
+    /// Clamped to (0, 0.45 fs], can change at every sample
+    fn set_cutoff(&mut self, cutoff: f64);
+    fn resonance(&self) -> f64;
+    /// Clamped to [0, 1]
+    fn set_resonance(&mut self, resonance: f64);
+    fn drive(&self) -> f64;
+    /// Clamped to [0.01, 100]
+    fn set_drive(&mut self, drive: f64);
+}
+
+struct Controls {
+    fs: i32,
+    filter_type: filter::Type,
+    cutoff: f64,
+    resonance: f64,
+    drive: f64,
+    /// Integrator gain, tan(π fc / fs)
+    g: f64,
+}
+
+impl Controls {
+    fn new(
+        name: &str,
+        supported_types: &[filter::Type],
+        filter_type: filter::Type,
+        cutoff: f64,
+        resonance: f64,
+        fs: i32,
+    ) -> Self {
+        assert!(fs > 0, "{}: invalid sample rate {}", name, fs);
+        assert!(
+            supported_types.contains(&filter_type),
+            "{}: unsupported type {:?}",
+            name,
+            filter_type
+        );
+        let mut controls = Controls {
+            fs,
+            filter_type,
+            cutoff: 0.0,
+            resonance: 0.0,
+            drive: 1.0,
+            g: 0.0,
+        };
+        controls.set_cutoff(cutoff);
+        controls.set_resonance(resonance);
+        controls
+    }
+
+    fn set_cutoff(&mut self, cutoff: f64) {
+        let max = MAX_RELATIVE_CUTOFF * self.fs as f64;
+        self.cutoff = if cutoff.is_nan() {
+            max
+        } else {
+            cutoff.clamp(f64::MIN_POSITIVE, max)
+        };
+        self.g = (PI * self.cutoff / self.fs as f64).tan();
+    }
+
+    /// Keeping the cutoff in Hz, clamped for the new sample rate
+    fn set_sample_rate(&mut self, name: &str, fs: i32) {
+        assert!(fs > 0, "{}: invalid sample rate {}", name, fs);
+        self.fs = fs;
+        self.set_cutoff(self.cutoff);
+    }
+
+    fn set_resonance(&mut self, resonance: f64) {
+        self.resonance = if resonance.is_nan() {
+            0.0
+        } else {
+            resonance.clamp(0.0, 1.0)
+        };
+    }
+
+    fn set_drive(&mut self, drive: f64) {
+        self.drive = if drive.is_nan() {
+            1.0
+        } else {
+            drive.clamp(MIN_DRIVE, MAX_DRIVE)
+        };
+    }
+
+    /// Feedback gain of the Sallen-Key filters
+    fn feedback(&self) -> f64 {
+        MAX_FEEDBACK * self.resonance
+    }
+
+    /// Damping of the SEM
+    fn damping(&self) -> f64 {
+        (2.0 * (1.0 - self.resonance)).max(MIN_DAMPING)
+    }
+
+    /// Gain of the one pole stages: their low pass is big_g * x + state
+    fn big_g(&self) -> f64 {
+        self.g / (1.0 + self.g)
+    }
+
+    #[inline(always)]
+    fn saturate(&self, x: f64) -> f64 {
+        (self.drive * x).tanh() / self.drive
+    }
+}
+
+/**
+ * Trapezoidal one pole low pass (and its high pass complement)
+ */
+#[derive(Default)]
+struct OnePole {
+    s: f64,
+}
+
+impl OnePole {
+    /// The part of the output due to the state: low pass = big_g * x + state
+    fn state(&self, big_g: f64) -> f64 {
+        (1.0 - big_g) * self.s
+    }
+
+    fn low_pass(&mut self, x: f64, big_g: f64) -> f64 {
+        let v = (x - self.s) * big_g;
+        let y = v + self.s;
+        self.s = y + v;
+        y
+    }
+
+    fn high_pass(&mut self, x: f64, big_g: f64) -> f64 {
+        x - self.low_pass(x, big_g)
+    }
+}
+
+macro_rules! character_filter {
+    ($filter:ident) => {
+        impl CharacterFilter for $filter {
+            fn cutoff(&self) -> f64 {
+                self.controls.cutoff
+            }
+
+            fn set_cutoff(&mut self, cutoff: f64) {
+                self.controls.set_cutoff(cutoff);
+            }
+
+            fn resonance(&self) -> f64 {
+                self.controls.resonance
+            }
+
+            fn set_resonance(&mut self, resonance: f64) {
+                self.controls.set_resonance(resonance);
+            }
+
+            fn drive(&self) -> f64 {
+                self.controls.drive
+            }
+
+            fn set_drive(&mut self, drive: f64) {
+                self.controls.set_drive(drive);
+            }
+        }
+    };
+}
+
+/**
+ * MS-20 style Sallen-Key, 12 dB/oct low or high pass
+ *
+ * Two one pole stages, with the complement of the second one fed back
+ * into the first one: 1 / (s^2 + (2 - k) s + 1). Self oscillating over ~0.95.
+ */
+pub struct Ms20 {
+    controls: Controls,
+    stage1: OnePole,
+    stage2: OnePole,
+}
+
+impl Ms20 {
+    /**
+     * Panics if fs <= 0 or the type is neither low nor high pass
+     */
+    pub fn new(filter_type: filter::Type, cutoff: f64, resonance: f64, fs: i32) -> Self {
+        Self {
+            controls: Controls::new(
+                "ms20",
+                &[filter::Type::LowPass, filter::Type::HighPass],
+                filter_type,
+                cutoff,
+                resonance,
+                fs,
+            ),
+            stage1: OnePole::default(),
+            stage2: OnePole::default(),
+        }
+    }
+}
+
+impl filter::Filter for Ms20 {
+    fn tick(&mut self, x: f64) -> f64 {
+        let big_g = self.controls.big_g();
+        let k = self.controls.feedback();
+        let s1 = self.stage1.state(big_g);
+        let s2 = self.stage2.state(big_g);
+        let denominator = 1.0 - k * big_g * (1.0 - big_g);
+
+        if self.controls.filter_type == filter::Type::LowPass {
+            // feedback: high pass of the second stage
+            let u = (x + k * ((1.0 - big_g) * s1 - s2)) / denominator;
+            let u = self.controls.saturate(u);
+            let y1 = self.stage1.low_pass(u, big_g);
+            self.stage2.low_pass(y1, big_g)
+        } else {
+            // feedback: low pass of the second stage
+            let u = (x + k * (s2 - big_g * s1)) / denominator;
+            let u = self.controls.saturate(u);
+            let y1 = self.stage1.high_pass(u, big_g);
+            self.stage2.high_pass(y1, big_g)
+        }
+    }
+}
+
+impl Processor for Ms20 {
+    fn reset(&mut self) {
+        self.stage1 = OnePole::default();
+        self.stage2 = OnePole::default();
+    }
+
+    /**
+     * Keeping the cutoff in Hz (clamped for the new sample rate)
+     *
+     * Panics if fs <= 0.
+     */
+    fn set_sample_rate(&mut self, fs: i32) {
+        self.controls.set_sample_rate("ms20", fs);
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::process_filter(self, inputs, outputs);
+    }
+
+    /**
+     * Tail of the small signals: s^2 + (2 - k) s + 1
+     */
+    fn tail(&self) -> processor::Tail {
+        let k = self.controls.feedback();
+        processor::analog_decay_tail(&[1.0, 2.0 - k, 1.0], self.controls.g)
+    }
+}
+
+character_filter!(Ms20);
+
+/**
+ * Korg35 (early MS-20), 12 dB/oct low or high pass after a one pole input stage
+ *
+ * The resonant loop is a one pole low (high) pass with a high (low) pass of its
+ * output in positive feedback. Self oscillating over ~0.95.
+ */
+pub struct Korg35 {
+    controls: Controls,
+    input: OnePole,
+    stage: OnePole,
+    feedback: OnePole,
+}
+
+impl Korg35 {
+    /**
+     * Panics if fs <= 0 or the type is neither low nor high pass
+     */
+    pub fn new(filter_type: filter::Type, cutoff: f64, resonance: f64, fs: i32) -> Self {
+        Self {
+            controls: Controls::new(
+                "korg35",
+                &[filter::Type::LowPass, filter::Type::HighPass],
+                filter_type,
+                cutoff,
+                resonance,
+                fs,
+            ),
+            input: OnePole::default(),
+            stage: OnePole::default(),
+            feedback: OnePole::default(),
+        }
+    }
+}
+
+impl filter::Filter for Korg35 {
+    fn tick(&mut self, x: f64) -> f64 {
+        let big_g = self.controls.big_g();
+        let k = self.controls.feedback();
+        let s_stage = self.stage.state(big_g);
+        let s_feedback = self.feedback.state(big_g);
+        let denominator = 1.0 - k * big_g * (1.0 - big_g);
+
+        if self.controls.filter_type == filter::Type::LowPass {
+            let y1 = self.input.low_pass(x, big_g);
+            let u = (y1 + k * ((1.0 - big_g) * s_stage - s_feedback)) / denominator;
+            let u = self.controls.saturate(u);
+            let y = self.stage.low_pass(u, big_g);
+            self.feedback.high_pass(y, big_g);
+            y
+        } else {
+            let y1 = self.input.high_pass(x, big_g);
+            let u = (y1 + k * (s_feedback - big_g * s_stage)) / denominator;
+            let u = self.controls.saturate(u);
+            let y = self.stage.high_pass(u, big_g);
+            self.feedback.low_pass(y, big_g);
+            y
+        }
+    }
+}
+
+impl Processor for Korg35 {
+    fn reset(&mut self) {
+        self.input = OnePole::default();
+        self.stage = OnePole::default();
+        self.feedback = OnePole::default();
+    }
+
+    /**
+     * Keeping the cutoff in Hz (clamped for the new sample rate)
+     *
+     * Panics if fs <= 0.
+     */
+    fn set_sample_rate(&mut self, fs: i32) {
+        self.controls.set_sample_rate("korg35", fs);
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::process_filter(self, inputs, outputs);
+    }
+
+    /**
+     * Tail of the small signals: the input stage (s + 1) then s^2 + (2 - k) s + 1
+     */
+    fn tail(&self) -> processor::Tail {
+        let k = self.controls.feedback();
+        processor::analog_decay_tail(&[1.0, 3.0 - k, 3.0 - k, 1.0], self.controls.g)
+    }
+}
+
+character_filter!(Korg35);
+
+/**
+ * Oberheim SEM style state variable filter, 12 dB/oct
+ *
+ * Low, high, band pass or notch. The input and the band pass integrator saturate,
+ * the latter taming the resonance of loud signals. Never self oscillating, as the original.
+ */
+pub struct Sem {
+    controls: Controls,
+    // integrators' memory
+    ic1eq: f64,
+    ic2eq: f64,
+}
+
+impl Sem {
+    /**
+     * Panics if fs <= 0 or the type is not low, high, band pass or notch
+     */
+    pub fn new(filter_type: filter::Type, cutoff: f64, resonance: f64, fs: i32) -> Self {
+        Self {
+            controls: Controls::new(
+                "sem",
+                &[
+                    filter::Type::LowPass,
+                    filter::Type::HighPass,
+                    filter::Type::BandPass,
+                    filter::Type::Notch,
+                ],
+                filter_type,
+                cutoff,
+                resonance,
+                fs,
+            ),
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+        }
+    }
+}
+
+impl filter::Filter for Sem {
+    fn tick(&mut self, x: f64) -> f64 {
+        let g = self.controls.g;
+        let k = self.controls.damping();
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let x = self.controls.saturate(x);
+        let v3 = x - self.ic2eq;
+        let band = a1 * self.ic1eq + a2 * v3;
+        let low = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = self.controls.saturate(2.0 * band - self.ic1eq);
+        self.ic2eq = 2.0 * low - self.ic2eq;
+
+        match self.controls.filter_type {
+            filter::Type::LowPass => low,
+            filter::Type::HighPass => x - k * band - low,
+            filter::Type::BandPass => k * band,
+            _ => x - k * band,
+        }
+    }
+}
+
+impl Processor for Sem {
+    fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+
+    /**
+     * Keeping the cutoff in Hz (clamped for the new sample rate)
+     *
+     * Panics if fs <= 0.
+     */
+    fn set_sample_rate(&mut self, fs: i32) {
+        self.controls.set_sample_rate("sem", fs);
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::process_filter(self, inputs, outputs);
+    }
+
+    /**
+     * Tail of the small signals: s^2 + k s + 1
+     */
+    fn tail(&self) -> processor::Tail {
+        let k = self.controls.damping();
+        processor::analog_decay_tail(&[1.0, k, 1.0], self.controls.g)
+    }
+}
+
+character_filter!(Sem);
@@ -0,0 +1,310 @@
+//! Linkwitz-Riley crossovers
+//!
+//! Each split is a pair of squared Butterworth filters (low and high pass), so that the
+//! two bands are -6 dB at the crossover frequency and sum up to an allpass.
+//! With 3 or more bands, every band below a split goes through the allpass of the
+//! splits above it, so that all the bands sum up with the same phase.
+//!
+//! Hosted as a [`Processor`], the bands are mixed back with their own gains (a
+//! multiband gain), the allpass response by default.
+//!
+//! See https://www.rane.com/note160.html
+//! and https://www.linkwitzlab.com/filters.htm
+
+use crate::biquad;
+use crate::cascade::Cascade;
+use crate::design;
+use crate::filter;
+use crate::processor::{self, Processor};
+use crate::sample::FloatOfMax1;
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Order {
+    /// 12 dB/oct, the high band is inverted
+    LR2,
+    /// 24 dB/oct
+    LR4,
+    /// 48 dB/oct
+    LR8,
+}
+
+impl Order {
+    fn butterworth_order(self) -> usize {
+        match self {
+            Order::LR2 => 1,
+            Order::LR4 => 2,
+            Order::LR8 => 4,
+        }
+    }
+}
+
+struct Split {
+    low: Cascade,
+    high: Cascade,
+}
+
+pub struct Crossover {
+    order: Order,
+    frequencies: Vec<f64>,
+    fs: i32,
+    splits: Vec<Split>,
+    /// Per band (except the last): the allpasses of the splits above it
+    compensation: Vec<Cascade>,
+    /// One sample per band, for the block processing
+    buffer: Vec<f64>,
+    /// Of the bands mixed back by [`Processor::process_block`]
+    band_gains: Vec<f64>,
+}
+
+impl Crossover {
+    /**
+     * `frequencies.len() + 1` bands, split at the given (increasing) frequencies
+     *
+     * Panics if there are no frequencies, or they are not increasing within (0, fs/2).
+     */
+    pub fn new(order: Order, frequencies: &[f64], fs: i32) -> Self {
+        assert!(!frequencies.is_empty(), "crossover: no frequencies");
+        assert!(
+            frequencies.windows(2).all(|pair| pair[0] < pair[1]),
+            "crossover: frequencies not increasing {:?}",
+            frequencies
+        );
+        assert!(
+            frequencies[0] > 0.0 && frequencies[frequencies.len() - 1] < fs as f64 / 2.0,
+            "crossover: frequencies out of (0, fs/2) {:?}",
+            frequencies
+        );
+
+        let mut crossover = Self {
+            order,
+            frequencies: frequencies.to_vec(),
+            fs,
+            splits: vec![],
+            compensation: vec![],
+            buffer: vec![0.0; frequencies.len() + 1],
+            band_gains: vec![1.0; frequencies.len() + 1],
+        };
+        crossover.design();
+        crossover
+    }
+
+    /// The splits and their compensation, at the frequencies clamped under fs/2
+    fn design(&mut self) {
+        let (order, fs) = (self.order, self.fs);
+        let frequencies = self.tuned_frequencies();
+        self.splits = frequencies
+            .iter()
+            .map(|fc| {
+                let low = butterworth(order, *fc, filter::Type::LowPass, fs);
+                let high = butterworth(order, *fc, filter::Type::HighPass, fs);
+                let mut high = Cascade::new([high.clone(), high].concat());
+                if order == Order::LR2 {
+                    high.gain = -1.0;
+                }
+                Split {
+                    low: Cascade::new([low.clone(), low].concat()),
+                    high,
+                }
+            })
+            .collect();
+        self.compensation = (0..frequencies.len())
+            .map(|band| {
+                Cascade::new(
+                    frequencies[band + 1..]
+                        .iter()
+                        .flat_map(|fc| allpass(order, *fc, fs))
+                        .collect(),
+                )
+            })
+            .collect();
+    }
+
+    /// Only lowered by a sample rate change, see [`Processor::set_sample_rate`]
+    fn tuned_frequencies(&self) -> Vec<f64> {
+        let max = filter::MAX_RELATIVE_FC * self.fs as f64;
+        self.frequencies.iter().map(|fc| fc.min(max)).collect()
+    }
+
+    pub fn order(&self) -> Order {
+        self.order
+    }
+
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    pub fn bands(&self) -> usize {
+        self.splits.len() + 1
+    }
+
+    /**
+     * The response of the summed bands: the allpasses of all the splits
+     *
+     * Useful to phase align a dry signal with the (summed) crossover output.
+     */
+    pub fn allpass_sections(&self) -> Vec<biquad::Params> {
+        self.tuned_frequencies()
+            .iter()
+            .flat_map(|fc| allpass(self.order, *fc, self.fs))
+            .collect()
+    }
+
+    pub fn band_gain(&self, band: usize) -> f64 {
+        self.band_gains[band]
+    }
+
+    /**
+     * Gain of a band in the mix of [`Processor::process_block`]
+     *
+     * Panics if there is no such band.
+     */
+    pub fn set_band_gain(&mut self, band: usize, gain: f64) {
+        assert!(band < self.bands(), "crossover: no band {}", band);
+        self.band_gains[band] = gain;
+    }
+
+    pub fn reset(&mut self) {
+        for split in self.splits.iter_mut() {
+            split.low.reset();
+            split.high.reset();
+        }
+        for compensation in self.compensation.iter_mut() {
+            compensation.reset();
+        }
+    }
+
+    /**
+     * Splitting one sample, from the lowest to the highest band
+     *
+     * Panics if `bands` is shorter than [`bands()`](Crossover::bands).
+     */
+    pub fn tick(&mut self, x: f64, bands: &mut [f64]) {
+        let mut rest = x;
+        for (band, (split, compensation)) in self
+            .splits
+            .iter_mut()
+            .zip(self.compensation.iter_mut())
+            .enumerate()
+        {
+            bands[band] = compensation.tick(split.low.tick(rest));
+            rest = split.high.tick(rest);
+        }
+        bands[self.splits.len()] = rest;
+    }
+
+    /**
+     * Splitting a block of samples, one output block per band
+     *
+     * Panics if the number of outputs differs from the number of bands,
+     * or the output lengths differ from the input length.
+     */
+    pub fn process_block<T: FloatOfMax1<T>>(&mut self, input: &[T], outputs: &mut [&mut [T]]) {
+        assert_eq!(outputs.len(), self.bands(), "outputs/bands mismatch");
+        for output in outputs.iter() {
+            assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        }
+
+        let mut samples = std::mem::take(&mut self.buffer);
+        for (i, sample) in input.iter().enumerate() {
+            self.tick(sample.to_f64(), &mut samples);
+            for (output, s) in outputs.iter_mut().zip(samples.iter()) {
+                output[i] = sample.from_f64(*s);
+            }
+        }
+        self.buffer = samples;
+    }
+}
+
+impl Processor for Crossover {
+    fn reset(&mut self) {
+        Crossover::reset(self)
+    }
+
+    /**
+     * Redesigned for the new sample rate, the frequencies clamped under fs/2
+     *
+     * Panics if fs <= 0.
+     */
+    fn set_sample_rate(&mut self, fs: i32) {
+        assert!(fs > 0, "crossover: invalid sample rate {}", fs);
+        self.fs = fs;
+        self.design();
+    }
+
+    /**
+     * Mono in, the bands mixed back with their gains out
+     */
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(1, inputs, outputs);
+        let mut bands = std::mem::take(&mut self.buffer);
+        for (x, y) in inputs[0].iter().zip(outputs[0].iter_mut()) {
+            self.tick(*x, &mut bands);
+            *y = bands
+                .iter()
+                .zip(self.band_gains.iter())
+                .map(|(band, gain)| band * gain)
+                .sum();
+        }
+        self.buffer = bands;
+    }
+
+    /**
+     * The longest path: all the splits and compensations in series
+     */
+    fn tail(&self) -> processor::Tail {
+        let splits = self
+            .splits
+            .iter()
+            .flat_map(|split| [&split.low, &split.high]);
+        splits
+            .chain(self.compensation.iter())
+            .fold(processor::Tail::Samples(0), |tail, cascade| {
+                tail.then(cascade.tail())
+            })
+    }
+}
+
+fn butterworth(order: Order, fc: f64, filter_type: filter::Type, fs: i32) -> Vec<biquad::Params> {
+    design::butterworth(
+        order.butterworth_order(),
+        filter::Params {
+            fc,
+            q: 0.0,
+            gain_db: 0.0,
+        },
+        filter_type,
+        fs,
+    )
+}
+
+/**
+ * The sum of the low and high band of a single split
+ *
+ * The Butterworth denominator D(s) with the numerator D(-s),
+ * which in z are the same coefficients in reverse order.
+ */
+fn allpass(order: Order, fc: f64, fs: i32) -> Vec<biquad::Params> {
+    butterworth(order, fc, filter::Type::LowPass, fs)
+        .into_iter()
+        .map(|p| {
+            if p.b2 == 0.0 {
+                // first order section
+                biquad::Params {
+                    a0: p.b1,
+                    a1: 1.0,
+                    a2: 0.0,
+                    b1: p.b1,
+                    b2: 0.0,
+                }
+            } else {
+                biquad::Params {
+                    a0: p.b2,
+                    a1: p.b1,
+                    a2: 1.0,
+                    b1: p.b1,
+                    b2: p.b2,
+                }
+            }
+        })
+        .collect()
+}
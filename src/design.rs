@@ -0,0 +1,776 @@
+//! Higher order filter design
+//!
+//! The designs are returned as second order sections, ready for a [`Cascade`](crate::cascade::Cascade).
+//!
+//! See https://www.earlevel.com/main/2016/09/29/cascading-filters/
+
+use crate::biquad;
+use crate::complex::Complex;
+use crate::filter;
+use std::f64::consts::PI;
+
+pub const MAX_ORDER: usize = 16;
+
+/**
+ * Butterworth low/high pass of the given order (1 to 16)
+ *
+ * Every section shares the cutoff `fc`, with the Q values of [`butterworth_q`].
+ * Odd orders get an extra first order section (at the end).
+ * `fc` is clamped as in [`filter::Params::clamped`]: every section gets the same one.
+ * `q` and `gain_db` of the filter params are not used.
+ *
+ * Panics on an order out of range or a filter type other than low/high pass.
+ */
+pub fn butterworth(
+    order: usize,
+    filter_params: filter::Params,
+    filter_type: filter::Type,
+    fs: i32,
+) -> Vec<biquad::Params> {
+    assert_order("butterworth", order);
+    let first_order: fn(f64, i32) -> biquad::Params = match filter_type {
+        filter::Type::LowPass => first_order_low_pass,
+        filter::Type::HighPass => first_order_high_pass,
+        _ => panic!("butterworth: unsupported filter type {:?}", filter_type),
+    };
+
+    let fc = filter_params.clamped(fs).fc;
+    let mut sections: Vec<biquad::Params> = butterworth_q(order)
+        .into_iter()
+        .map(|q| {
+            biquad::Params::from_audio_filter_params(
+                filter::Params {
+                    fc,
+                    q,
+                    gain_db: 0.0,
+                },
+                filter_type,
+                fs,
+            )
+        })
+        .collect();
+    if order % 2 == 1 {
+        // as the second order sections, a pass through on an invalid fs
+        sections.push(if fs <= 0 {
+            biquad::Params::default()
+        } else {
+            first_order(fc, fs)
+        });
+    }
+
+    sections
+}
+
+/**
+ * Q values of the second order sections of a Butterworth filter, in increasing order
+ *
+ * The poles lie on the unit circle, at angles `(2k + 1)π / 2N` from the imaginary axis.
+ * For N = 4 this gives 0.5412 and 1.3066.
+ */
+pub fn butterworth_q(order: usize) -> Vec<f64> {
+    (0..order / 2)
+        .rev()
+        .map(|k| {
+            let angle = (2 * k + 1) as f64 * PI / (2 * order) as f64;
+            1.0 / (2.0 * angle.sin())
+        })
+        .collect()
+}
+
+/**
+ * First order low pass (bilinear transform), stored in a biquad with a2 = b2 = 0
+ */
+fn first_order_low_pass(fc: f64, fs: i32) -> biquad::Params {
+    let k = (PI * fc / fs as f64).tan();
+    let norm = 1.0 / (1.0 + k);
+
+    let a0 = k * norm;
+    biquad::Params {
+        a0,
+        a1: a0,
+        a2: 0.0,
+        b1: (k - 1.0) * norm,
+        b2: 0.0,
+    }
+}
+
+fn first_order_high_pass(fc: f64, fs: i32) -> biquad::Params {
+    let k = (PI * fc / fs as f64).tan();
+    let norm = 1.0 / (1.0 + k);
+
+    biquad::Params {
+        a0: norm,
+        a1: -norm,
+        a2: 0.0,
+        b1: (k - 1.0) * norm,
+        b2: 0.0,
+    }
+}
+
+/**
+ * Chebyshev type I: equiripple passband, `ripple_db` deep
+ *
+ * `fc` is the passband edge, where the magnitude is -`ripple_db`.
+ * For band pass/notch `fc` is the center and `q` gives the bandwidth (fc / q) between the edges.
+ * Band pass/notch designs have twice the sections of the low/high pass ones.
+ *
+ * Panics on an order out of range, a filter type other than low/high/band pass or notch,
+ * edges out of (0, fs/2) (see [`assert_edges`]) or a `ripple_db` that is not > 0.
+ */
+pub fn chebyshev1(
+    order: usize,
+    ripple_db: f64,
+    filter_params: filter::Params,
+    filter_type: filter::Type,
+    fs: i32,
+) -> Vec<biquad::Params> {
+    assert_order("chebyshev1", order);
+    assert_edges("chebyshev1", filter_params, filter_type, fs);
+    assert_positive_db("chebyshev1", "ripple", ripple_db);
+    let n = order as f64;
+    let eps = (10.0f64.powf(ripple_db / 10.0) - 1.0).sqrt();
+    let mu = (1.0 / eps).asinh() / n;
+
+    let poles: Vec<Complex> = (0..order)
+        .map(|i| {
+            let theta = PI * (2.0 * i as f64 - n + 1.0) / (2.0 * n);
+            -Complex::new(mu, theta).sinh()
+        })
+        .collect();
+    let mut gain = product(poles.iter().map(|p| -*p)).re;
+    // even orders: the DC gain is at the bottom of the passband ripple
+    if order % 2 != 1 {
+        gain /= (1.0 + eps * eps).sqrt();
+    }
+
+    let prototype = Zpk {
+        zeros: vec![],
+        poles,
+        gain,
+    };
+    prototype.into_sections(filter_params, filter_type, fs)
+}
+
+/**
+ * Chebyshev type II (inverse Chebyshev): equiripple stopband, `attenuation_db` down
+ *
+ * `fc` is the stopband edge, where the magnitude first reaches -`attenuation_db`.
+ * Band pass/notch as in [`chebyshev1`], with the edges being the stopband edges.
+ *
+ * Panics as [`chebyshev1`], on an `attenuation_db` that is not > 0.
+ */
+pub fn chebyshev2(
+    order: usize,
+    attenuation_db: f64,
+    filter_params: filter::Params,
+    filter_type: filter::Type,
+    fs: i32,
+) -> Vec<biquad::Params> {
+    assert_order("chebyshev2", order);
+    assert_edges("chebyshev2", filter_params, filter_type, fs);
+    assert_positive_db("chebyshev2", "attenuation", attenuation_db);
+    let n = order as f64;
+    let de = 1.0 / (10.0f64.powf(attenuation_db / 10.0) - 1.0).sqrt();
+    let mu = (1.0 / de).asinh() / n;
+
+    let zeros: Vec<Complex> = (0..order)
+        .map(|i| 2.0 * i as f64 - n + 1.0)
+        // odd orders: the zero at infinity is left out
+        .filter(|m| *m != 0.0)
+        .map(|m| Complex::new(0.0, 1.0 / (m * PI / (2.0 * n)).sin()))
+        .collect();
+    let poles: Vec<Complex> = (0..order)
+        .map(|i| {
+            let p = -Complex::unit(PI * (2.0 * i as f64 - n + 1.0) / (2.0 * n));
+            1.0 / Complex::new(mu.sinh() * p.re, mu.cosh() * p.im)
+        })
+        .collect();
+    let gain = (product(poles.iter().map(|p| -*p)) / product(zeros.iter().map(|z| -*z))).re;
+
+    let prototype = Zpk { zeros, poles, gain };
+    prototype.into_sections(filter_params, filter_type, fs)
+}
+
+/**
+ * Elliptic (Cauer): equiripple passband and stopband
+ *
+ * `fc` is the passband edge, as in [`chebyshev1`].
+ * The transition band is the narrowest possible for the given order and specs.
+ *
+ * Panics as [`chebyshev1`], and on an `attenuation_db` not above `ripple_db`.
+ *
+ * See Orfanidis, "Lecture Notes on Elliptic Filter Design"
+ * http://eceweb1.rutgers.edu/~orfanidi/ece521/notes.pdf
+ */
+pub fn elliptic(
+    order: usize,
+    ripple_db: f64,
+    attenuation_db: f64,
+    filter_params: filter::Params,
+    filter_type: filter::Type,
+    fs: i32,
+) -> Vec<biquad::Params> {
+    assert_order("elliptic", order);
+    assert_edges("elliptic", filter_params, filter_type, fs);
+    assert_positive_db("elliptic", "ripple", ripple_db);
+    assert!(
+        attenuation_db > ripple_db,
+        "elliptic: attenuation {} dB not above the ripple {} dB",
+        attenuation_db,
+        ripple_db
+    );
+    let n = order as f64;
+    let ep = (10.0f64.powf(ripple_db / 10.0) - 1.0).sqrt();
+    let es = (10.0f64.powf(attenuation_db / 10.0) - 1.0).sqrt();
+    let k1 = ep / es;
+    let k = elliptic_degree(order, k1);
+
+    let mut zeros = vec![];
+    let mut poles = vec![];
+    let v0 = (-Complex::I * elliptic::asn(Complex::new(0.0, 1.0 / ep), k1) / n).re;
+    for i in 1..=order / 2 {
+        let u = (2 * i - 1) as f64 / n;
+        let zeta = elliptic::cd(Complex::from(u), k);
+        let zero = Complex::I / (zeta * k);
+        let pole = Complex::I * elliptic::cd(Complex::new(u, -v0), k);
+        zeros.push(zero);
+        zeros.push(zero.conj());
+        poles.push(pole);
+        poles.push(pole.conj());
+    }
+    if order % 2 == 1 {
+        let pole = Complex::I * elliptic::sn(Complex::new(0.0, v0), k);
+        poles.push(Complex::from(pole.re));
+    }
+    let mut gain = (product(poles.iter().map(|p| -*p)) / product(zeros.iter().map(|z| -*z))).norm();
+    // even orders: the DC gain is at the bottom of the passband ripple
+    if order % 2 != 1 {
+        gain /= (1.0 + ep * ep).sqrt();
+    }
+
+    let prototype = Zpk { zeros, poles, gain };
+    prototype.into_sections(filter_params, filter_type, fs)
+}
+
+pub const BESSEL_MAX_ORDER: usize = 10;
+
+/**
+ * How `fc` relates to a Bessel filter
+ */
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum BesselNorm {
+    /// The magnitude asymptotes match the Butterworth of the same order
+    Phase,
+    /// -3 dB at `fc`
+    Magnitude,
+    /// The group delay at DC is about 1 / (2π fc) (exact before the bilinear warping)
+    Delay,
+}
+
+/**
+ * Bessel (Thomson): maximally flat group delay, order 1 to 10
+ *
+ * The poles are the roots of the reverse Bessel polynomial, scaled as described by `norm`.
+ * Band pass/notch as in [`chebyshev1`], although the flat delay only holds for low/high pass.
+ *
+ * Panics on an order out of range, or edges out of (0, fs/2) as [`chebyshev1`].
+ */
+pub fn bessel(
+    order: usize,
+    norm: BesselNorm,
+    filter_params: filter::Params,
+    filter_type: filter::Type,
+    fs: i32,
+) -> Vec<biquad::Params> {
+    assert!(
+        (1..=BESSEL_MAX_ORDER).contains(&order),
+        "bessel: order {} out of range 1..={}",
+        order,
+        BESSEL_MAX_ORDER
+    );
+    assert_edges("bessel", filter_params, filter_type, fs);
+    let coefficients = reverse_bessel_polynomial(order);
+    // the constant term: unity gain at DC
+    let a0 = coefficients[0];
+    let poles = polynomial_roots(&coefficients);
+
+    let scale = match norm {
+        BesselNorm::Delay => 1.0,
+        BesselNorm::Phase => a0.powf(-1.0 / order as f64),
+        BesselNorm::Magnitude => 1.0 / minus_3_db_frequency(&poles, a0),
+    };
+    let poles: Vec<Complex> = poles.iter().map(|p| *p * scale).collect();
+    let gain = product(poles.iter().map(|p| -*p)).re;
+
+    let prototype = Zpk {
+        zeros: vec![],
+        poles,
+        gain,
+    };
+    prototype.into_sections(filter_params, filter_type, fs)
+}
+
+/**
+ * Coefficients (lowest power first) of θn(s) = Σ (2n - k)! / (2^(n - k) k! (n - k)!) s^k
+ */
+fn reverse_bessel_polynomial(order: usize) -> Vec<f64> {
+    let factorial = |n: usize| (1..=n).map(|i| i as f64).product::<f64>();
+    (0..=order)
+        .map(|k| {
+            factorial(2 * order - k)
+                / (2.0f64.powi((order - k) as i32) * factorial(k) * factorial(order - k))
+        })
+        .collect()
+}
+
+/**
+ * Roots of a monic polynomial (coefficients lowest power first), with the Durand-Kerner method
+ */
+pub(crate) fn polynomial_roots(coefficients: &[f64]) -> Vec<Complex> {
+    let order = coefficients.len() - 1;
+    let evaluate = |s: Complex| {
+        coefficients
+            .iter()
+            .rev()
+            .fold(Complex::ZERO, |acc, c| acc * s + *c)
+    };
+    // starting on a circle with the radius of the geometric mean of the roots
+    let radius = coefficients[0].abs().powf(1.0 / order as f64);
+    let mut roots: Vec<Complex> = (0..order)
+        .map(|i| Complex::from_polar(radius, 0.4 + 2.0 * PI * i as f64 / order as f64))
+        .collect();
+    for _ in 0..500 {
+        let mut max_step: f64 = 0.0;
+        for i in 0..order {
+            let denominator = product((0..order).filter(|j| *j != i).map(|j| roots[i] - roots[j]));
+            let step = evaluate(roots[i]) / denominator;
+            roots[i] = roots[i] - step;
+            max_step = max_step.max(step.norm() / radius);
+        }
+        if max_step < 1e-15 {
+            break;
+        }
+    }
+    roots
+}
+
+/**
+ * Frequency (rad/s) where a0 / Π(s - p) falls to -3 dB, by bisection
+ */
+fn minus_3_db_frequency(poles: &[Complex], a0: f64) -> f64 {
+    let magnitude = |w: f64| {
+        let s = Complex::new(0.0, w);
+        a0 / product(poles.iter().map(|p| s - *p)).norm()
+    };
+    let target = 0.5f64.sqrt();
+    let (mut low, mut high) = (0.0, 1.0);
+    while magnitude(high) > target {
+        high *= 2.0;
+    }
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if magnitude(middle) > target {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/**
+ * Selectivity k (passband/stopband edge ratio) of an elliptic filter,
+ * solving the degree equation for the given order and discrimination k1
+ */
+fn elliptic_degree(order: usize, k1: f64) -> f64 {
+    let n = order as f64;
+    let kc1 = (1.0 - k1 * k1).sqrt();
+    let product: f64 = (1..=order / 2)
+        .map(|i| elliptic::sn(Complex::from((2 * i - 1) as f64 / n), kc1).re)
+        .product();
+    let kc = kc1.powi(order as i32) * product.powi(4);
+    (1.0 - kc * kc).sqrt()
+}
+
+/// Jacobi elliptic functions with normalized argument (u in units of the quarter period K)
+mod elliptic {
+    use crate::complex::Complex;
+    use std::f64::consts::PI;
+
+    /// Descending Landen sequence of the modulus
+    fn landen(k: f64) -> Vec<f64> {
+        let mut v = vec![];
+        let mut k = k;
+        while k > 1e-15 && v.len() < 16 {
+            k = (k / (1.0 + (1.0 - k * k).sqrt())).powi(2);
+            v.push(k);
+        }
+        v
+    }
+
+    fn ascend(w: Complex, k: f64) -> Complex {
+        landen(k)
+            .iter()
+            .rev()
+            .fold(w, |w, v| w * (1.0 + v) / (1.0 + w * w * *v))
+    }
+
+    /// cd(uK, k)
+    pub fn cd(u: Complex, k: f64) -> Complex {
+        ascend((u * (PI / 2.0)).cos(), k)
+    }
+
+    /// sn(uK, k)
+    pub fn sn(u: Complex, k: f64) -> Complex {
+        ascend((u * (PI / 2.0)).sin(), k)
+    }
+
+    /// Inverse of sn, normalized: sn(asn(w) K, k) = w
+    pub fn asn(w: Complex, k: f64) -> Complex {
+        let v = landen(k);
+        let mut w = w;
+        let mut previous = k;
+        for vn in v.iter() {
+            w = w / (1.0 + (1.0 - w * w * previous * previous).sqrt()) * 2.0 / (1.0 + vn);
+            previous = *vn;
+        }
+        // asn = 1 - acd
+        Complex::ONE - w.acos() * (2.0 / PI)
+    }
+}
+
+fn assert_order(name: &str, order: usize) {
+    assert!(
+        (1..=MAX_ORDER).contains(&order),
+        "{}: order {} out of range 1..={}",
+        name,
+        order,
+        MAX_ORDER
+    );
+}
+
+/**
+ * Checking the edges of the design are within (0, fs/2)
+ *
+ * fc for low/high pass, the band edges around it for band pass/notch (with a finite q > 0):
+ * past Nyquist the prewarping has no solution.
+ */
+fn assert_edges(name: &str, filter_params: filter::Params, filter_type: filter::Type, fs: i32) {
+    assert!(fs > 0, "{}: invalid sample rate {}", name, fs);
+    let nyquist = fs as f64 / 2.0;
+    let (f_low, f_high) = match filter_type {
+        filter::Type::BandPass | filter::Type::Notch => {
+            let q = filter_params.q;
+            assert!(q > 0.0 && q.is_finite(), "{}: invalid q {}", name, q);
+            band_edges(filter_params)
+        }
+        _ => (filter_params.fc, filter_params.fc),
+    };
+    assert!(
+        f_low > 0.0 && f_high < nyquist,
+        "{}: edges {} to {} Hz out of range (0, {})",
+        name,
+        f_low,
+        f_high,
+        nyquist
+    );
+}
+
+fn assert_positive_db(name: &str, spec: &str, db: f64) {
+    assert!(
+        db > 0.0 && db.is_finite(),
+        "{}: invalid {} {} dB",
+        name,
+        spec,
+        db
+    );
+}
+
+/**
+ * Band edges, geometrically centered: f_high - f_low = fc / q, f_low * f_high = fc^2
+ */
+fn band_edges(filter_params: filter::Params) -> (f64, f64) {
+    let fc = filter_params.fc;
+    let bandwidth = fc / filter_params.q;
+    let f_low = (-bandwidth + (bandwidth * bandwidth + 4.0 * fc * fc).sqrt()) / 2.0;
+    (f_low, f_low + bandwidth)
+}
+
+fn product<I: Iterator<Item = Complex>>(values: I) -> Complex {
+    values.fold(Complex::ONE, |acc, x| acc * x)
+}
+
+/**
+ * Analog prototype (low pass, normalized at 1 rad/s) in zeros, poles and gain form
+ */
+struct Zpk {
+    zeros: Vec<Complex>,
+    poles: Vec<Complex>,
+    gain: f64,
+}
+
+impl Zpk {
+    /**
+     * Frequency transformation, bilinear transform and grouping into second order sections
+     *
+     * The edges are prewarped so that they land exactly at the requested frequencies.
+     */
+    fn into_sections(
+        self,
+        filter_params: filter::Params,
+        filter_type: filter::Type,
+        fs: i32,
+    ) -> Vec<biquad::Params> {
+        // bilinear transform with T = 1: s = 2 (z - 1) / (z + 1)
+        let warp = |f: f64| 2.0 * (PI * f / fs as f64).tan();
+        let fc = filter_params.fc;
+        let (analog, w_reference) = match filter_type {
+            filter::Type::LowPass => (self.low_pass(warp(fc)), 0.0),
+            filter::Type::HighPass => (self.high_pass(warp(fc)), PI),
+            filter::Type::BandPass | filter::Type::Notch => {
+                let (f_low, f_high) = band_edges(filter_params);
+                let (w_low, w_high) = (warp(f_low), warp(f_high));
+                let w0 = (w_low * w_high).sqrt();
+                if filter_type == filter::Type::BandPass {
+                    (self.band_pass(w0, w_high - w_low), 2.0 * (w0 / 2.0).atan())
+                } else {
+                    (self.band_stop(w0, w_high - w_low), 0.0)
+                }
+            }
+            _ => panic!("unsupported filter type {:?}", filter_type),
+        };
+        analog.bilinear().sections(w_reference)
+    }
+
+    fn degree(&self) -> usize {
+        self.poles.len() - self.zeros.len()
+    }
+
+    fn low_pass(self, w0: f64) -> Zpk {
+        let degree = self.degree();
+        Zpk {
+            zeros: self.zeros.iter().map(|z| *z * w0).collect(),
+            poles: self.poles.iter().map(|p| *p * w0).collect(),
+            gain: self.gain * w0.powi(degree as i32),
+        }
+    }
+
+    fn high_pass(self, w0: f64) -> Zpk {
+        let degree = self.degree();
+        let gain = self.gain
+            * (product(self.zeros.iter().map(|z| -*z)) / product(self.poles.iter().map(|p| -*p)))
+                .re;
+        let mut zeros: Vec<Complex> = self.zeros.iter().map(|z| w0 / *z).collect();
+        zeros.extend(vec![Complex::ZERO; degree]);
+        Zpk {
+            zeros,
+            poles: self.poles.iter().map(|p| w0 / *p).collect(),
+            gain,
+        }
+    }
+
+    fn band_pass(self, w0: f64, bandwidth: f64) -> Zpk {
+        let degree = self.degree();
+        // every root r of the prototype gives the two roots of s^2 - r bw s + w0^2
+        let split = |r: &Complex| {
+            let r = *r * (bandwidth / 2.0);
+            let root = (r * r - w0 * w0).sqrt();
+            vec![r + root, r - root]
+        };
+        let mut zeros: Vec<Complex> = self.zeros.iter().flat_map(split).collect();
+        zeros.extend(vec![Complex::ZERO; degree]);
+        Zpk {
+            zeros,
+            poles: self.poles.iter().flat_map(split).collect(),
+            gain: self.gain * bandwidth.powi(degree as i32),
+        }
+    }
+
+    fn band_stop(self, w0: f64, bandwidth: f64) -> Zpk {
+        let degree = self.degree();
+        let gain = self.gain
+            * (product(self.zeros.iter().map(|z| -*z)) / product(self.poles.iter().map(|p| -*p)))
+                .re;
+        let split = |r: &Complex| {
+            let r = (bandwidth / 2.0) / *r;
+            let root = (r * r - w0 * w0).sqrt();
+            vec![r + root, r - root]
+        };
+        let mut zeros: Vec<Complex> = self.zeros.iter().flat_map(split).collect();
+        for _ in 0..degree {
+            zeros.push(Complex::new(0.0, w0));
+            zeros.push(Complex::new(0.0, -w0));
+        }
+        Zpk {
+            zeros,
+            poles: self.poles.iter().flat_map(split).collect(),
+            gain,
+        }
+    }
+
+    /**
+     * s -> z, with the zeros at infinity mapped to z = -1
+     */
+    fn bilinear(self) -> Zpk {
+        let degree = self.degree();
+        let gain = self.gain
+            * (product(self.zeros.iter().map(|z| 2.0 - *z))
+                / product(self.poles.iter().map(|p| 2.0 - *p)))
+            .re;
+        let map = |r: &Complex| (2.0 + *r) / (2.0 - *r);
+        let mut zeros: Vec<Complex> = self.zeros.iter().map(map).collect();
+        zeros.extend(vec![-Complex::ONE; degree]);
+        Zpk {
+            zeros,
+            poles: self.poles.iter().map(map).collect(),
+            gain,
+        }
+    }
+
+    /**
+     * Grouping the (digital) roots into second order sections
+     *
+     * The poles closest to the unit circle get the nearest zeros, and the sections are
+     * ordered by increasing Q. Each section has unity gain at `w_reference`, the overall
+     * gain goes into the first one.
+     */
+    fn sections(self, w_reference: f64) -> Vec<biquad::Params> {
+        let mut zeros = self.zeros;
+        let mut groups = root_groups(&self.poles);
+        // single real pole first, so that the real zeros left are even in count
+        groups.sort_by(|a, b| {
+            (a.len() == 2)
+                .cmp(&(b.len() == 2))
+                .then(distance_to_unit_circle(a[0]).total_cmp(&distance_to_unit_circle(b[0])))
+        });
+
+        let mut sections: Vec<(biquad::Params, f64)> = groups
+            .iter()
+            .map(|poles| {
+                let section_zeros = take_nearest_zeros(&mut zeros, poles[0], poles.len());
+                let params = section_params(&section_zeros, poles);
+                (params, distance_to_unit_circle(poles[0]))
+            })
+            .collect();
+        sections.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let z = Complex::unit(w_reference);
+        let mut gain = self.gain;
+        let mut sections: Vec<biquad::Params> = sections
+            .into_iter()
+            .map(|(mut params, _)| {
+                let magnitude = section_response(&params, z).norm();
+                if magnitude > 1e-12 {
+                    scale_numerator(&mut params, 1.0 / magnitude);
+                    gain *= magnitude;
+                }
+                params
+            })
+            .collect();
+        if let Some(first) = sections.first_mut() {
+            scale_numerator(first, gain);
+        }
+
+        sections
+    }
+}
+
+const ROOT_TOLERANCE: f64 = 1e-10;
+
+/// Conjugate pairs, pairs of real roots and at most one single real root
+fn root_groups(roots: &[Complex]) -> Vec<Vec<Complex>> {
+    let mut reals: Vec<f64> = roots
+        .iter()
+        .filter(|r| r.is_real(ROOT_TOLERANCE))
+        .map(|r| r.re)
+        .collect();
+    reals.sort_by(|a, b| a.total_cmp(b));
+
+    let mut groups: Vec<Vec<Complex>> = roots
+        .iter()
+        .filter(|r| !r.is_real(ROOT_TOLERANCE) && r.im > 0.0)
+        .map(|r| vec![*r, r.conj()])
+        .collect();
+    groups.extend(
+        reals
+            .chunks(2)
+            .map(|pair| pair.iter().map(|r| Complex::from(*r)).collect()),
+    );
+    groups
+}
+
+fn distance_to_unit_circle(root: Complex) -> f64 {
+    (1.0 - root.norm()).abs()
+}
+
+/// Removing (and returning) the `count` zeros nearest to the pole, keeping conjugates together
+fn take_nearest_zeros(zeros: &mut Vec<Complex>, pole: Complex, count: usize) -> Vec<Complex> {
+    let mut taken = vec![];
+    while taken.len() < count && !zeros.is_empty() {
+        let only_real = taken.len() == count - 1;
+        let nearest = zeros
+            .iter()
+            .enumerate()
+            .filter(|(_, z)| !only_real || z.is_real(ROOT_TOLERANCE))
+            .min_by(|(_, a), (_, b)| (**a - pole).norm().total_cmp(&(**b - pole).norm()))
+            .map(|(i, _)| i);
+        let index = match nearest {
+            Some(index) => index,
+            None => break,
+        };
+        let zero = zeros.remove(index);
+        if zero.is_real(ROOT_TOLERANCE) {
+            taken.push(Complex::from(zero.re));
+        } else {
+            let conj = zeros
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    (**a - zero.conj())
+                        .norm()
+                        .total_cmp(&(**b - zero.conj()).norm())
+                })
+                .map(|(i, _)| i)
+                .expect("complex zero without its conjugate");
+            zeros.remove(conj);
+            taken.push(zero);
+            taken.push(zero.conj());
+        }
+    }
+    taken
+}
+
+/// (1 - r1 z^-1)(1 - r2 z^-1) = 1 - (r1 + r2) z^-1 + r1 r2 z^-2
+fn polynomial(roots: &[Complex]) -> (f64, f64) {
+    match roots {
+        [] => (0.0, 0.0),
+        [r] => (-r.re, 0.0),
+        [r1, r2] => (-(*r1 + *r2).re, (*r1 * *r2).re),
+        _ => unreachable!("more than 2 roots in a section"),
+    }
+}
+
+fn section_params(zeros: &[Complex], poles: &[Complex]) -> biquad::Params {
+    let (a1, a2) = polynomial(zeros);
+    let (b1, b2) = polynomial(poles);
+    biquad::Params {
+        a0: 1.0,
+        a1,
+        a2,
+        b1,
+        b2,
+    }
+}
+
+/// H(z) of a single section
+fn section_response(params: &biquad::Params, z: Complex) -> Complex {
+    let z1 = z.inv();
+    let z2 = z1 * z1;
+    (params.a0 + z1 * params.a1 + z2 * params.a2) / (1.0 + z1 * params.b1 + z2 * params.b2)
+}
+
+fn scale_numerator(params: &mut biquad::Params, gain: f64) {
+    params.a0 *= gain;
+    params.a1 *= gain;
+    params.a2 *= gain;
+}
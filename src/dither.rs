@@ -0,0 +1,155 @@
+//! Dither and noise shaping, for the bit depth reductions
+//!
+//! Rounding a float output to 16 bits leaves an error correlated with the signal:
+//! harmonics and, on fade outs, a noise that comes and goes. A dither noise added
+//! before the rounding turns it into a constant noise floor:
+//! - RPDF: uniform noise of ±0.5 LSB, removes the distortion but not the noise modulation
+//! - TPDF: triangular noise of ±1 LSB (the sum of two uniforms), removes both
+//! - high-pass TPDF: the difference of two successive uniforms, the same triangle
+//!   but with its power moved to the high frequencies
+//!
+//! The noise shaping filters the total error (dither plus rounding) with
+//! NTF(z) = 1 - (h1 z^-1 + h2 z^-2 + ...), by subtracting the filtered past errors
+//! from the input: less noise where the ear is sensitive (2 to 5 kHz),
+//! more above 15 kHz. The curves are designed for 44.1 kHz.
+//!
+//! Credits: Wannamaker, Psychoacoustically Optimal Noise Shaping (1992), and
+//! Lipshitz, Vanderkooy, Wannamaker, Minimally Audible Noise Shaping (1991)
+
+/// Taps of the longest shaping filter
+const MAX_TAPS: usize = 9;
+
+/**
+ * Noise added before the rounding
+ */
+#[derive(Clone, Copy, Default, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Dither {
+    /// Plain rounding
+    None,
+    /// Rectangular, ±0.5 LSB
+    Rpdf,
+    /// Triangular, ±1 LSB
+    #[default]
+    Tpdf,
+    /// Triangular, ±1 LSB, with a first order high-pass spectrum
+    HighPassTpdf,
+}
+
+/**
+ * Error feedback filter h, the noise transfer function being 1 - H(z)
+ */
+#[derive(Clone, Copy, Default, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum NoiseShaping {
+    /// White noise
+    #[default]
+    None,
+    /// First order: a zero at DC
+    ErrorFeedback,
+    /// Wannamaker's 3 taps F-weighted curve
+    FWeighted,
+    /// Wannamaker's 9 taps F-weighted curve
+    Wannamaker9,
+}
+
+impl NoiseShaping {
+    /// h1, h2, ...
+    pub fn coefficients(self) -> &'static [f64] {
+        match self {
+            NoiseShaping::None => &[],
+            NoiseShaping::ErrorFeedback => &[1.0],
+            NoiseShaping::FWeighted => &[1.623, -0.982, 0.109],
+            NoiseShaping::Wannamaker9 => &[
+                2.412, -3.370, 3.937, -4.174, 3.353, -2.205, 1.281, -0.569, 0.0847,
+            ],
+        }
+    }
+}
+
+/**
+ * Rounding of samples scaled in LSB, with dither and noise shaping
+ */
+#[derive(Clone, std::fmt::Debug)]
+pub struct Ditherer {
+    dither: Dither,
+    shaping: NoiseShaping,
+    /// xorshift32 state
+    state: u32,
+    /// Last uniform noise, for the high-pass TPDF
+    previous: f64,
+    /// Past errors, the latest first
+    errors: [f64; MAX_TAPS],
+}
+
+impl Ditherer {
+    /**
+     * Same seed, same noise (0 is replaced by 1)
+     */
+    pub fn new(dither: Dither, shaping: NoiseShaping, seed: u32) -> Self {
+        Self {
+            dither,
+            shaping,
+            state: seed.max(1),
+            previous: 0.0,
+            errors: [0.0; MAX_TAPS],
+        }
+    }
+
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    pub fn shaping(&self) -> NoiseShaping {
+        self.shaping
+    }
+
+    /**
+     * Clearing the past errors (the noise goes on)
+     */
+    pub fn reset(&mut self) {
+        self.previous = 0.0;
+        self.errors = [0.0; MAX_TAPS];
+    }
+
+    /**
+     * x in LSB, rounded to the nearest integer after the dither and the shaping
+     *
+     * The result is not saturated: the error fed back stays within a few LSB
+     * even when the caller clips it.
+     */
+    pub fn quantize(&mut self, x: f64) -> f64 {
+        let feedback: f64 = self
+            .shaping
+            .coefficients()
+            .iter()
+            .zip(self.errors.iter())
+            .map(|(h, e)| h * e)
+            .sum();
+        let wanted = x - feedback;
+        let y = (wanted + self.noise()).round();
+        self.errors.copy_within(0..MAX_TAPS - 1, 1);
+        self.errors[0] = y - wanted;
+        y
+    }
+
+    fn noise(&mut self) -> f64 {
+        match self.dither {
+            Dither::None => 0.0,
+            Dither::Rpdf => self.uniform() - 0.5,
+            Dither::Tpdf => self.uniform() + self.uniform() - 1.0,
+            Dither::HighPassTpdf => {
+                let current = self.uniform() - 0.5;
+                let noise = current - self.previous;
+                self.previous = current;
+                noise
+            }
+        }
+    }
+
+    /// From 0 to 1
+    fn uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f64 / u32::MAX as f64
+    }
+}
@@ -0,0 +1,165 @@
+//! Errors of the validated constructors and builders
+
+use std::fmt;
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum FilterError {
+    /// A coefficient is NaN or infinite
+    NonFiniteCoefficient,
+    /// A pole on or outside of the unit circle, with its magnitude
+    Unstable { pole_magnitude: f64 },
+    /// fs <= 0
+    InvalidSampleRate { fs: i32 },
+    /// fc <= 0 or NaN
+    InvalidFrequency { fc: f64 },
+    /// fc >= fs/2
+    FrequencyAboveNyquist { fc: f64, nyquist: f64 },
+    /// q <= 0, infinite or NaN
+    InvalidQ { q: f64 },
+    /// Infinite or NaN gain
+    NonFiniteGain { gain_db: f64 },
+    /// A coefficient out of the fixed point range, with the given headroom shift
+    CoefficientOverflow { coefficient: f64, shift: u32 },
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterError::NonFiniteCoefficient => write!(f, "non finite coefficient"),
+            FilterError::Unstable { pole_magnitude } => write!(
+                f,
+                "unstable filter: pole of magnitude {} (must be < 1)",
+                pole_magnitude
+            ),
+            FilterError::InvalidSampleRate { fs } => {
+                write!(f, "invalid sample rate {} (must be > 0)", fs)
+            }
+            FilterError::InvalidFrequency { fc } => {
+                write!(f, "invalid frequency {} (must be > 0)", fc)
+            }
+            FilterError::FrequencyAboveNyquist { fc, nyquist } => {
+                write!(f, "frequency {} above Nyquist (must be < {})", fc, nyquist)
+            }
+            FilterError::InvalidQ { q } => write!(f, "invalid q {} (must be > 0)", q),
+            FilterError::NonFiniteGain { gain_db } => write!(f, "non finite gain {} dB", gain_db),
+            FilterError::CoefficientOverflow { coefficient, shift } => write!(
+                f,
+                "coefficient {} out of the fixed point range with a headroom shift of {}",
+                coefficient, shift
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum GraphError {
+    /// No channel, or more than [`graph::MAX_CHANNELS`](crate::graph::MAX_CHANNELS)
+    InvalidChannelCount { channels: usize },
+    /// A max block size of 0
+    InvalidBlockSize,
+    /// A node whose processor doesn't have the graph's channel count
+    ChannelMismatch {
+        node: usize,
+        channels: usize,
+        expected: usize,
+    },
+    /// A node id of another graph
+    UnknownNode { node: usize },
+    /// An edge into the graph input, or out of the graph output
+    InvalidEdge,
+    /// A feedback edge without delay
+    ZeroDelayFeedback,
+    /// A loop of edges without delay, through the given node
+    Cycle { node: usize },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::InvalidChannelCount { channels } => {
+                write!(f, "invalid channel count {}", channels)
+            }
+            GraphError::InvalidBlockSize => write!(f, "invalid max block size 0"),
+            GraphError::ChannelMismatch {
+                node,
+                channels,
+                expected,
+            } => write!(
+                f,
+                "node {} has {} channels (expected {})",
+                node, channels, expected
+            ),
+            GraphError::UnknownNode { node } => write!(f, "unknown node {}", node),
+            GraphError::InvalidEdge => {
+                write!(f, "invalid edge: into the input or out of the output")
+            }
+            GraphError::ZeroDelayFeedback => write!(f, "feedback edge without delay"),
+            GraphError::Cycle { node } => {
+                write!(f, "cycle without delay through node {}", node)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+/**
+ * Errors of the [`wav`](crate::wav) module
+ */
+#[cfg(feature = "wav")]
+#[derive(std::fmt::Debug)]
+pub enum WavError {
+    /// Reading, writing or parsing the file
+    Hound(hound::Error),
+    /// Other than 8, 16, 24 or 32 bits integers and 32 bits floats
+    UnsupportedFormat { bits: u16, float: bool },
+    /// No channel, or more than 65535
+    InvalidChannelCount { channels: usize },
+    /// fs <= 0
+    InvalidSampleRate { fs: i32 },
+    /// Buffers or a processor not matching the file's channel count
+    ChannelMismatch { channels: usize, expected: usize },
+}
+
+#[cfg(feature = "wav")]
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WavError::Hound(error) => write!(f, "wav: {}", error),
+            WavError::UnsupportedFormat { bits, float } => write!(
+                f,
+                "unsupported {} bits {} samples",
+                bits,
+                if *float { "float" } else { "integer" }
+            ),
+            WavError::InvalidChannelCount { channels } => {
+                write!(f, "invalid channel count {}", channels)
+            }
+            WavError::InvalidSampleRate { fs } => {
+                write!(f, "invalid sample rate {} (must be > 0)", fs)
+            }
+            WavError::ChannelMismatch { channels, expected } => {
+                write!(f, "{} channels (expected {})", channels, expected)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wav")]
+impl std::error::Error for WavError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WavError::Hound(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "wav")]
+impl From<hound::Error> for WavError {
+    fn from(error: hound::Error) -> Self {
+        WavError::Hound(error)
+    }
+}
@@ -1,9 +1,129 @@
+use crate::error::FilterError;
+use crate::processor::Processor;
+use std::f64::consts::FRAC_1_SQRT_2;
+
+/// Lowest cut off frequency of the clamping, in Hz
+pub const MIN_FC: f64 = 0.01;
+/// Highest cut off frequency of the clamping, relative to fs
+pub const MAX_RELATIVE_FC: f64 = 0.499;
+/// Lowest resonance of the clamping
+pub const MIN_Q: f64 = 0.01;
+/// Highest resonance of the clamping: an infinite q puts the poles on the unit circle
+pub const MAX_Q: f64 = 1000.0;
+/// Highest absolute gain of the clamping
+pub const MAX_GAIN_DB: f64 = 120.0;
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
 pub struct Params {
     pub fc: f64, // frequency cut off
     pub q: f64, // resonance
     pub gain_db: f64, // peak gain (at fc)
 }
 
+impl Params {
+    /**
+     * Validated params, see [`Builder`]
+     */
+    pub fn builder(fc: f64) -> Builder {
+        Builder {
+            params: Params {
+                fc,
+                q: FRAC_1_SQRT_2,
+                gain_db: 0.0,
+            },
+        }
+    }
+
+    /**
+     * Checking the params can design a filter at the sample rate fs
+     *
+     * fs > 0, 0 < fc < fs/2, a finite q > 0 and a finite gain.
+     */
+    pub fn validate(&self, fs: i32) -> Result<(), FilterError> {
+        if fs <= 0 {
+            return Err(FilterError::InvalidSampleRate { fs });
+        }
+        if self.fc.is_nan() || self.fc <= 0.0 {
+            return Err(FilterError::InvalidFrequency { fc: self.fc });
+        }
+        let nyquist = fs as f64 / 2.0;
+        if self.fc >= nyquist {
+            return Err(FilterError::FrequencyAboveNyquist {
+                fc: self.fc,
+                nyquist,
+            });
+        }
+        if self.q.is_nan() || self.q <= 0.0 || self.q.is_infinite() {
+            return Err(FilterError::InvalidQ { q: self.q });
+        }
+        if !self.gain_db.is_finite() {
+            return Err(FilterError::NonFiniteGain {
+                gain_db: self.gain_db,
+            });
+        }
+        Ok(())
+    }
+
+    /**
+     * The closest valid params at the sample rate fs (> 0)
+     *
+     * - fc clamped to [MIN_FC, MAX_RELATIVE_FC * fs], a NaN fc becomes the max
+     * - q clamped to [MIN_Q, MAX_Q], a NaN q becomes 1/sqrt(2)
+     * - gain clamped to [-MAX_GAIN_DB, MAX_GAIN_DB], a NaN gain becomes 0 dB
+     */
+    pub fn clamped(&self, fs: i32) -> Params {
+        let max_fc = (MAX_RELATIVE_FC * fs as f64).max(MIN_FC);
+        Params {
+            fc: if self.fc.is_nan() {
+                max_fc
+            } else {
+                self.fc.clamp(MIN_FC, max_fc)
+            },
+            q: if self.q.is_nan() {
+                FRAC_1_SQRT_2
+            } else {
+                self.q.clamp(MIN_Q, MAX_Q)
+            },
+            gain_db: if self.gain_db.is_nan() {
+                0.0
+            } else {
+                self.gain_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB)
+            },
+        }
+    }
+}
+
+/**
+ * Fallible builder of [`Params`]
+ *
+ * Defaults to a Butterworth resonance (q = 1/sqrt(2)) and no gain.
+ */
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct Builder {
+    params: Params,
+}
+
+impl Builder {
+    pub fn q(mut self, q: f64) -> Self {
+        self.params.q = q;
+        self
+    }
+
+    pub fn gain_db(mut self, gain_db: f64) -> Self {
+        self.params.gain_db = gain_db;
+        self
+    }
+
+    /**
+     * The params, if valid at the sample rate fs (see [`Params::validate`])
+     */
+    pub fn build(self, fs: i32) -> Result<Params, FilterError> {
+        self.params.validate(fs)?;
+        Ok(self.params)
+    }
+}
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
 pub enum Type {
     LowPass,
     HighPass,
@@ -12,4 +132,21 @@ pub enum Type {
     Peak,
     LowShelf,
     HighShelf,
+}
+
+/**
+ * Processing samples one at a time, to swap filter implementations
+ *
+ * Object safe: different filters can be A/B tested behind a `Box<dyn Filter>`.
+ * A filter is a mono [`Processor`], which also clears its memory with
+ * [`reset`](Processor::reset).
+ */
+pub trait Filter: Processor {
+    fn tick(&mut self, x: f64) -> f64;
+
+    fn tick_block(&mut self, samples: &mut [f64]) {
+        for sample in samples.iter_mut() {
+            *sample = self.tick(*sample);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,279 @@
+//! Fixed point biquads: integer samples, integer coefficients
+//!
+//! Direct form I on Q15 (i16) or Q31 (i32) samples, without going through floats.
+//! The coefficients are quantized to the width of the samples. They can be over 1
+//! (b1 is close to -2 for low cut offs), so they are stored with a headroom shift s:
+//! Q15 coefficients with a shift of 1 are Q14, from -2 to 2.
+//!
+//! The products are summed in a native 64 bit accumulator (no 128 bit arithmetic to
+//! emulate on small targets): exact for Q15, the Q31 products dropping their 2 lowest
+//! bits for the headroom of the sum. Then the sum is requantized to the sample format.
+//!
+//! With error feedback, the truncation error of the previous output is added back
+//! to the sum: the quantization noise gets a zero at DC (first order noise shaping)
+//! and the low frequency limit cycles go away. Without it, the sum is rounded.
+//! The outputs saturate instead of wrapping.
+//!
+//! Credits: https://www.earlevel.com/main/2003/02/28/biquads/ and
+//! Dattorro, The Implementation of Recursive Digital Filters for High-Fidelity Audio
+
+use crate::biquad;
+use crate::error::FilterError;
+use crate::processor::{self, Processor};
+use std::ops::{Add, Shl, Shr, Sub};
+
+/**
+ * Integer samples in a Q format: the value is the integer divided by 2^FRACTIONAL_BITS
+ *
+ * The products of the biquad are summed in a native [`Accumulator`](FixedPoint::Accumulator).
+ */
+pub trait FixedPoint: Copy + Default {
+    /// 15 for Q15, 31 for Q31
+    const FRACTIONAL_BITS: u32;
+    /// Bits dropped from each product, so that 5 of them fit in the accumulator
+    const PRODUCT_SHIFT: u32;
+    type Accumulator: Copy
+        + Default
+        + From<i32>
+        + Add<Output = Self::Accumulator>
+        + Sub<Output = Self::Accumulator>
+        + Shl<u32, Output = Self::Accumulator>
+        + Shr<u32, Output = Self::Accumulator>;
+
+    /// The full product, shifted right by PRODUCT_SHIFT
+    fn multiply(self, other: Self) -> Self::Accumulator;
+    /// Saturating at the bounds of the type
+    fn from_accumulator(x: Self::Accumulator) -> Self;
+    /// Rounded, saturating at the bounds of the type (NaN is 0)
+    fn from_f64_saturating(x: f64) -> Self;
+    fn into_f64(self) -> f64;
+}
+
+impl FixedPoint for i16 {
+    const FRACTIONAL_BITS: u32 = 15;
+    /// 5 products of 30 bits: 33 bits
+    const PRODUCT_SHIFT: u32 = 0;
+    type Accumulator = i64;
+
+    fn multiply(self, other: i16) -> i64 {
+        self as i64 * other as i64
+    }
+
+    fn from_accumulator(x: i64) -> i16 {
+        x.clamp(i16::MIN as i64, i16::MAX as i64) as i16
+    }
+
+    fn from_f64_saturating(x: f64) -> i16 {
+        x.round() as i16
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl FixedPoint for i32 {
+    const FRACTIONAL_BITS: u32 = 31;
+    /// 5 products of 62 bits: 63 bits once shifted by 2
+    const PRODUCT_SHIFT: u32 = 2;
+    type Accumulator = i64;
+
+    fn multiply(self, other: i32) -> i64 {
+        (self as i64 * other as i64) >> Self::PRODUCT_SHIFT
+    }
+
+    fn from_accumulator(x: i64) -> i32 {
+        x.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    fn from_f64_saturating(x: f64) -> i32 {
+        x.round() as i32
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+/**
+ * Direct form I, Q15 for i16 and Q31 for i32 samples
+ */
+pub struct Process<T: FixedPoint> {
+    /// a0, a1, a2, b1, b2 in Q(FRACTIONAL_BITS - shift)
+    coefficients: [T; 5],
+    shift: u32,
+    error_feedback: bool,
+    // past samples
+    x1: T,
+    x2: T,
+    y1: T,
+    y2: T,
+    /// Truncation error of the last output, in the accumulator format
+    error: T::Accumulator,
+}
+
+impl<T: FixedPoint> Process<T> {
+    /**
+     * With the smallest headroom shift fitting the coefficients, and error feedback
+     */
+    pub fn new(params: biquad::Params) -> Result<Self, FilterError> {
+        let max_shift = T::FRACTIONAL_BITS - T::PRODUCT_SHIFT - 1;
+        let shift = (0..=max_shift)
+            .find(|shift| quantize::<T>(&params, *shift).is_ok())
+            .unwrap_or(max_shift);
+        Self::with_shift(params, shift)
+    }
+
+    /**
+     * Fails if a coefficient is not finite or out of ±2^shift
+     *
+     * Panics if the shift leaves no fractional bit to the (shifted) products.
+     */
+    pub fn with_shift(params: biquad::Params, shift: u32) -> Result<Self, FilterError> {
+        assert!(
+            shift + T::PRODUCT_SHIFT < T::FRACTIONAL_BITS,
+            "fixed: headroom shift {} of Q{}",
+            shift,
+            T::FRACTIONAL_BITS
+        );
+        Ok(Self {
+            coefficients: quantize::<T>(&params, shift)?,
+            shift,
+            error_feedback: true,
+            x1: T::default(),
+            x2: T::default(),
+            y1: T::default(),
+            y2: T::default(),
+            error: T::Accumulator::default(),
+        })
+    }
+
+    pub fn shift(&self) -> u32 {
+        self.shift
+    }
+
+    pub fn error_feedback(&self) -> bool {
+        self.error_feedback
+    }
+
+    /**
+     * Error feedback (the default), or rounding of the outputs
+     */
+    pub fn set_error_feedback(&mut self, error_feedback: bool) {
+        self.error_feedback = error_feedback;
+        self.error = T::Accumulator::default();
+    }
+
+    /**
+     * The coefficients actually used, back in floating point
+     */
+    pub fn quantized_params(&self) -> biquad::Params {
+        let scale = (1u64 << (T::FRACTIONAL_BITS - self.shift)) as f64;
+        let [a0, a1, a2, b1, b2] = self.coefficients.map(|c| c.into_f64() / scale);
+        biquad::Params { a0, a1, a2, b1, b2 }
+    }
+
+    /**
+     * Clearing the filter memory
+     */
+    pub fn reset(&mut self) {
+        self.x1 = T::default();
+        self.x2 = T::default();
+        self.y1 = T::default();
+        self.y2 = T::default();
+        self.error = T::Accumulator::default();
+    }
+
+    pub fn tick(&mut self, x: T) -> T {
+        let [a0, a1, a2, b1, b2] = self.coefficients;
+        let accumulator = a0.multiply(x) + a1.multiply(self.x1) + a2.multiply(self.x2)
+            - b1.multiply(self.y1)
+            - b2.multiply(self.y2);
+
+        // back from Q(2 F - shift - PRODUCT_SHIFT) to QF
+        let bits = T::FRACTIONAL_BITS - self.shift - T::PRODUCT_SHIFT;
+        let out = if self.error_feedback {
+            let accumulator = accumulator + self.error;
+            let out = accumulator >> bits;
+            self.error = accumulator - (out << bits);
+            out
+        } else {
+            (accumulator + (T::Accumulator::from(1) << (bits - 1))) >> bits
+        };
+        let out = T::from_accumulator(out);
+
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = out;
+
+        out
+    }
+
+    /**
+     * Processing a block of samples
+     *
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.tick(*x);
+        }
+    }
+
+    pub fn process_in_place(&mut self, samples: &mut [T]) {
+        for sample in samples.iter_mut() {
+            *sample = self.tick(*sample);
+        }
+    }
+}
+
+/**
+ * Hosting on f64 buffers: the samples are converted to QF (rounded, saturated) and back
+ */
+impl<T: FixedPoint> Processor for Process<T> {
+    fn reset(&mut self) {
+        Process::reset(self)
+    }
+
+    /**
+     * Quantized raw coefficients: they are kept
+     */
+    fn set_sample_rate(&mut self, _fs: i32) {}
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(1, inputs, outputs);
+        let scale = (1u64 << T::FRACTIONAL_BITS) as f64;
+        for (x, y) in inputs[0].iter().zip(outputs[0].iter_mut()) {
+            let out = self.tick(T::from_f64_saturating(x * scale));
+            *y = out.into_f64() / scale;
+        }
+    }
+
+    fn tail(&self) -> processor::Tail {
+        processor::decay_tail(self.quantized_params().pole_magnitude())
+    }
+}
+
+/// a0, a1, a2, b1, b2 rounded to Q(F - shift), within the range of T
+fn quantize<T: FixedPoint>(params: &biquad::Params, shift: u32) -> Result<[T; 5], FilterError> {
+    let scale = (1u64 << (T::FRACTIONAL_BITS - shift)) as f64;
+    let mut coefficients = [T::default(); 5];
+    let floats = [params.a0, params.a1, params.a2, params.b1, params.b2];
+    for (quantized, coefficient) in coefficients.iter_mut().zip(floats.iter()) {
+        if !coefficient.is_finite() {
+            return Err(FilterError::NonFiniteCoefficient);
+        }
+        let q = (coefficient * scale).round();
+        let quantized_q = T::from_f64_saturating(q);
+        if quantized_q.into_f64() != q {
+            return Err(FilterError::CoefficientOverflow {
+                coefficient: *coefficient,
+                shift,
+            });
+        }
+        *quantized = quantized_q;
+    }
+    Ok(coefficients)
+}
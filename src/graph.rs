@@ -0,0 +1,534 @@
+//! Processing graphs, built at runtime
+//!
+//! The nodes are [`Processor`]s, all with the channel count of the graph.
+//! The edges carry a gain, from the graph input or a node to a node or the graph output,
+//! and the edges into the same node (or into the output) are summed. That's enough for:
+//! - serial chains, see [`Graph::serial`]
+//! - parallel splits mixed back together, see [`Graph::parallel`]
+//! - dry/wet, see [`Graph::dry_wet`]
+//! - feedback, through edges with an explicit delay of at least one sample
+//!
+//! The nodes run in the topological order of the edges without delay.
+//! All the buffers are allocated by [`Builder::build`]: processing doesn't allocate.
+//! Blocks longer than the max block size, or than the shortest feedback delay,
+//! are processed in sub-blocks.
+//!
+//! A graph is a processor itself: graphs nest.
+
+use crate::error::GraphError;
+use crate::processor::{self, Processor, Tail};
+
+/// Most channels of a graph (the per channel slices given to the nodes live on the stack)
+pub const MAX_CHANNELS: usize = 8;
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct NodeId(usize);
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct EdgeId(usize);
+
+/// Dry edge of the graphs built by [`Graph::dry_wet`]
+pub const DRY_EDGE: EdgeId = EdgeId(0);
+/// Wet edge of the graphs built by [`Graph::dry_wet`]
+pub const WET_EDGE: EdgeId = EdgeId(1);
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Endpoint {
+    Input,
+    Node(NodeId),
+    Output,
+}
+
+#[derive(Clone, Copy)]
+struct Edge {
+    from: Endpoint,
+    to: Endpoint,
+    gain: f64,
+    /// None for the direct edges
+    delay: Option<usize>,
+}
+
+pub struct Builder {
+    channels: usize,
+    processors: Vec<Box<dyn Processor>>,
+    edges: Vec<Edge>,
+}
+
+impl Builder {
+    pub fn add(&mut self, processor: Box<dyn Processor>) -> NodeId {
+        self.processors.push(processor);
+        NodeId(self.processors.len() - 1)
+    }
+
+    /**
+     * Direct edge: the samples of `from` go into `to` in the same block
+     */
+    pub fn connect(&mut self, from: Endpoint, to: Endpoint, gain: f64) -> EdgeId {
+        self.push_edge(Edge {
+            from,
+            to,
+            gain,
+            delay: None,
+        })
+    }
+
+    /**
+     * Delayed edge, by at least one sample: can close a loop
+     */
+    pub fn feedback(&mut self, from: NodeId, to: NodeId, gain: f64, delay: usize) -> EdgeId {
+        self.push_edge(Edge {
+            from: Endpoint::Node(from),
+            to: Endpoint::Node(to),
+            gain,
+            delay: Some(delay),
+        })
+    }
+
+    fn push_edge(&mut self, edge: Edge) -> EdgeId {
+        self.edges.push(edge);
+        EdgeId(self.edges.len() - 1)
+    }
+
+    /**
+     * Validating the graph, sorting the nodes and allocating the buffers
+     */
+    pub fn build(self, max_block_size: usize) -> Result<Graph, GraphError> {
+        let channels = self.channels;
+        if channels == 0 || channels > MAX_CHANNELS {
+            return Err(GraphError::InvalidChannelCount { channels });
+        }
+        if max_block_size == 0 {
+            return Err(GraphError::InvalidBlockSize);
+        }
+        for (node, processor) in self.processors.iter().enumerate() {
+            if processor.channels() != channels {
+                return Err(GraphError::ChannelMismatch {
+                    node,
+                    channels: processor.channels(),
+                    expected: channels,
+                });
+            }
+        }
+        for edge in self.edges.iter() {
+            self.validate_edge(edge)?;
+        }
+        let order = self.topological_order()?;
+
+        let buffers = |length| vec![vec![0.0; length]; channels];
+        let mut nodes: Vec<Node> = self
+            .processors
+            .into_iter()
+            .map(|processor| Node {
+                processor,
+                inputs: vec![],
+                feedback: vec![],
+                output: buffers(max_block_size),
+            })
+            .collect();
+        let mut outputs = vec![];
+        let mut delay_lines = vec![];
+        for (index, edge) in self.edges.iter().enumerate() {
+            let source = match edge.from {
+                Endpoint::Node(NodeId(node)) => Source::Node(node),
+                _ => Source::Input,
+            };
+            match (edge.to, edge.delay) {
+                (Endpoint::Node(NodeId(node)), Some(delay)) => {
+                    nodes[node].feedback.push(delay_lines.len());
+                    delay_lines.push(DelayLine {
+                        from: match source {
+                            Source::Node(from) => from,
+                            Source::Input => unreachable!(),
+                        },
+                        edge: index,
+                        buffer: buffers(delay),
+                        position: 0,
+                    });
+                }
+                (Endpoint::Node(NodeId(node)), None) => nodes[node].inputs.push((source, index)),
+                _ => outputs.push((source, index)),
+            }
+        }
+        let shortest_delay = self.edges.iter().filter_map(|edge| edge.delay).min();
+
+        Ok(Graph {
+            channels,
+            sub_block_size: shortest_delay
+                .map_or(max_block_size, |delay| delay.min(max_block_size)),
+            gains: self.edges.iter().map(|edge| edge.gain).collect(),
+            nodes,
+            order,
+            outputs,
+            delay_lines,
+            scratch: buffers(max_block_size),
+        })
+    }
+
+    fn validate_edge(&self, edge: &Edge) -> Result<(), GraphError> {
+        if edge.from == Endpoint::Output || edge.to == Endpoint::Input {
+            return Err(GraphError::InvalidEdge);
+        }
+        for endpoint in [edge.from, edge.to].iter() {
+            if let Endpoint::Node(NodeId(node)) = *endpoint {
+                if node >= self.processors.len() {
+                    return Err(GraphError::UnknownNode { node });
+                }
+            }
+        }
+        if edge.delay == Some(0) {
+            return Err(GraphError::ZeroDelayFeedback);
+        }
+        Ok(())
+    }
+
+    /// Kahn's algorithm over the direct edges between nodes
+    fn topological_order(&self) -> Result<Vec<usize>, GraphError> {
+        let count = self.processors.len();
+        let mut in_degrees = vec![0; count];
+        let mut successors = vec![vec![]; count];
+        for edge in self.edges.iter().filter(|edge| edge.delay.is_none()) {
+            if let (Endpoint::Node(NodeId(from)), Endpoint::Node(NodeId(to))) = (edge.from, edge.to)
+            {
+                in_degrees[to] += 1;
+                successors[from].push(to);
+            }
+        }
+        let mut order = Vec::with_capacity(count);
+        let mut ready: Vec<usize> = (0..count)
+            .rev()
+            .filter(|node| in_degrees[*node] == 0)
+            .collect();
+        while let Some(node) = ready.pop() {
+            order.push(node);
+            for successor in successors[node].iter() {
+                in_degrees[*successor] -= 1;
+                if in_degrees[*successor] == 0 {
+                    ready.push(*successor);
+                }
+            }
+        }
+        match in_degrees.iter().position(|degree| *degree > 0) {
+            Some(node) => Err(GraphError::Cycle { node }),
+            None => Ok(order),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Source {
+    Input,
+    Node(usize),
+}
+
+struct Node {
+    processor: Box<dyn Processor>,
+    /// Direct edges into the node: (source, edge index)
+    inputs: Vec<(Source, usize)>,
+    /// Indices of the delay lines into the node
+    feedback: Vec<usize>,
+    output: Vec<Vec<f64>>,
+}
+
+struct DelayLine {
+    from: usize,
+    edge: usize,
+    /// Per channel circular buffer, of the length of the delay
+    buffer: Vec<Vec<f64>>,
+    position: usize,
+}
+
+pub struct Graph {
+    channels: usize,
+    /// Processing granularity: at most the max block size and the shortest delay
+    sub_block_size: usize,
+    gains: Vec<f64>,
+    nodes: Vec<Node>,
+    /// Node indices, sources before destinations
+    order: Vec<usize>,
+    /// Direct edges into the graph output
+    outputs: Vec<(Source, usize)>,
+    delay_lines: Vec<DelayLine>,
+    /// Summed inputs of the node being processed
+    scratch: Vec<Vec<f64>>,
+}
+
+impl Graph {
+    /**
+     * Graph of the given channel count, built by adding nodes and edges
+     */
+    pub fn builder(channels: usize) -> Builder {
+        Builder {
+            channels,
+            processors: vec![],
+            edges: vec![],
+        }
+    }
+
+    /**
+     * Processors one after the other (a passthrough if there is none)
+     */
+    pub fn serial(
+        processors: Vec<Box<dyn Processor>>,
+        channels: usize,
+        max_block_size: usize,
+    ) -> Result<Graph, GraphError> {
+        let mut builder = Graph::builder(channels);
+        let mut previous = Endpoint::Input;
+        for processor in processors {
+            let node = Endpoint::Node(builder.add(processor));
+            builder.connect(previous, node, 1.0);
+            previous = node;
+        }
+        builder.connect(previous, Endpoint::Output, 1.0);
+        builder.build(max_block_size)
+    }
+
+    /**
+     * The input split into the processors, their outputs mixed with the given gains
+     */
+    pub fn parallel(
+        branches: Vec<(Box<dyn Processor>, f64)>,
+        channels: usize,
+        max_block_size: usize,
+    ) -> Result<Graph, GraphError> {
+        let mut builder = Graph::builder(channels);
+        for (processor, gain) in branches {
+            let node = Endpoint::Node(builder.add(processor));
+            builder.connect(Endpoint::Input, node, 1.0);
+            builder.connect(node, Endpoint::Output, gain);
+        }
+        builder.build(max_block_size)
+    }
+
+    /**
+     * (1 - wet) of the input plus wet of the processed input
+     *
+     * The gains can change with [`set_gain`](Graph::set_gain) of [`DRY_EDGE`] and [`WET_EDGE`].
+     * The dry signal is not delayed by the latency of the processor.
+     */
+    pub fn dry_wet(
+        processor: Box<dyn Processor>,
+        wet: f64,
+        channels: usize,
+        max_block_size: usize,
+    ) -> Result<Graph, GraphError> {
+        let mut builder = Graph::builder(channels);
+        builder.connect(Endpoint::Input, Endpoint::Output, 1.0 - wet);
+        let node = Endpoint::Node(builder.add(processor));
+        builder.connect(node, Endpoint::Output, wet);
+        builder.connect(Endpoint::Input, node, 1.0);
+        builder.build(max_block_size)
+    }
+
+    pub fn gain(&self, edge: EdgeId) -> f64 {
+        self.gains[edge.0]
+    }
+
+    /**
+     * Changing the gain of an edge, from the next block on
+     *
+     * Panics if the edge is not of this graph.
+     */
+    pub fn set_gain(&mut self, edge: EdgeId, gain: f64) {
+        self.gains[edge.0] = gain;
+    }
+
+    fn process_sub_block(
+        &mut self,
+        inputs: &[&[f64]],
+        outputs: &mut [&mut [f64]],
+        start: usize,
+        length: usize,
+    ) {
+        let channels = self.channels;
+        for &index in self.order.iter() {
+            let node = &self.nodes[index];
+            for (channel, scratch) in self.scratch.iter_mut().enumerate() {
+                let scratch = &mut scratch[..length];
+                mix(
+                    scratch,
+                    channel,
+                    &node.inputs,
+                    &self.gains,
+                    inputs,
+                    start,
+                    &self.nodes,
+                );
+                for &line in node.feedback.iter() {
+                    self.delay_lines[line].read(
+                        scratch,
+                        channel,
+                        self.gains[self.delay_lines[line].edge],
+                    );
+                }
+            }
+
+            let node = &mut self.nodes[index];
+            let mut node_inputs: [&[f64]; MAX_CHANNELS] = Default::default();
+            let mut node_outputs: [&mut [f64]; MAX_CHANNELS] = Default::default();
+            for (node_input, scratch) in node_inputs.iter_mut().zip(self.scratch.iter()) {
+                *node_input = &scratch[..length];
+            }
+            for (node_output, output) in node_outputs.iter_mut().zip(node.output.iter_mut()) {
+                *node_output = &mut output[..length];
+            }
+            node.processor
+                .process_block(&node_inputs[..channels], &mut node_outputs[..channels]);
+        }
+
+        for (channel, output) in outputs.iter_mut().enumerate() {
+            let output = &mut output[start..start + length];
+            mix(
+                output,
+                channel,
+                &self.outputs,
+                &self.gains,
+                inputs,
+                start,
+                &self.nodes,
+            );
+        }
+
+        // all the delayed samples of this sub-block have been read
+        for line in self.delay_lines.iter_mut() {
+            line.write(&self.nodes[line.from].output, length);
+        }
+    }
+}
+
+/// Summing the sources of a node or of the graph output, for one channel
+fn mix(
+    destination: &mut [f64],
+    channel: usize,
+    sources: &[(Source, usize)],
+    gains: &[f64],
+    inputs: &[&[f64]],
+    start: usize,
+    nodes: &[Node],
+) {
+    for d in destination.iter_mut() {
+        *d = 0.0;
+    }
+    for &(source, edge) in sources.iter() {
+        let samples = match source {
+            Source::Input => &inputs[channel][start..],
+            Source::Node(node) => &nodes[node].output[channel][..],
+        };
+        let gain = gains[edge];
+        for (d, s) in destination.iter_mut().zip(samples.iter()) {
+            *d += gain * s;
+        }
+    }
+}
+
+impl DelayLine {
+    /// Adding the delayed samples, the oldest first
+    fn read(&self, destination: &mut [f64], channel: usize, gain: f64) {
+        let buffer = &self.buffer[channel];
+        for (i, d) in destination.iter_mut().enumerate() {
+            *d += gain * buffer[(self.position + i) % buffer.len()];
+        }
+    }
+
+    /// Overwriting the samples just read
+    fn write(&mut self, samples: &[Vec<f64>], length: usize) {
+        let delay = self.buffer[0].len();
+        for (buffer, samples) in self.buffer.iter_mut().zip(samples.iter()) {
+            for (i, s) in samples[..length].iter().enumerate() {
+                buffer[(self.position + i) % delay] = *s;
+            }
+        }
+        self.position = (self.position + length) % delay;
+    }
+}
+
+impl Processor for Graph {
+    fn reset(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.processor.reset();
+        }
+        for line in self.delay_lines.iter_mut() {
+            for buffer in line.buffer.iter_mut() {
+                for s in buffer.iter_mut() {
+                    *s = 0.0;
+                }
+            }
+            line.position = 0;
+        }
+    }
+
+    fn set_sample_rate(&mut self, fs: i32) {
+        for node in self.nodes.iter_mut() {
+            node.processor.set_sample_rate(fs);
+        }
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(self.channels, inputs, outputs);
+        let length = inputs[0].len();
+        let mut start = 0;
+        while start < length {
+            let sub_block = self.sub_block_size.min(length - start);
+            self.process_sub_block(inputs, outputs, start, sub_block);
+            start += sub_block;
+        }
+    }
+
+    /**
+     * Latency of the slowest path from the input to the output (feedback excluded)
+     */
+    fn latency(&self) -> usize {
+        let mut latencies = vec![0; self.nodes.len()];
+        let latency_of = |latencies: &[usize], source: &Source| match source {
+            Source::Input => 0,
+            Source::Node(node) => latencies[*node],
+        };
+        for &index in self.order.iter() {
+            let node = &self.nodes[index];
+            let input = node
+                .inputs
+                .iter()
+                .map(|(source, _)| latency_of(&latencies, source))
+                .max();
+            latencies[index] = input.unwrap_or(0) + node.processor.latency();
+        }
+        self.outputs
+            .iter()
+            .map(|(source, _)| latency_of(&latencies, source))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /**
+     * Longest tail from the input to the output, infinite with any feedback edge
+     */
+    fn tail(&self) -> Tail {
+        if !self.delay_lines.is_empty() {
+            return Tail::Infinite;
+        }
+        let mut tails = vec![Tail::Samples(0); self.nodes.len()];
+        let tail_of = |tails: &[Tail], source: &Source| match source {
+            Source::Input => Tail::Samples(0),
+            Source::Node(node) => tails[*node],
+        };
+        for &index in self.order.iter() {
+            let node = &self.nodes[index];
+            let input = node
+                .inputs
+                .iter()
+                .fold(Tail::Samples(0), |tail, (source, _)| {
+                    tail.max(tail_of(&tails, source))
+                });
+            tails[index] = input.then(node.processor.tail());
+        }
+        self.outputs
+            .iter()
+            .fold(Tail::Samples(0), |tail, (source, _)| {
+                tail.max(tail_of(&tails, source))
+            })
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+}
@@ -0,0 +1,511 @@
+//! Nonlinear ladder filters (voice filters of analog synths)
+//!
+//! 4 one pole low pass stages in series with a global negative feedback,
+//! each stage with the saturation of its transistors (or diodes).
+//! The feedback is solved per sample with the nonlinear gains of the previous state
+//! (zero delay feedback, linearized around the state).
+
+use crate::filter;
+use crate::processor::{self, Processor};
+use crate::sample::{FloatOfMax1, IntegerSample, Quantizer};
+use std::f64::consts::PI;
+
+/// Highest cutoff, relative to fs
+const MAX_RELATIVE_CUTOFF: f64 = 0.45;
+
+// The transistor ladder (tanh_x_dx and TransistorLadder) is a port of:
+//
+//// LICENSE TERMS: Copyright 2012 Teemu Voipio
+//
+// You can use this however you like for pretty much any purpose,
+// as long as you don't claim you wrote it. There is no warranty.
+//
+// Distribution of substantial portions of this code in source form
+// must include this copyright notice and list of conditions.
+//
+
+/**
+ * tanh(x)/x, Padé approximant
+ *
+ * Flattens at very large inputs (limit of 1/15), so not safe for very large feedback gains.
+ */
+#[inline(always)]
+fn tanh_x_dx(x: f64) -> f64 {
+    let a = x * x;
+    ((a + 105.0) * a + 945.0) / ((15.0 * a + 420.0) * a + 945.0)
+}
+
+/**
+ * Transistor ladder (Moog-style) low pass, 24 dB/oct
+ *
+ * Credits: Teemu Voipio, https://www.kvraudio.com/forum/viewtopic.php?t=349859
+ * (see docs/dsp-reading.org)
+ */
+pub struct TransistorLadder {
+    fs: i32,
+    cutoff: f64,
+    resonance: f64,
+    /// tuning
+    f: f64,
+    /// feedback
+    r: f64,
+    /// input delay, for the nonlinearity
+    zi: f64,
+    s: [f64; 4],
+}
+
+impl TransistorLadder {
+    /**
+     * Cutoff in Hz, resonance from 0 to 1 (self oscillating over 0.9)
+     *
+     * See [`set_cutoff`](TransistorLadder::set_cutoff) and
+     * [`set_resonance`](TransistorLadder::set_resonance) for the clamping.
+     * Panics if fs <= 0.
+     */
+    pub fn new(cutoff: f64, resonance: f64, fs: i32) -> Self {
+        assert!(fs > 0, "transistor ladder: invalid sample rate {}", fs);
+        let mut ladder = Self {
+            fs,
+            cutoff: 0.0,
+            resonance: 0.0,
+            f: 0.0,
+            r: 0.0,
+            zi: 0.0,
+            s: [0.0; 4],
+        };
+        ladder.set_cutoff(cutoff);
+        ladder.set_resonance(resonance);
+        ladder
+    }
+
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    /**
+     * Clamped to (0, 0.45 fs], can change at every sample
+     */
+    pub fn set_cutoff(&mut self, cutoff: f64) {
+        let max = MAX_RELATIVE_CUTOFF * self.fs as f64;
+        self.cutoff = if cutoff.is_nan() {
+            max
+        } else {
+            cutoff.clamp(f64::MIN_POSITIVE, max)
+        };
+        self.f = (PI * self.cutoff / self.fs as f64).tan();
+    }
+
+    pub fn resonance(&self) -> f64 {
+        self.resonance
+    }
+
+    /**
+     * Clamped to [0, 1]
+     */
+    pub fn set_resonance(&mut self, resonance: f64) {
+        self.resonance = if resonance.is_nan() {
+            0.0
+        } else {
+            resonance.clamp(0.0, 1.0)
+        };
+        self.r = (40.0 / 9.0) * self.resonance;
+    }
+
+    /**
+     * Clearing the filter memory
+     */
+    pub fn reset(&mut self) {
+        self.zi = 0.0;
+        self.s = [0.0; 4];
+    }
+
+    pub fn tick(&mut self, x: f64) -> f64 {
+        let (f, r, s) = (self.f, self.r, &mut self.s);
+
+        // input with half delay, for the nonlinearities
+        let ih = 0.5 * (x + self.zi);
+        self.zi = x;
+
+        // the nonlinear gains
+        let t0 = tanh_x_dx(ih - r * s[3]);
+        let t1 = tanh_x_dx(s[0]);
+        let t2 = tanh_x_dx(s[1]);
+        let t3 = tanh_x_dx(s[2]);
+        let t4 = tanh_x_dx(s[3]);
+
+        // denominators of the solutions of the individual stages
+        let g0 = 1.0 / (1.0 + f * t1);
+        let g1 = 1.0 / (1.0 + f * t2);
+        let g2 = 1.0 / (1.0 + f * t3);
+        let g3 = 1.0 / (1.0 + f * t4);
+
+        // factored out of the feedback solution
+        let f3 = f * t3 * g3;
+        let f2 = f * t2 * g2 * f3;
+        let f1 = f * t1 * g1 * f2;
+        let f0 = f * t0 * g0 * f1;
+
+        // solving the feedback
+        let y3 = (g3 * s[3] + f3 * g2 * s[2] + f2 * g1 * s[1] + f1 * g0 * s[0] + f0 * x)
+            / (1.0 + r * f0);
+
+        // then the other outputs (with the nonlinear gains)
+        let xx = t0 * (x - r * y3);
+        let y0 = t1 * g0 * (s[0] + f * xx);
+        let y1 = t2 * g1 * (s[1] + f * y0);
+        let y2 = t3 * g2 * (s[2] + f * y1);
+
+        s[0] += 2.0 * f * (xx - y0);
+        s[1] += 2.0 * f * (y0 - y1);
+        s[2] += 2.0 * f * (y1 - y2);
+        s[3] += 2.0 * f * (y2 - t4 * y3);
+
+        y3
+    }
+
+    /**
+     * Processing a block of samples
+     *
+     * Integer samples are only rounded: see [`TransistorLadder::process_block_quantized`].
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block<T: FloatOfMax1<T>>(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = x.from_f64(self.tick(x.to_f64()));
+        }
+    }
+
+    /**
+     * Processing a block of integer samples with the scaling and dither of the quantizer
+     *
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block_quantized<T: IntegerSample>(
+        &mut self,
+        input: &[T],
+        output: &mut [T],
+        quantizer: &mut Quantizer,
+    ) {
+        quantizer.process_block(input, output, |x| self.tick(x));
+    }
+
+    pub fn process_in_place<T: FloatOfMax1<T>>(&mut self, samples: &mut [T]) {
+        for sample in samples.iter_mut() {
+            *sample = sample.from_f64(self.tick(sample.to_f64()));
+        }
+    }
+}
+
+// The diode ladder (DiodeLadder and fast_tanh) is a port of code released under
+// the MIT license:
+//
+// The MIT License
+//
+// Copyright (c) 2012 Dominique Wurtz (www.blaukraut.info)
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+/// Feedback gain from which [`DiodeLadder::reset`] keeps the state
+const DIODE_RESET_MAX_K: f64 = 17.0;
+/// Highest normalized cutoff, just under Nyquist (where the integrators blow up)
+const DIODE_MAX_CUTOFF: f64 = 0.99;
+
+/**
+ * Diode ladder (TB-303 or EMS VCS3 style) low pass, 24 dB/oct
+ *
+ * Unlike the transistor ladder, the stages load each other, hence the coupled solution.
+ * Designed for 2x oversampling: the cutoff is not prewarped.
+ * Cutoffs are normalized: 1 is Nyquist. The cutoff is given at each [`tick`](DiodeLadder::tick),
+ * or set once for the [`Filter`](filter::Filter) and [`Processor`] processing.
+ *
+ * Credits: Dominique Wurtz (MIT license, see above and docs/dsp-reading.org)
+ */
+pub struct DiodeLadder {
+    /// of the [`Filter`](filter::Filter) and [`Processor`] processing
+    cutoff: f64,
+    /// feedback
+    k: f64,
+    /// resonance gain compensation
+    a: f64,
+    z: [f64; 4],
+    /// integrator gain of the one pole high pass in the feedback, 0 when off
+    hpf_g: f64,
+    hpf_s: f64,
+}
+
+impl Default for DiodeLadder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiodeLadder {
+    /**
+     * No resonance, no feedback high pass, fully open
+     */
+    pub fn new() -> Self {
+        let mut ladder = Self {
+            cutoff: DIODE_MAX_CUTOFF,
+            k: 0.0,
+            a: 0.0,
+            z: [0.0; 4],
+            hpf_g: 0.0,
+            hpf_s: 0.0,
+        };
+        ladder.set_q(0.0);
+        ladder
+    }
+
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    /**
+     * Cutoff of the [`Filter`](filter::Filter) and [`Processor`] processing, clamped to (0, 0.99]
+     */
+    pub fn set_cutoff(&mut self, fc: f64) {
+        self.cutoff = clamped_cutoff(fc);
+    }
+
+    /**
+     * Resonance, clamped to [0, 1]
+     */
+    pub fn set_q(&mut self, q: f64) {
+        let q = if q.is_nan() { 0.0 } else { q.clamp(0.0, 1.0) };
+        self.k = 20.0 * q;
+        self.a = 1.0 + 0.5 * self.k;
+    }
+
+    /**
+     * One pole high pass in the feedback path, 0 (the default) turns it off
+     *
+     * Less low end lost with resonance, as in the TB-303 (and not in the reference code).
+     * Clamped to [0, 0.99].
+     */
+    pub fn set_feedback_hpf_cutoff(&mut self, fc: f64) {
+        let fc = if fc.is_nan() {
+            0.0
+        } else {
+            fc.clamp(0.0, DIODE_MAX_CUTOFF)
+        };
+        self.hpf_g = (PI / 2.0 * fc).tan();
+        if self.hpf_g == 0.0 {
+            self.hpf_s = 0.0;
+        }
+    }
+
+    /**
+     * Clearing the filter memory, unless self oscillating (k >= 17) as in the reference
+     */
+    pub fn reset(&mut self) {
+        if self.k < DIODE_RESET_MAX_K {
+            self.z = [0.0; 4];
+            self.hpf_s = 0.0;
+        }
+    }
+
+    /**
+     * Processing one sample, with the cutoff fc clamped to (0, 0.99]
+     *
+     * The cutoff can be modulated at every sample.
+     */
+    pub fn tick(&mut self, x: f64, fc: f64) -> f64 {
+        let fc = clamped_cutoff(fc);
+        let (k, z) = (self.k, &self.z);
+        let wc = PI / 2.0 * fc;
+        let wc2 = wc * wc;
+        let wc3 = wc2 * wc;
+        let wc4 = wc3 * wc;
+        let b = 1.0 / (1.0 + 8.0 * wc + 20.0 * wc2 + 16.0 * wc3 + 2.0 * wc4);
+        let g = 2.0 * wc4 * b;
+
+        // current state
+        let s = (z[0] * wc3
+            + z[1] * (wc2 + 2.0 * wc3)
+            + z[2] * (wc + 4.0 * wc2 + 2.0 * wc3)
+            + z[3] * (1.0 + 6.0 * wc + 9.0 * wc2 + 2.0 * wc3))
+            * b;
+
+        // solving the feedback loop (linear), through the high pass:
+        // hp(y4) = (y4 - hpf_s) / (1 + hpf_g)
+        let hpf_norm = 1.0 / (1.0 + self.hpf_g);
+        let y4 = (g * (x + k * self.hpf_s * hpf_norm) + s) / (1.0 + g * k * hpf_norm);
+
+        // input clipping
+        let y0 = fast_tanh(x - k * (y4 - self.hpf_s) * hpf_norm);
+
+        // all the integrator outputs
+        let y1 = (y0 * (2.0 * wc + 12.0 * wc2 + 20.0 * wc3 + 8.0 * wc4)
+            + z[0] * (1.0 + 6.0 * wc + 10.0 * wc2 + 4.0 * wc3)
+            + z[1] * (2.0 * wc + 8.0 * wc2 + 6.0 * wc3)
+            + z[2] * (2.0 * wc2 + 4.0 * wc3)
+            + z[3] * 2.0 * wc3)
+            * b;
+        let y2 = (y0 * (2.0 * wc2 + 8.0 * wc3 + 6.0 * wc4)
+            + z[0] * (wc + 4.0 * wc2 + 3.0 * wc3)
+            + z[1] * (1.0 + 6.0 * wc + 11.0 * wc2 + 6.0 * wc3)
+            + z[2] * (wc + 4.0 * wc2 + 4.0 * wc3)
+            + z[3] * (wc2 + 2.0 * wc3))
+            * b;
+        let y3 = (y0 * (2.0 * wc3 + 4.0 * wc4)
+            + z[0] * (wc2 + 2.0 * wc3)
+            + z[1] * (wc + 4.0 * wc2 + 4.0 * wc3)
+            + z[2] * (1.0 + 6.0 * wc + 10.0 * wc2 + 4.0 * wc3)
+            + z[3] * (wc + 4.0 * wc2 + 2.0 * wc3))
+            * b;
+        let y4 = g * y0 + s;
+
+        self.z[0] += 4.0 * wc * (y0 - y1 + y2);
+        self.z[1] += 2.0 * wc * (y1 - 2.0 * y2 + y3);
+        self.z[2] += 2.0 * wc * (y2 - 2.0 * y3 + y4);
+        self.z[3] += 2.0 * wc * (y3 - 2.0 * y4);
+
+        // high pass state (its low pass part), trapezoidal integrator
+        let v = (y4 - self.hpf_s) * self.hpf_g * hpf_norm;
+        self.hpf_s += 2.0 * v;
+
+        self.a * y4
+    }
+
+    /**
+     * Processing a block of samples, at a fixed cutoff
+     *
+     * Integer samples are only rounded: see [`DiodeLadder::process_block_quantized`].
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block<T: FloatOfMax1<T>>(&mut self, input: &[T], output: &mut [T], fc: f64) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = x.from_f64(self.tick(x.to_f64(), fc));
+        }
+    }
+
+    /**
+     * Processing a block of integer samples at a fixed cutoff, with the scaling and dither of
+     * the quantizer
+     *
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block_quantized<T: IntegerSample>(
+        &mut self,
+        input: &[T],
+        output: &mut [T],
+        quantizer: &mut Quantizer,
+        fc: f64,
+    ) {
+        quantizer.process_block(input, output, |x| self.tick(x, fc));
+    }
+
+    pub fn process_in_place<T: FloatOfMax1<T>>(&mut self, samples: &mut [T], fc: f64) {
+        for sample in samples.iter_mut() {
+            *sample = sample.from_f64(self.tick(sample.to_f64(), fc));
+        }
+    }
+}
+
+fn clamped_cutoff(fc: f64) -> f64 {
+    if fc.is_nan() {
+        DIODE_MAX_CUTOFF
+    } else {
+        fc.clamp(f64::MIN_POSITIVE, DIODE_MAX_CUTOFF)
+    }
+}
+
+/// x / (1 + |x|), a cheap soft clipper
+///
+/// The reference calls `abs`, which is the int overload with some C++ standard libraries.
+#[inline(always)]
+fn fast_tanh(x: f64) -> f64 {
+    x / (1.0 + x.abs())
+}
+
+impl filter::Filter for TransistorLadder {
+    fn tick(&mut self, x: f64) -> f64 {
+        TransistorLadder::tick(self, x)
+    }
+}
+
+impl Processor for TransistorLadder {
+    fn reset(&mut self) {
+        TransistorLadder::reset(self)
+    }
+
+    /**
+     * Keeping the cutoff in Hz (clamped for the new sample rate)
+     *
+     * Panics if fs <= 0.
+     */
+    fn set_sample_rate(&mut self, fs: i32) {
+        assert!(fs > 0, "transistor ladder: invalid sample rate {}", fs);
+        self.fs = fs;
+        self.set_cutoff(self.cutoff);
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::process_filter(self, inputs, outputs);
+    }
+
+    /**
+     * Tail of the small signals: 4 one pole stages in a loop, (1 + s)^4 + r
+     */
+    fn tail(&self) -> processor::Tail {
+        processor::analog_decay_tail(&[1.0 + self.r, 4.0, 6.0, 4.0, 1.0], self.f)
+    }
+}
+
+impl filter::Filter for DiodeLadder {
+    /// At the cutoff set by [`set_cutoff`](DiodeLadder::set_cutoff)
+    fn tick(&mut self, x: f64) -> f64 {
+        DiodeLadder::tick(self, x, self.cutoff)
+    }
+}
+
+impl Processor for DiodeLadder {
+    fn reset(&mut self) {
+        DiodeLadder::reset(self)
+    }
+
+    /**
+     * The cutoffs are relative to fs: nothing to retune
+     */
+    fn set_sample_rate(&mut self, _fs: i32) {}
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::process_filter(self, inputs, outputs);
+    }
+
+    /**
+     * Tail of the small signals, without the feedback high pass in the loop:
+     * s^4 + 8 s^3 + 20 s^2 + 16 s + 2 + 2 k (self oscillating from k = 17)
+     */
+    fn tail(&self) -> processor::Tail {
+        let k = self.k;
+        let ladder = processor::analog_decay_tail(
+            &[2.0 + 2.0 * k, 16.0, 20.0, 8.0, 1.0],
+            PI / 2.0 * self.cutoff,
+        );
+        if self.hpf_g == 0.0 {
+            ladder
+        } else {
+            let hpf_pole = (1.0 - self.hpf_g) / (1.0 + self.hpf_g);
+            ladder.max(processor::decay_tail(hpf_pole.abs()))
+        }
+    }
+}
@@ -1,3 +1,22 @@
 pub mod biquad;
 pub mod utils;
-pub mod filter;
\ No newline at end of file
+pub mod filter;
+pub mod cascade;
+pub mod complex;
+pub mod crossover;
+pub mod design;
+pub mod error;
+pub mod response;
+pub mod smoothing;
+pub mod svf;
+pub mod ladder;
+pub mod character;
+pub mod processor;
+pub mod graph;
+pub mod simd;
+pub mod fixed;
+pub mod sample;
+pub mod dither;
+#[cfg(feature = "wav")]
+pub mod wav;
+pub mod plot;
\ No newline at end of file
@@ -1,3 +1,343 @@
+//! Command line tool: filtering WAV files, plotting filter designs
+//!
+//! Needs the `wav` feature, on by default: `cargo run -- help`
+
+use dsp_playground::cascade::Cascade;
+use dsp_playground::dither::{Dither, Ditherer, NoiseShaping};
+use dsp_playground::filter;
+use dsp_playground::plot::Plot;
+use dsp_playground::processor::{self, Processor};
+use dsp_playground::sample::{Quantizer, Scaling};
+use dsp_playground::wav;
+use std::error::Error;
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::process;
+
+const USAGE: &str = "\
+usage: dsp-playground filter <input.wav> <output.wav> [options]
+       dsp-playground plot <output.svg> [options]
+       dsp-playground help
+
+filter: filters a WAV file (any channel count, sample rate and bit depth)
+through a chain of biquads, applied in the order of the command line.
+
+options:
+  -f, --filter <type>:<fc>[:<q>[:<gain_db>]]
+        type: lowpass, highpass, bandpass, notch, peak, lowshelf, highshelf
+        q defaults to 0.7071, the gain to 0 dB (used by peak and the shelves)
+  -b, --bits <8|16|24|32|32f>
+        output bit depth, defaults to the input's (32f: 32 bits float)
+  -d, --dither <none|rpdf|tpdf|hp-tpdf>
+        dither of the integer outputs, defaults to tpdf
+  -s, --shaping <none|error-feedback|f-weighted|wannamaker9>
+        noise shaping of the dither, defaults to none
+
+plot: plots the magnitude and phase responses of chains of biquads to an SVG
+file, one curve per chain.
+
+options:
+  -c, --curve <filter>[,<filter>...]
+        a curve, the response of a chain of filters (type:fc[:q[:gain_db]])
+  -r, --fs <sample_rate>
+        defaults to 44100
+  -t, --title <title>
+  --no-phase
+        without the phase plot
+
+examples:
+  dsp-playground filter in.wav out.wav -f highpass:80 -f peak:3000:2:-4 -b 16
+  dsp-playground plot eq.svg -c highpass:80,peak:3000:2:-4 -c lowshelf:200:0.7:3
+";
+
+const BLOCK_SIZE: usize = 1024;
+const DEFAULT_FS: i32 = 44100;
+/// The same input gives the same dithered output
+const DITHER_SEED: u32 = 1;
+
+struct FilterArgs {
+    input: String,
+    output: String,
+    filters: Vec<(filter::Params, filter::Type)>,
+    format: Option<wav::Format>,
+    dither: Dither,
+    shaping: NoiseShaping,
+}
+
+/// The filters of the command line, with their own memory in each channel
+struct Chain {
+    channels: Vec<Cascade>,
+}
+
+impl Processor for Chain {
+    fn reset(&mut self) {
+        for cascade in self.channels.iter_mut() {
+            cascade.reset();
+        }
+    }
+
+    fn set_sample_rate(&mut self, fs: i32) {
+        for cascade in self.channels.iter_mut() {
+            Processor::set_sample_rate(cascade, fs);
+        }
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        assert_eq!(inputs.len(), self.channels.len(), "chain: input channels");
+        assert_eq!(outputs.len(), self.channels.len(), "chain: output channels");
+        for ((cascade, input), output) in self
+            .channels
+            .iter_mut()
+            .zip(inputs.iter())
+            .zip(outputs.iter_mut())
+        {
+            output.copy_from_slice(input);
+            cascade.process_in_place(output);
+        }
+    }
+
+    fn tail(&self) -> processor::Tail {
+        self.channels
+            .iter()
+            .fold(processor::Tail::Samples(0), |tail, cascade| {
+                tail.max(cascade.tail())
+            })
+    }
+
+    fn channels(&self) -> usize {
+        self.channels.len()
+    }
+}
+
+struct PlotArgs {
+    output: String,
+    /// Label and filters of each curve
+    curves: Vec<(String, Vec<(filter::Params, filter::Type)>)>,
+    fs: i32,
+    title: Option<String>,
+    phase: bool,
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(|arg| arg.as_str()) {
+        Some("filter") => parse_filter_args(&args[1..]).and_then(|args| filter(&args)),
+        Some("plot") => parse_plot_args(&args[1..]).and_then(|args| plot(&args)),
+        Some("help") | Some("-h") | Some("--help") => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => Err(format!("unknown command '{}'", command).into()),
+        None => Err("missing command".into()),
+    };
+    if let Err(error) = result {
+        eprintln!("error: {}\n\n{}", error, USAGE);
+        process::exit(1);
+    }
+}
+
+fn parse_filter_args(args: &[String]) -> Result<FilterArgs, Box<dyn Error>> {
+    let mut paths = vec![];
+    let mut filters = vec![];
+    let mut format = None;
+    let mut dither = Dither::Tpdf;
+    let mut shaping = NoiseShaping::None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            paths.push(arg.clone());
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value of {}", arg))?;
+        match arg.as_str() {
+            "-f" | "--filter" => filters.push(parse_filter(value)?),
+            "-b" | "--bits" => format = Some(parse_format(value)?),
+            "-d" | "--dither" => dither = parse_dither(value)?,
+            "-s" | "--shaping" => shaping = parse_shaping(value)?,
+            _ => return Err(format!("unknown option {}", arg).into()),
+        }
+    }
+
+    if paths.len() != 2 {
+        return Err(format!("expected an input and an output, got {:?}", paths).into());
+    }
+    let output = paths.pop().unwrap();
+    let input = paths.pop().unwrap();
+    Ok(FilterArgs {
+        input,
+        output,
+        filters,
+        format,
+        dither,
+        shaping,
+    })
+}
+
+fn parse_plot_args(args: &[String]) -> Result<PlotArgs, Box<dyn Error>> {
+    let mut paths = vec![];
+    let mut curves = vec![];
+    let mut fs = DEFAULT_FS;
+    let mut title = None;
+    let mut phase = true;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            paths.push(arg.clone());
+            continue;
+        }
+        if arg == "--no-phase" {
+            phase = false;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value of {}", arg))?;
+        match arg.as_str() {
+            "-c" | "--curve" => {
+                let filters = value
+                    .split(',')
+                    .map(parse_filter)
+                    .collect::<Result<Vec<_>, _>>()?;
+                curves.push((value.clone(), filters));
+            }
+            "-r" | "--fs" => {
+                fs = value
+                    .parse()
+                    .map_err(|_| format!("invalid sample rate '{}'", value))?
+            }
+            "-t" | "--title" => title = Some(value.clone()),
+            _ => return Err(format!("unknown option {}", arg).into()),
+        }
+    }
+
+    if paths.len() != 1 {
+        return Err(format!("expected an output, got {:?}", paths).into());
+    }
+    if curves.is_empty() {
+        return Err("nothing to plot: missing --curve".into());
+    }
+    Ok(PlotArgs {
+        output: paths.pop().unwrap(),
+        curves,
+        fs,
+        title,
+        phase,
+    })
+}
+
+/// type:fc[:q[:gain_db]]
+fn parse_filter(value: &str) -> Result<(filter::Params, filter::Type), String> {
+    let fields: Vec<&str> = value.split(':').collect();
+    if fields.len() < 2 || fields.len() > 4 {
+        return Err(format!(
+            "invalid filter '{}' (expected type:fc[:q[:gain_db]])",
+            value
+        ));
+    }
+    let filter_type = match fields[0] {
+        "lowpass" => filter::Type::LowPass,
+        "highpass" => filter::Type::HighPass,
+        "bandpass" => filter::Type::BandPass,
+        "notch" => filter::Type::Notch,
+        "peak" => filter::Type::Peak,
+        "lowshelf" => filter::Type::LowShelf,
+        "highshelf" => filter::Type::HighShelf,
+        other => return Err(format!("unknown filter type '{}'", other)),
+    };
+    let number = |index: usize, name: &str, default: f64| match fields.get(index) {
+        Some(field) => field
+            .parse::<f64>()
+            .map_err(|_| format!("invalid {} '{}' in filter '{}'", name, field, value)),
+        None => Ok(default),
+    };
+    let params = filter::Params {
+        fc: number(1, "fc", 0.0)?,
+        q: number(2, "q", FRAC_1_SQRT_2)?,
+        gain_db: number(3, "gain", 0.0)?,
+    };
+    Ok((params, filter_type))
+}
+
+fn parse_format(value: &str) -> Result<wav::Format, String> {
+    match value {
+        "8" => Ok(wav::Format::Int8),
+        "16" => Ok(wav::Format::Int16),
+        "24" => Ok(wav::Format::Int24),
+        "32" => Ok(wav::Format::Int32),
+        "32f" => Ok(wav::Format::Float32),
+        _ => Err(format!("invalid bit depth '{}'", value)),
+    }
+}
+
+fn parse_dither(value: &str) -> Result<Dither, String> {
+    match value {
+        "none" => Ok(Dither::None),
+        "rpdf" => Ok(Dither::Rpdf),
+        "tpdf" => Ok(Dither::Tpdf),
+        "hp-tpdf" => Ok(Dither::HighPassTpdf),
+        _ => Err(format!("invalid dither '{}'", value)),
+    }
+}
+
+fn parse_shaping(value: &str) -> Result<NoiseShaping, String> {
+    match value {
+        "none" => Ok(NoiseShaping::None),
+        "error-feedback" => Ok(NoiseShaping::ErrorFeedback),
+        "f-weighted" => Ok(NoiseShaping::FWeighted),
+        "wannamaker9" => Ok(NoiseShaping::Wannamaker9),
+        _ => Err(format!("invalid noise shaping '{}'", value)),
+    }
+}
+
+fn filter(args: &FilterArgs) -> Result<(), Box<dyn Error>> {
+    let mut reader = wav::Reader::open(&args.input)?;
+    let spec = reader.spec();
+
+    for (i, (params, _)) in args.filters.iter().enumerate() {
+        params
+            .validate(spec.sample_rate)
+            .map_err(|error| format!("filter {}: {}", i + 1, error))?;
+    }
+    let mut chain = Chain {
+        channels: (0..spec.channels)
+            .map(|_| Cascade::from_audio_filter_params(&args.filters, spec.sample_rate))
+            .collect(),
+    };
+
+    let output_spec = wav::Spec {
+        format: args.format.unwrap_or(spec.format),
+        ..spec
+    };
+    let mut writer = wav::Writer::create(&args.output, output_spec)?;
+    for channel in 0..spec.channels {
+        // uncorrelated noises between the channels
+        let ditherer = Ditherer::new(args.dither, args.shaping, DITHER_SEED + channel as u32);
+        writer.set_quantizer(
+            channel,
+            Quantizer::with_ditherer(Scaling::Symmetric, ditherer),
+        );
+    }
+    wav::process(&mut reader, &mut writer, &mut chain, BLOCK_SIZE)?;
+    writer.finalize()?;
+    Ok(())
+}
+
+fn plot(args: &PlotArgs) -> Result<(), Box<dyn Error>> {
+    let mut plot = Plot::new(args.fs).phase(args.phase);
+    if let Some(title) = &args.title {
+        plot = plot.title(title);
+    }
+    for (i, (label, filters)) in args.curves.iter().enumerate() {
+        for (j, (params, _)) in filters.iter().enumerate() {
+            params
+                .validate(args.fs)
+                .map_err(|error| format!("curve {}, filter {}: {}", i + 1, j + 1, error))?;
+        }
+        plot.add_curve(label, &Cascade::from_audio_filter_params(filters, args.fs));
+    }
+    plot.write(&args.output)?;
+    Ok(())
 }
@@ -0,0 +1,436 @@
+//! SVG plots of frequency responses
+//!
+//! Self contained SVG files (no script, font or style sheet to load): the magnitude
+//! in dB over a log frequency axis and, below it, the unwrapped phase in degrees.
+//! Several curves are overlaid, with a legend.
+//!
+//! ```no_run
+//! use dsp_playground::{biquad, plot::Plot};
+//!
+//! Plot::new(44100)
+//!     .title("low pass")
+//!     .curve("1 kHz", &biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6)
+//!     .write("lowpass.svg")
+//!     .unwrap();
+//! ```
+
+use crate::response::{self, FrequencyResponse, Point};
+use std::fmt::Write as _;
+use std::path::Path;
+
+const WIDTH: f64 = 800.0;
+const MAGNITUDE_HEIGHT: f64 = 320.0;
+const PHASE_HEIGHT: f64 = 200.0;
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 40.0;
+/// Between the panels, and below the last one (for the frequency labels)
+const GAP: f64 = 40.0;
+/// Points per curve
+const RESOLUTION: usize = 512;
+/// Largest dB span of the automatic range: deep notches don't squash the rest
+const MAX_DB_SPAN: f64 = 96.0;
+const COLORS: [&str; 8] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+struct Curve {
+    label: String,
+    points: Vec<Point>,
+}
+
+/**
+ * Magnitude (and phase) plot, rendered by [`to_svg`](Plot::to_svg)
+ *
+ * The curves are evaluated when added: set the frequency range first.
+ */
+pub struct Plot {
+    fs: i32,
+    title: Option<String>,
+    f_min: f64,
+    f_max: f64,
+    db_range: Option<(f64, f64)>,
+    phase: bool,
+    curves: Vec<Curve>,
+}
+
+/// A vertical range and its grid
+struct Axis {
+    min: f64,
+    max: f64,
+    step: f64,
+}
+
+impl Axis {
+    /// Rounded out to multiples of `unit`, then of the step of a grid of at most `lines` steps
+    fn fit(min: f64, max: f64, unit: f64, steps: &[f64], lines: f64) -> Axis {
+        let mut min = (min / unit).floor() * unit;
+        let mut max = (max / unit).ceil() * unit;
+        if max - min < 2.0 * unit {
+            min -= unit;
+            max += unit;
+        }
+        let span = max - min;
+        let step = steps
+            .iter()
+            .cloned()
+            .find(|step| span / step <= lines)
+            .unwrap_or(span);
+        Axis {
+            min: (min / step).floor() * step,
+            max: (max / step).ceil() * step,
+            step,
+        }
+    }
+
+    fn ticks(&self) -> impl Iterator<Item = f64> + '_ {
+        let first = (self.min / self.step).ceil() as i64;
+        let last = (self.max / self.step).floor() as i64;
+        (first..=last).map(move |i| i as f64 * self.step)
+    }
+}
+
+/// A panel of the plot, in SVG coordinates
+struct Panel {
+    top: f64,
+    height: f64,
+    axis: Axis,
+}
+
+impl Panel {
+    fn y(&self, value: f64) -> f64 {
+        let value = value.clamp(self.axis.min, self.axis.max);
+        self.top + (self.axis.max - value) / (self.axis.max - self.axis.min) * self.height
+    }
+}
+
+impl Plot {
+    /**
+     * From 20 Hz to fs/2, with the phase and an automatic dB range
+     */
+    pub fn new(fs: i32) -> Self {
+        Self {
+            fs,
+            title: None,
+            f_min: 20.0,
+            f_max: fs as f64 / 2.0,
+            db_range: None,
+            phase: true,
+            curves: vec![],
+        }
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /**
+     * Panics unless 0 < f_min < f_max.
+     */
+    pub fn frequency_range(mut self, f_min: f64, f_max: f64) -> Self {
+        assert!(
+            f_min > 0.0 && f_min < f_max,
+            "plot: invalid frequency range {} to {}",
+            f_min,
+            f_max
+        );
+        self.f_min = f_min;
+        self.f_max = f_max;
+        self
+    }
+
+    /**
+     * Fixed magnitude range, the curves being clamped to it
+     */
+    pub fn db_range(mut self, min: f64, max: f64) -> Self {
+        assert!(min < max, "plot: invalid dB range {} to {}", min, max);
+        self.db_range = Some((min, max));
+        self
+    }
+
+    /**
+     * With the phase panel (the default) or without
+     */
+    pub fn phase(mut self, phase: bool) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /**
+     * Overlaying the response of a filter: biquad params, a slice of them, a cascade
+     */
+    pub fn curve<R: FrequencyResponse + ?Sized>(mut self, label: &str, filter: &R) -> Self {
+        self.add_curve(label, filter);
+        self
+    }
+
+    pub fn add_curve<R: FrequencyResponse + ?Sized>(&mut self, label: &str, filter: &R) {
+        let frequencies = response::log_frequencies(self.f_min, self.f_max, RESOLUTION);
+        let points = response::evaluate(filter, &frequencies, self.fs);
+        self.add_points(label, points);
+    }
+
+    /**
+     * Overlaying points computed elsewhere (a measured response for example)
+     */
+    pub fn add_points(&mut self, label: &str, points: Vec<Point>) {
+        self.curves.push(Curve {
+            label: label.to_string(),
+            points,
+        });
+    }
+
+    pub fn height(&self) -> f64 {
+        let phase = if self.phase { PHASE_HEIGHT + GAP } else { 0.0 };
+        MARGIN_TOP + MAGNITUDE_HEIGHT + phase + GAP
+    }
+
+    pub fn to_svg(&self) -> String {
+        let height = self.height();
+        let mut svg = String::new();
+        // writing into a String doesn't fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="11">"#,
+            w = WIDTH,
+            h = height
+        );
+        svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+        if let Some(title) = &self.title {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="24" font-size="14" text-anchor="middle">{}</text>"#,
+                WIDTH / 2.0,
+                escape(title)
+            );
+        }
+
+        let magnitude = Panel {
+            top: MARGIN_TOP,
+            height: MAGNITUDE_HEIGHT,
+            axis: self.magnitude_axis(),
+        };
+        self.panel(&mut svg, &magnitude, "dB", |point| point.magnitude_db);
+        if self.phase {
+            let phase = Panel {
+                top: MARGIN_TOP + MAGNITUDE_HEIGHT + GAP,
+                height: PHASE_HEIGHT,
+                axis: self.phase_axis(),
+            };
+            self.panel(&mut svg, &phase, "deg", |point| point.phase.to_degrees());
+        }
+        self.legend(&mut svg);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_svg())
+    }
+
+    fn values(&self, value: fn(&Point) -> f64) -> impl Iterator<Item = f64> + '_ {
+        self.curves
+            .iter()
+            .flat_map(|curve| curve.points.iter())
+            .map(value)
+            .filter(|v| v.is_finite())
+    }
+
+    fn magnitude_axis(&self) -> Axis {
+        if let Some((min, max)) = self.db_range {
+            // a step dividing the bounds, if any
+            let span = max - min;
+            let steps = [1.0, 2.0, 3.0, 5.0, 6.0, 10.0, 12.0, 20.0, 24.0, 48.0];
+            let fits = |step: &f64| span / step <= 10.0;
+            let divides = |step: &f64| (min / step).fract() == 0.0 && (max / step).fract() == 0.0;
+            let step = steps
+                .iter()
+                .cloned()
+                .find(|step| fits(step) && divides(step))
+                .or_else(|| steps.iter().cloned().find(fits))
+                .unwrap_or(span);
+            return Axis { min, max, step };
+        }
+        let steps = [1.0, 2.0, 3.0, 6.0, 12.0, 24.0, 48.0];
+        let max = self.values(|p| p.magnitude_db).fold(f64::MIN, f64::max);
+        let min = self.values(|p| p.magnitude_db).fold(f64::MAX, f64::min);
+        if max < min {
+            // no curve
+            return Axis::fit(-24.0, 6.0, 6.0, &steps, 10.0);
+        }
+        let min = min.max(max - MAX_DB_SPAN);
+        Axis::fit(min - 1.0, max + 1.0, 6.0, &steps, 10.0)
+    }
+
+    fn phase_axis(&self) -> Axis {
+        let max = self
+            .values(|p| p.phase.to_degrees())
+            .fold(f64::MIN, f64::max);
+        let min = self
+            .values(|p| p.phase.to_degrees())
+            .fold(f64::MAX, f64::min);
+        let (min, max) = if max < min { (-90.0, 90.0) } else { (min, max) };
+        let steps = [15.0, 30.0, 45.0, 90.0, 180.0, 360.0, 720.0];
+        Axis::fit(min, max, 90.0, &steps, 8.0)
+    }
+
+    fn x(&self, f: f64) -> f64 {
+        let width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+        MARGIN_LEFT + (f / self.f_min).ln() / (self.f_max / self.f_min).ln() * width
+    }
+
+    fn panel(&self, svg: &mut String, panel: &Panel, unit: &str, value: impl Fn(&Point) -> f64) {
+        let left = MARGIN_LEFT;
+        let right = WIDTH - MARGIN_RIGHT;
+        let bottom = panel.top + panel.height;
+
+        svg.push_str("<g stroke=\"#e0e0e0\" stroke-width=\"1\">\n");
+        for (f, major) in self.frequency_ticks() {
+            let x = self.x(f);
+            let stroke = if major { " stroke=\"#b0b0b0\"" } else { "" };
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{x:.1}" y1="{:.1}" x2="{x:.1}" y2="{:.1}"{}/>"#,
+                panel.top,
+                bottom,
+                stroke,
+                x = x
+            );
+        }
+        for tick in panel.axis.ticks() {
+            let y = panel.y(tick);
+            let stroke = if tick == 0.0 {
+                " stroke=\"#b0b0b0\""
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}"{}/>"#,
+                left,
+                right,
+                stroke,
+                y = y
+            );
+        }
+        svg.push_str("</g>\n");
+
+        // labels
+        svg.push_str("<g fill=\"#404040\">\n");
+        for (f, _) in self.frequency_ticks().filter(|(f, _)| is_labeled(*f)) {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                self.x(f),
+                bottom + 14.0,
+                format_frequency(f)
+            );
+        }
+        for tick in panel.axis.ticks() {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#,
+                left - 6.0,
+                panel.y(tick) + 4.0,
+                format_number(tick)
+            );
+        }
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#,
+            left - 6.0,
+            panel.top - 8.0,
+            unit
+        );
+        svg.push_str("</g>\n");
+
+        // the values out of the range are clamped to the frame
+        svg.push_str("<g fill=\"none\" stroke-width=\"1.5\">\n");
+        for (curve, color) in self.curves.iter().zip(COLORS.iter().cycle()) {
+            let mut points = String::new();
+            for point in curve.points.iter() {
+                let v = value(point);
+                let v = if v.is_nan() { panel.axis.min } else { v };
+                let _ = write!(points, "{:.1},{:.1} ", self.x(point.f), panel.y(v));
+            }
+            let _ = writeln!(
+                svg,
+                r#"<polyline stroke="{}" points="{}"/>"#,
+                color,
+                points.trim_end()
+            );
+        }
+        svg.push_str("</g>\n");
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="none" stroke="#808080"/>"##,
+            left,
+            panel.top,
+            right - left,
+            panel.height
+        );
+    }
+
+    fn legend(&self, svg: &mut String) {
+        let right = WIDTH - MARGIN_RIGHT - 10.0;
+        for (i, (curve, color)) in self.curves.iter().zip(COLORS.iter().cycle()).enumerate() {
+            let y = MARGIN_TOP + 16.0 + 16.0 * i as f64;
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="{}" stroke-width="2"/>"#,
+                right - 20.0,
+                right,
+                color,
+                y = y
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#,
+                right - 26.0,
+                y + 4.0,
+                escape(&curve.label)
+            );
+        }
+    }
+
+    /// 1, 2, ... 9 times the powers of 10 in the range, major on the powers of 10
+    fn frequency_ticks(&self) -> impl Iterator<Item = (f64, bool)> + '_ {
+        let first = self.f_min.log10().floor() as i32;
+        let last = self.f_max.log10().ceil() as i32;
+        (first..=last)
+            .flat_map(|decade| (1..10).map(move |m| (m as f64 * 10f64.powi(decade), m == 1)))
+            .filter(move |(f, _)| *f >= self.f_min && *f <= self.f_max)
+    }
+}
+
+/// 1, 2 and 5 times the powers of 10
+fn is_labeled(f: f64) -> bool {
+    let mantissa = f / 10f64.powf(f.log10().floor());
+    [1.0, 2.0, 5.0].iter().any(|m| (mantissa - m).abs() < 1e-6)
+}
+
+fn format_frequency(f: f64) -> String {
+    if f >= 1_000.0 {
+        format!("{}k", format_number(f / 1_000.0))
+    } else {
+        format_number(f)
+    }
+}
+
+fn format_number(x: f64) -> String {
+    let rounded = (x * 100.0).round() / 100.0;
+    if rounded == 0.0 {
+        // not -0
+        "0".to_string()
+    } else {
+        format!("{}", rounded)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,148 @@
+//! Hosting any DSP block the same way
+//!
+//! [`Processor`] is what a plugin wrapper or an offline tool needs to know about a block:
+//! how to reset it, retune it for a new sample rate, run it on planar f64 buffers
+//! (one slice per channel), and how long its delay and its tail are.
+//!
+//! Every [`Filter`](crate::filter::Filter) is a mono processor.
+//! Object safe: a chain can be a `Vec<Box<dyn Processor>>`.
+//! The concrete types keep their own generic `process_block`, so call the trait one
+//! as `Processor::process_block(&mut p, ..)` when both are in scope.
+
+use crate::design;
+use crate::filter::Filter;
+
+/// Level under which a decaying tail counts as silence (-120 dB)
+pub const TAIL_THRESHOLD: f64 = 1e-6;
+
+/**
+ * How long the output keeps going after the input stops
+ */
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Tail {
+    /// Silent after this number of samples (0 for memoryless processors)
+    Samples(usize),
+    /// Never decays: self oscillating, or poles on the unit circle
+    Infinite,
+}
+
+impl Tail {
+    /**
+     * Tail of two processors in series
+     */
+    pub fn then(self, other: Tail) -> Tail {
+        match (self, other) {
+            (Tail::Samples(a), Tail::Samples(b)) => Tail::Samples(a + b),
+            _ => Tail::Infinite,
+        }
+    }
+
+    /**
+     * Tail of two processors in parallel
+     */
+    pub fn max(self, other: Tail) -> Tail {
+        match (self, other) {
+            (Tail::Samples(a), Tail::Samples(b)) => Tail::Samples(a.max(b)),
+            _ => Tail::Infinite,
+        }
+    }
+}
+
+pub trait Processor {
+    /// Clearing the memory, as at the start of a new stream
+    fn reset(&mut self);
+
+    /**
+     * Retuning for a new sample rate
+     *
+     * Processors designed from audio params (fc, q, gain) redesign their coefficients,
+     * the ones built from raw coefficients keep them.
+     */
+    fn set_sample_rate(&mut self, fs: i32);
+
+    /**
+     * Processing planar buffers: one input and one output slice per channel
+     *
+     * Panics if the number of slices is not [`channels`](Processor::channels)
+     * or if their lengths differ.
+     */
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]);
+
+    /// Delay introduced by the processing, in samples
+    fn latency(&self) -> usize {
+        0
+    }
+
+    fn tail(&self) -> Tail;
+
+    fn channels(&self) -> usize {
+        1
+    }
+}
+
+/**
+ * Tail of a filter whose slowest pole has the given magnitude
+ *
+ * The number of samples for the impulse response envelope to fall under
+ * [`TAIL_THRESHOLD`], plus the 2 samples of a second order section's delay line.
+ */
+pub fn decay_tail(pole_magnitude: f64) -> Tail {
+    if pole_magnitude.is_nan() || pole_magnitude >= 1.0 {
+        Tail::Infinite
+    } else if pole_magnitude <= TAIL_THRESHOLD {
+        Tail::Samples(2)
+    } else {
+        let samples = TAIL_THRESHOLD.ln() / pole_magnitude.ln();
+        Tail::Samples(samples.ceil() as usize + 2)
+    }
+}
+
+/**
+ * Tail of a zero delay feedback filter, from the denominator of its analog model
+ *
+ * The denominator is monic, lowest power first, in s normalized to the cutoff.
+ * Its roots map to the poles z = (1 + g s) / (1 - g s), with g the gain of the
+ * trapezoidal integrators. For the nonlinear filters: the tail of the small signals.
+ */
+pub(crate) fn analog_decay_tail(denominator: &[f64], g: f64) -> Tail {
+    let pole_magnitude = design::polynomial_roots(denominator)
+        .into_iter()
+        .map(|s| ((1.0 + g * s) / (1.0 - g * s)).norm())
+        .fold(0.0, f64::max);
+    decay_tail(pole_magnitude)
+}
+
+/**
+ * Processing the mono buffers of a filter, one sample at a time
+ */
+pub(crate) fn process_filter<F: Filter + ?Sized>(
+    filter: &mut F,
+    inputs: &[&[f64]],
+    outputs: &mut [&mut [f64]],
+) {
+    assert_buffers(1, inputs, outputs);
+    for (x, y) in inputs[0].iter().zip(outputs[0].iter_mut()) {
+        *y = filter.tick(*x);
+    }
+}
+
+/**
+ * Checking the planar buffers of a processor with the given number of channels
+ */
+pub(crate) fn assert_buffers(channels: usize, inputs: &[&[f64]], outputs: &[&mut [f64]]) {
+    assert_eq!(
+        inputs.len(),
+        channels,
+        "expected {} input channels",
+        channels
+    );
+    assert_eq!(
+        outputs.len(),
+        channels,
+        "expected {} output channels",
+        channels
+    );
+    for (input, output) in inputs.iter().zip(outputs.iter()) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+    }
+}
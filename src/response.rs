@@ -0,0 +1,213 @@
+//! Frequency response
+//!
+//! H(e^jw) of biquads and cascades, evaluated at arbitrary frequencies.
+//! Frequencies are in Hz, phases in radians and delays in samples.
+
+use crate::biquad;
+use crate::cascade::Cascade;
+use crate::complex::Complex;
+use std::f64::consts::PI;
+
+/// Just above DC, where the continuous phase is anchored
+const W_EPSILON: f64 = 1e-9;
+
+pub trait FrequencyResponse {
+    /// Complex response H(e^jw), with w = 2π f / fs
+    fn response(&self, f: f64, fs: i32) -> Complex;
+
+    /// Group delay -dφ/dw, in samples
+    fn group_delay(&self, f: f64, fs: i32) -> f64;
+
+    /// Continuous phase, without the 2π jumps of [`phase`](FrequencyResponse::phase)
+    ///
+    /// Anchored just above DC, within (-π, π].
+    fn unwrapped_phase(&self, f: f64, fs: i32) -> f64;
+
+    fn magnitude(&self, f: f64, fs: i32) -> f64 {
+        self.response(f, fs).norm()
+    }
+
+    fn magnitude_db(&self, f: f64, fs: i32) -> f64 {
+        20.0 * self.magnitude(f, fs).log10()
+    }
+
+    /// Phase, wrapped into (-π, π]
+    fn phase(&self, f: f64, fs: i32) -> f64 {
+        self.response(f, fs).arg()
+    }
+
+    /// Phase delay -φ/w, in samples (the limit of the group delay at DC)
+    fn phase_delay(&self, f: f64, fs: i32) -> f64 {
+        if f == 0.0 {
+            return self.group_delay(0.0, fs);
+        }
+        -self.unwrapped_phase(f, fs) / omega(f, fs)
+    }
+}
+
+/**
+ * One point of a frequency response
+ */
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct Point {
+    pub f: f64,
+    pub magnitude_db: f64,
+    /// unwrapped
+    pub phase: f64,
+    pub group_delay: f64,
+}
+
+/**
+ * The response at each of the given frequencies
+ */
+pub fn evaluate<R: FrequencyResponse + ?Sized>(
+    filter: &R,
+    frequencies: &[f64],
+    fs: i32,
+) -> Vec<Point> {
+    frequencies
+        .iter()
+        .map(|f| Point {
+            f: *f,
+            magnitude_db: filter.magnitude_db(*f, fs),
+            phase: filter.unwrapped_phase(*f, fs),
+            group_delay: filter.group_delay(*f, fs),
+        })
+        .collect()
+}
+
+/**
+ * `count` log spaced frequencies from `f_min` to `f_max` (inclusive)
+ */
+pub fn log_frequencies(f_min: f64, f_max: f64, count: usize) -> Vec<f64> {
+    match count {
+        0 => vec![],
+        1 => vec![f_min],
+        _ => {
+            let ratio = (f_max / f_min).ln() / (count - 1) as f64;
+            (0..count)
+                .map(|i| f_min * (ratio * i as f64).exp())
+                .collect()
+        }
+    }
+}
+
+fn omega(f: f64, fs: i32) -> f64 {
+    2.0 * PI * f / fs as f64
+}
+
+/// c0 + c1 z^-1 + c2 z^-2
+fn polynomial(coefficients: [f64; 3], z1: Complex) -> Complex {
+    coefficients[0] + z1 * (coefficients[1] + z1 * coefficients[2])
+}
+
+/// Re(Σ k ck z^-k / Σ ck z^-k): the group delay of a polynomial in z^-1
+fn polynomial_delay(coefficients: [f64; 3], z1: Complex) -> f64 {
+    let derivative = z1 * (coefficients[1] + z1 * (2.0 * coefficients[2]));
+    (derivative / polynomial(coefficients, z1)).re
+}
+
+/// The phase of (1 - r e^-jw), continuous in w
+fn factor_phase(r: Complex, w: f64) -> f64 {
+    if r.norm() <= 1.0 {
+        // positive real part: no jumps
+        (1.0 - r * Complex::unit(-w)).arg()
+    } else {
+        // 1 - r e^-jw = -r e^-jw (1 - e^jw / r)
+        (-r).arg() - w + (1.0 - Complex::unit(w) / r).arg()
+    }
+}
+
+/**
+ * The phase of a section, continuous in w
+ *
+ * Factored into a delay, the sign of the leading coefficient and its zeros and poles.
+ * The 2π offset is arbitrary: it is fixed by the anchoring at DC.
+ */
+fn continuous_phase(params: &biquad::Params, w: f64) -> f64 {
+    let numerator = numerator(params);
+    let delay = match numerator.iter().position(|c| *c != 0.0) {
+        Some(delay) => delay,
+        None => return 0.0,
+    };
+    let sign = if numerator[delay] < 0.0 { PI } else { 0.0 };
+    let zeros: f64 = params.zeros().iter().map(|z| factor_phase(*z, w)).sum();
+    let poles: f64 = params.poles().iter().map(|p| factor_phase(*p, w)).sum();
+    sign - w * delay as f64 + zeros - poles
+}
+
+fn numerator(params: &biquad::Params) -> [f64; 3] {
+    [params.a0, params.a1, params.a2]
+}
+
+fn denominator(params: &biquad::Params) -> [f64; 3] {
+    [1.0, params.b1, params.b2]
+}
+
+/// Anchoring a continuous phase (up to 2π) to the wrapped phase just above DC
+fn anchored<R: FrequencyResponse + ?Sized>(
+    filter: &R,
+    continuous: impl Fn(f64) -> f64,
+    f: f64,
+    fs: i32,
+) -> f64 {
+    let f_epsilon = W_EPSILON * fs as f64 / (2.0 * PI);
+    let offset = filter.phase(f_epsilon, fs) - continuous(W_EPSILON);
+    let offset = 2.0 * PI * (offset / (2.0 * PI)).round();
+    continuous(omega(f, fs)) + offset
+}
+
+impl FrequencyResponse for biquad::Params {
+    fn response(&self, f: f64, fs: i32) -> Complex {
+        let z1 = Complex::unit(-omega(f, fs));
+        polynomial(numerator(self), z1) / polynomial(denominator(self), z1)
+    }
+
+    fn group_delay(&self, f: f64, fs: i32) -> f64 {
+        let z1 = Complex::unit(-omega(f, fs));
+        polynomial_delay(numerator(self), z1) - polynomial_delay(denominator(self), z1)
+    }
+
+    fn unwrapped_phase(&self, f: f64, fs: i32) -> f64 {
+        anchored(self, |w| continuous_phase(self, w), f, fs)
+    }
+}
+
+impl FrequencyResponse for [biquad::Params] {
+    fn response(&self, f: f64, fs: i32) -> Complex {
+        self.iter()
+            .fold(Complex::ONE, |acc, params| acc * params.response(f, fs))
+    }
+
+    fn group_delay(&self, f: f64, fs: i32) -> f64 {
+        self.iter().map(|params| params.group_delay(f, fs)).sum()
+    }
+
+    fn unwrapped_phase(&self, f: f64, fs: i32) -> f64 {
+        let continuous = |w: f64| self.iter().map(|params| continuous_phase(params, w)).sum();
+        anchored(self, continuous, f, fs)
+    }
+}
+
+impl FrequencyResponse for Cascade {
+    fn response(&self, f: f64, fs: i32) -> Complex {
+        self.params().fold(Complex::from(self.gain), |acc, params| {
+            acc * params.response(f, fs)
+        })
+    }
+
+    fn group_delay(&self, f: f64, fs: i32) -> f64 {
+        self.params().map(|params| params.group_delay(f, fs)).sum()
+    }
+
+    fn unwrapped_phase(&self, f: f64, fs: i32) -> f64 {
+        let sign = if self.gain < 0.0 { PI } else { 0.0 };
+        let continuous = |w: f64| {
+            sign + self
+                .params()
+                .map(|params| continuous_phase(params, w))
+                .sum::<f64>()
+        };
+        anchored(self, continuous, f, fs)
+    }
+}
@@ -0,0 +1,331 @@
+//! Sample formats
+//!
+//! The filters work on f64 from -1.0 to 1.0. [`FloatOfMax1`] converts the stored
+//! samples to and from it: floats as they are, and the integer formats of the WAV files
+//! (u8, i8, i16, u16, 24 bits packed or in an i32, i32) with a [`Scaling`].
+//!
+//! Back to integers, the samples are rounded to the nearest and saturate:
+//! values slightly over 1.0 give the max, not a wrapped around negative.
+//! [`Quantizer`] adds an optional dither and noise shaping before the rounding.
+//! [`FloatOfMax1`] has neither a scaling choice nor a dither: to filter integer
+//! samples with them, [`Quantizer::process_block`] is the extra step to go through
+//! (the `process_block_quantized` of the filters).
+
+use crate::dither::{Dither, Ditherer, NoiseShaping};
+
+/**
+ * Representing samples in float from -1.0 to 1.0 range
+ *
+ * Internal representation is in f64.
+ * For example, for an int8 range that goes from -128 to 127
+ * - -127 will give -1.0
+ * - +127 will give +1.0
+ *
+ * The integer formats use the [`Scaling::Symmetric`] convention and are only rounded
+ * back. With another scaling or a dither, use [`Quantizer::process_block`] instead.
+ */
+pub trait FloatOfMax1<T> {
+    fn to_f64(&self) -> f64;
+    #[allow(clippy::wrong_self_convention)]
+    fn from_f64(&self, x: f64) -> T;
+}
+
+impl FloatOfMax1<f64> for f64 {
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    fn from_f64(&self, x: f64) -> f64 {
+        x
+    }
+}
+
+impl FloatOfMax1<f32> for f32 {
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    fn from_f64(&self, x: f64) -> f32 {
+        x as f32
+    }
+}
+
+impl<T: IntegerSample> FloatOfMax1<T> for T {
+    fn to_f64(&self) -> f64 {
+        Scaling::Symmetric.to_f64(*self)
+    }
+
+    fn from_f64(&self, x: f64) -> T {
+        Scaling::Symmetric.from_f64(x)
+    }
+}
+
+/**
+ * How the integers map to -1.0..1.0
+ */
+#[derive(Clone, Copy, Default, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Scaling {
+    /// ±1.0 is ±max: the min integer is slightly below -1.0
+    #[default]
+    Symmetric,
+    /// -1.0 is the min integer (a power of 2): the max is slightly below 1.0
+    Asymmetric,
+}
+
+impl Scaling {
+    /// The integer value of 1.0
+    fn full_scale<T: IntegerSample>(self) -> f64 {
+        match self {
+            Scaling::Symmetric => max::<T>() as f64,
+            Scaling::Asymmetric => -min::<T>() as f64,
+        }
+    }
+
+    pub fn to_f64<T: IntegerSample>(self, sample: T) -> f64 {
+        sample.to_signed() as f64 / self.full_scale::<T>()
+    }
+
+    /**
+     * Rounded to the nearest integer, saturating (NaN gives 0)
+     */
+    pub fn from_f64<T: IntegerSample>(self, x: f64) -> T {
+        saturate(x * self.full_scale::<T>())
+    }
+}
+
+/**
+ * Integer formats, signed or not, as a signed integer centered on 0
+ */
+pub trait IntegerSample: Copy {
+    /// Bit depth
+    const BITS: u32;
+    fn to_signed(self) -> i32;
+    /// From a value in the range of the format
+    fn from_signed(x: i32) -> Self;
+}
+
+fn min<T: IntegerSample>() -> i64 {
+    -(1i64 << (T::BITS - 1))
+}
+
+fn max<T: IntegerSample>() -> i64 {
+    (1i64 << (T::BITS - 1)) - 1
+}
+
+fn saturate<T: IntegerSample>(x: f64) -> T {
+    if x.is_nan() {
+        return T::from_signed(0);
+    }
+    let x = x.round().clamp(min::<T>() as f64, max::<T>() as f64);
+    T::from_signed(x as i32)
+}
+
+impl IntegerSample for i8 {
+    const BITS: u32 = 8;
+
+    fn to_signed(self) -> i32 {
+        self as i32
+    }
+
+    fn from_signed(x: i32) -> i8 {
+        x as i8
+    }
+}
+
+/// 8 bit WAV samples: unsigned, centered on 128
+impl IntegerSample for u8 {
+    const BITS: u32 = 8;
+
+    fn to_signed(self) -> i32 {
+        self as i32 - 128
+    }
+
+    fn from_signed(x: i32) -> u8 {
+        (x + 128) as u8
+    }
+}
+
+impl IntegerSample for i16 {
+    const BITS: u32 = 16;
+
+    fn to_signed(self) -> i32 {
+        self as i32
+    }
+
+    fn from_signed(x: i32) -> i16 {
+        x as i16
+    }
+}
+
+/// Unsigned, centered on 32768
+impl IntegerSample for u16 {
+    const BITS: u32 = 16;
+
+    fn to_signed(self) -> i32 {
+        self as i32 - 32768
+    }
+
+    fn from_signed(x: i32) -> u16 {
+        (x + 32768) as u16
+    }
+}
+
+impl IntegerSample for i32 {
+    const BITS: u32 = 32;
+
+    fn to_signed(self) -> i32 {
+        self
+    }
+
+    fn from_signed(x: i32) -> i32 {
+        x
+    }
+}
+
+/**
+ * 24 bit sample in an i32, as read by hound
+ */
+#[derive(Clone, Copy, Default, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct I24(i32);
+
+impl I24 {
+    pub const MIN: i32 = -(1 << 23);
+    pub const MAX: i32 = (1 << 23) - 1;
+
+    /**
+     * Saturating to the 24 bit range
+     */
+    pub fn new(value: i32) -> Self {
+        I24(value.clamp(I24::MIN, I24::MAX))
+    }
+
+    pub fn value(self) -> i32 {
+        self.0
+    }
+}
+
+impl IntegerSample for I24 {
+    const BITS: u32 = 24;
+
+    fn to_signed(self) -> i32 {
+        self.0
+    }
+
+    fn from_signed(x: i32) -> I24 {
+        I24(x)
+    }
+}
+
+/**
+ * 24 bit sample packed in 3 bytes, little endian (as in the WAV files)
+ */
+#[derive(Clone, Copy, Default, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct I24Packed(pub [u8; 3]);
+
+impl IntegerSample for I24Packed {
+    const BITS: u32 = 24;
+
+    fn to_signed(self) -> i32 {
+        let [b0, b1, b2] = self.0;
+        // sign extension from the 24th bit
+        i32::from_le_bytes([0, b0, b1, b2]) >> 8
+    }
+
+    fn from_signed(x: i32) -> I24Packed {
+        let [b0, b1, b2, _] = x.to_le_bytes();
+        I24Packed([b0, b1, b2])
+    }
+}
+
+impl From<I24> for I24Packed {
+    fn from(sample: I24) -> Self {
+        I24Packed::from_signed(sample.0)
+    }
+}
+
+impl From<I24Packed> for I24 {
+    fn from(sample: I24Packed) -> Self {
+        I24(sample.to_signed())
+    }
+}
+
+/**
+ * Converting f64 samples to an integer format, with an optional dither
+ *
+ * The triangular dither (the sum of two uniform noises of ±0.5 LSB) decorrelates
+ * the rounding error from the signal: low level signals fade into a constant
+ * noise floor instead of distorting. See the [`dither`](crate::dither) module
+ * for the other dithers and the noise shaping.
+ */
+pub struct Quantizer {
+    scaling: Scaling,
+    ditherer: Ditherer,
+}
+
+impl Quantizer {
+    pub fn new(scaling: Scaling) -> Self {
+        Self::with_ditherer(scaling, Ditherer::new(Dither::None, NoiseShaping::None, 1))
+    }
+
+    /**
+     * Same seed, same noise (0 is replaced by 1)
+     */
+    pub fn with_tpdf_dither(scaling: Scaling, seed: u32) -> Self {
+        Self::with_ditherer(
+            scaling,
+            Ditherer::new(Dither::Tpdf, NoiseShaping::None, seed),
+        )
+    }
+
+    pub fn with_ditherer(scaling: Scaling, ditherer: Ditherer) -> Self {
+        Self { scaling, ditherer }
+    }
+
+    pub fn scaling(&self) -> Scaling {
+        self.scaling
+    }
+
+    pub fn ditherer_mut(&mut self) -> &mut Ditherer {
+        &mut self.ditherer
+    }
+
+    /**
+     * From the integer format, with the scaling of the quantizer
+     */
+    pub fn to_f64<T: IntegerSample>(&self, sample: T) -> f64 {
+        self.scaling.to_f64(sample)
+    }
+
+    pub fn quantize<T: IntegerSample>(&mut self, x: f64) -> T {
+        saturate(self.ditherer.quantize(x * self.scaling.full_scale::<T>()))
+    }
+
+    /**
+     * Panics if the input and output lengths differ.
+     */
+    pub fn quantize_block<T: IntegerSample>(&mut self, input: &[f64], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.quantize(*x);
+        }
+    }
+
+    /**
+     * Filtering a block of integer samples with `tick`
+     *
+     * The samples are read with the scaling of the quantizer, and quantized back
+     * with its dither and noise shaping.
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block<T: IntegerSample>(
+        &mut self,
+        input: &[T],
+        output: &mut [T],
+        mut tick: impl FnMut(f64) -> f64,
+    ) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.quantize(tick(self.to_f64(*x)));
+        }
+    }
+}
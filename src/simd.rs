@@ -0,0 +1,469 @@
+//! Independent biquads processed side by side with SIMD
+//!
+//! Each lane is a biquad of its own: the channels of a strip, or the bands of a
+//! filter bank (the same input given to several lanes). The lanes go by groups of
+//! [`LANES`], in one AVX register or two SSE2 ones, picked at runtime with a scalar
+//! fallback on other CPUs.
+//!
+//! Transposed direct form II, with the operations in the same order as
+//! [`biquad::Process`]: the output is bit for bit the one of the scalar filter
+//! (no fused multiply-add).
+
+use crate::biquad;
+use crate::processor::{self, Processor};
+
+/// Biquads per group (f64 lanes of an AVX register)
+pub const LANES: usize = 4;
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Backend {
+    Scalar,
+    /// 2 lanes per register
+    Sse2,
+    /// 4 lanes per register
+    Avx,
+}
+
+impl Backend {
+    /**
+     * The widest backend of the running CPU
+     */
+    pub fn detect() -> Backend {
+        [Backend::Avx, Backend::Sse2]
+            .iter()
+            .cloned()
+            .find(|backend| backend.is_supported())
+            .unwrap_or(Backend::Scalar)
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Scalar => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx => is_x86_feature_detected!("avx"),
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            _ => false,
+        }
+    }
+}
+
+/// Coefficients and state per lane, padded to a multiple of [`LANES`]
+#[derive(Default)]
+struct Lanes {
+    a0: Vec<f64>,
+    a1: Vec<f64>,
+    a2: Vec<f64>,
+    b1: Vec<f64>,
+    b2: Vec<f64>,
+    s1: Vec<f64>,
+    s2: Vec<f64>,
+}
+
+pub struct SimdProcess {
+    channels: usize,
+    backend: Backend,
+    lanes: Lanes,
+}
+
+impl SimdProcess {
+    /**
+     * One lane per params, on the widest backend of the CPU
+     */
+    pub fn new(params: &[biquad::Params]) -> Self {
+        Self::with_backend(params, Backend::detect())
+    }
+
+    /**
+     * Panics if the backend is not supported by the CPU
+     */
+    pub fn with_backend(params: &[biquad::Params], backend: Backend) -> Self {
+        assert!(
+            backend.is_supported(),
+            "simd: unsupported backend {:?}",
+            backend
+        );
+        let padded = params.len().div_ceil(LANES) * LANES;
+        let zeros = vec![0.0; padded];
+        let mut process = Self {
+            channels: params.len(),
+            backend,
+            lanes: Lanes {
+                a0: zeros.clone(),
+                a1: zeros.clone(),
+                a2: zeros.clone(),
+                b1: zeros.clone(),
+                b2: zeros.clone(),
+                s1: zeros.clone(),
+                s2: zeros,
+            },
+        };
+        for (lane, params) in params.iter().enumerate() {
+            process.set_params(lane, *params);
+        }
+        process
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn params(&self, lane: usize) -> biquad::Params {
+        let lanes = &self.lanes;
+        biquad::Params {
+            a0: lanes.a0[lane],
+            a1: lanes.a1[lane],
+            a2: lanes.a2[lane],
+            b1: lanes.b1[lane],
+            b2: lanes.b2[lane],
+        }
+    }
+
+    /**
+     * Panics if the lane is out of range
+     */
+    pub fn set_params(&mut self, lane: usize, params: biquad::Params) {
+        assert!(lane < self.channels, "simd: no lane {}", lane);
+        let lanes = &mut self.lanes;
+        lanes.a0[lane] = params.a0;
+        lanes.a1[lane] = params.a1;
+        lanes.a2[lane] = params.a2;
+        lanes.b1[lane] = params.b1;
+        lanes.b2[lane] = params.b2;
+    }
+
+    /**
+     * Clearing the memory of all the lanes
+     */
+    pub fn reset(&mut self) {
+        for s in self.lanes.s1.iter_mut().chain(self.lanes.s2.iter_mut()) {
+            *s = 0.0;
+        }
+    }
+
+    /**
+     * Processing interleaved frames: one sample per lane, lane after lane
+     *
+     * Panics if the lengths differ or are not a multiple of the channel count.
+     */
+    pub fn process_interleaved(&mut self, input: &[f64], output: &mut [f64]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        self.assert_frames(input.len());
+        let channels = self.channels;
+        for group in 0..self.groups() {
+            let mut frames = Interleaved {
+                input,
+                output: &mut *output,
+                channels,
+                offset: group * LANES,
+            };
+            self.process_group(group, &mut frames);
+        }
+    }
+
+    pub fn process_interleaved_in_place(&mut self, samples: &mut [f64]) {
+        self.assert_frames(samples.len());
+        let channels = self.channels;
+        for group in 0..self.groups() {
+            let mut frames = InterleavedInPlace {
+                samples: &mut *samples,
+                channels,
+                offset: group * LANES,
+            };
+            self.process_group(group, &mut frames);
+        }
+    }
+
+    /**
+     * Processing planar buffers: one input and one output slice per lane
+     *
+     * For a filter bank, give the same input slice to all the lanes.
+     * Panics if the number of slices is not the channel count, or if their lengths differ.
+     */
+    pub fn process_planar(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(self.channels, inputs, outputs);
+        let length = inputs.first().map_or(0, |input| input.len());
+        assert!(
+            inputs.iter().all(|input| input.len() == length),
+            "simd: lanes of different lengths"
+        );
+        for group in 0..self.groups() {
+            let lanes = group * LANES..(self.channels).min((group + 1) * LANES);
+            let mut frames = Planar {
+                inputs: &inputs[lanes.clone()],
+                outputs: &mut outputs[lanes],
+                length,
+            };
+            self.process_group(group, &mut frames);
+        }
+    }
+
+    fn groups(&self) -> usize {
+        self.lanes.a0.len() / LANES
+    }
+
+    fn assert_frames(&self, length: usize) {
+        assert!(
+            self.channels == 0 || length.is_multiple_of(self.channels),
+            "interleaved length {} is not a multiple of {} channels",
+            length,
+            self.channels
+        );
+    }
+
+    fn process_group<F: Frames>(&mut self, group: usize, frames: &mut F) {
+        match self.backend {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            // safe: the backend is supported, checked at construction
+            Backend::Avx => unsafe { x86::process_avx(&mut self.lanes, group, frames) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Sse2 => unsafe { x86::process_sse2(&mut self.lanes, group, frames) },
+            _ => process_scalar(&mut self.lanes, group, frames),
+        }
+    }
+}
+
+/**
+ * Access to the samples of a group of lanes, frame by frame
+ *
+ * The lanes past the channel count are left alone: read as 0, never written.
+ */
+trait Frames {
+    fn count(&self) -> usize;
+    fn read(&self, frame: usize, x: &mut [f64; LANES]);
+    fn write(&mut self, frame: usize, y: &[f64; LANES]);
+}
+
+/// Copying the lanes of a frame, with a fixed size (no call to memcpy) for full groups
+#[inline(always)]
+fn copy_lanes(source: &[f64], destination: &mut [f64]) {
+    if source.len() == LANES && destination.len() == LANES {
+        destination[..LANES].copy_from_slice(&source[..LANES]);
+    } else {
+        for (d, s) in destination.iter_mut().zip(source.iter()) {
+            *d = *s;
+        }
+    }
+}
+
+struct Interleaved<'a> {
+    input: &'a [f64],
+    output: &'a mut [f64],
+    channels: usize,
+    /// First lane of the group
+    offset: usize,
+}
+
+impl Frames for Interleaved<'_> {
+    fn count(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    #[inline(always)]
+    fn read(&self, frame: usize, x: &mut [f64; LANES]) {
+        let lanes = LANES.min(self.channels - self.offset);
+        let start = frame * self.channels + self.offset;
+        copy_lanes(&self.input[start..start + lanes], x);
+    }
+
+    #[inline(always)]
+    fn write(&mut self, frame: usize, y: &[f64; LANES]) {
+        let lanes = LANES.min(self.channels - self.offset);
+        let start = frame * self.channels + self.offset;
+        copy_lanes(&y[..lanes], &mut self.output[start..start + lanes]);
+    }
+}
+
+struct InterleavedInPlace<'a> {
+    samples: &'a mut [f64],
+    channels: usize,
+    offset: usize,
+}
+
+impl Frames for InterleavedInPlace<'_> {
+    fn count(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    #[inline(always)]
+    fn read(&self, frame: usize, x: &mut [f64; LANES]) {
+        let lanes = LANES.min(self.channels - self.offset);
+        let start = frame * self.channels + self.offset;
+        copy_lanes(&self.samples[start..start + lanes], x);
+    }
+
+    #[inline(always)]
+    fn write(&mut self, frame: usize, y: &[f64; LANES]) {
+        let lanes = LANES.min(self.channels - self.offset);
+        let start = frame * self.channels + self.offset;
+        copy_lanes(&y[..lanes], &mut self.samples[start..start + lanes]);
+    }
+}
+
+struct Planar<'a, 'b, 'c> {
+    /// The slices of the group's lanes
+    inputs: &'a [&'b [f64]],
+    outputs: &'a mut [&'c mut [f64]],
+    length: usize,
+}
+
+impl Frames for Planar<'_, '_, '_> {
+    fn count(&self) -> usize {
+        self.length
+    }
+
+    #[inline(always)]
+    fn read(&self, frame: usize, x: &mut [f64; LANES]) {
+        for (x, input) in x.iter_mut().zip(self.inputs.iter()) {
+            *x = input[frame];
+        }
+    }
+
+    #[inline(always)]
+    fn write(&mut self, frame: usize, y: &[f64; LANES]) {
+        for (y, output) in y.iter().zip(self.outputs.iter_mut()) {
+            output[frame] = *y;
+        }
+    }
+}
+
+fn process_scalar<F: Frames>(lanes: &mut Lanes, group: usize, frames: &mut F) {
+    let mut x = [0.0; LANES];
+    let mut y = [0.0; LANES];
+    for frame in 0..frames.count() {
+        frames.read(frame, &mut x);
+        for lane in 0..LANES {
+            let i = group * LANES + lane;
+            let out = lanes.a0[i] * x[lane] + lanes.s1[i];
+            lanes.s1[i] = lanes.a1[i] * x[lane] - lanes.b1[i] * out + lanes.s2[i];
+            lanes.s2[i] = lanes.a2[i] * x[lane] - lanes.b2[i] * out;
+            y[lane] = out;
+        }
+        frames.write(frame, &y);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    use super::{Frames, Lanes, LANES};
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    /**
+     * The state stays in registers for the whole block
+     *
+     * Safety: the CPU must support AVX.
+     */
+    #[target_feature(enable = "avx")]
+    pub(super) unsafe fn process_avx<F: Frames>(lanes: &mut Lanes, group: usize, frames: &mut F) {
+        let i = group * LANES;
+        let a0 = _mm256_loadu_pd(lanes.a0[i..].as_ptr());
+        let a1 = _mm256_loadu_pd(lanes.a1[i..].as_ptr());
+        let a2 = _mm256_loadu_pd(lanes.a2[i..].as_ptr());
+        let b1 = _mm256_loadu_pd(lanes.b1[i..].as_ptr());
+        let b2 = _mm256_loadu_pd(lanes.b2[i..].as_ptr());
+        let mut s1 = _mm256_loadu_pd(lanes.s1[i..].as_ptr());
+        let mut s2 = _mm256_loadu_pd(lanes.s2[i..].as_ptr());
+
+        let mut x = [0.0; LANES];
+        let mut y = [0.0; LANES];
+        for frame in 0..frames.count() {
+            frames.read(frame, &mut x);
+            let xv = _mm256_loadu_pd(x.as_ptr());
+            let out = _mm256_add_pd(_mm256_mul_pd(a0, xv), s1);
+            s1 = _mm256_add_pd(
+                _mm256_sub_pd(_mm256_mul_pd(a1, xv), _mm256_mul_pd(b1, out)),
+                s2,
+            );
+            s2 = _mm256_sub_pd(_mm256_mul_pd(a2, xv), _mm256_mul_pd(b2, out));
+            _mm256_storeu_pd(y.as_mut_ptr(), out);
+            frames.write(frame, &y);
+        }
+
+        _mm256_storeu_pd(lanes.s1[i..].as_mut_ptr(), s1);
+        _mm256_storeu_pd(lanes.s2[i..].as_mut_ptr(), s2);
+    }
+
+    /**
+     * Two registers of two lanes
+     *
+     * Safety: the CPU must support SSE2.
+     */
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn process_sse2<F: Frames>(lanes: &mut Lanes, group: usize, frames: &mut F) {
+        let i = group * LANES;
+        let load = |v: &[f64]| {
+            (
+                _mm_loadu_pd(v[i..].as_ptr()),
+                _mm_loadu_pd(v[i + 2..].as_ptr()),
+            )
+        };
+        let (a0_lo, a0_hi) = load(&lanes.a0);
+        let (a1_lo, a1_hi) = load(&lanes.a1);
+        let (a2_lo, a2_hi) = load(&lanes.a2);
+        let (b1_lo, b1_hi) = load(&lanes.b1);
+        let (b2_lo, b2_hi) = load(&lanes.b2);
+        let (mut s1_lo, mut s1_hi) = load(&lanes.s1);
+        let (mut s2_lo, mut s2_hi) = load(&lanes.s2);
+
+        let mut x = [0.0; LANES];
+        let mut y = [0.0; LANES];
+        for frame in 0..frames.count() {
+            frames.read(frame, &mut x);
+            let x_lo = _mm_loadu_pd(x.as_ptr());
+            let x_hi = _mm_loadu_pd(x[2..].as_ptr());
+
+            let out_lo = _mm_add_pd(_mm_mul_pd(a0_lo, x_lo), s1_lo);
+            let out_hi = _mm_add_pd(_mm_mul_pd(a0_hi, x_hi), s1_hi);
+            s1_lo = _mm_add_pd(
+                _mm_sub_pd(_mm_mul_pd(a1_lo, x_lo), _mm_mul_pd(b1_lo, out_lo)),
+                s2_lo,
+            );
+            s1_hi = _mm_add_pd(
+                _mm_sub_pd(_mm_mul_pd(a1_hi, x_hi), _mm_mul_pd(b1_hi, out_hi)),
+                s2_hi,
+            );
+            s2_lo = _mm_sub_pd(_mm_mul_pd(a2_lo, x_lo), _mm_mul_pd(b2_lo, out_lo));
+            s2_hi = _mm_sub_pd(_mm_mul_pd(a2_hi, x_hi), _mm_mul_pd(b2_hi, out_hi));
+
+            _mm_storeu_pd(y.as_mut_ptr(), out_lo);
+            _mm_storeu_pd(y[2..].as_mut_ptr(), out_hi);
+            frames.write(frame, &y);
+        }
+
+        _mm_storeu_pd(lanes.s1[i..].as_mut_ptr(), s1_lo);
+        _mm_storeu_pd(lanes.s1[i + 2..].as_mut_ptr(), s1_hi);
+        _mm_storeu_pd(lanes.s2[i..].as_mut_ptr(), s2_lo);
+        _mm_storeu_pd(lanes.s2[i + 2..].as_mut_ptr(), s2_hi);
+    }
+}
+
+impl Processor for SimdProcess {
+    fn reset(&mut self) {
+        SimdProcess::reset(self)
+    }
+
+    /// Raw coefficients: kept as they are
+    fn set_sample_rate(&mut self, _fs: i32) {}
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        self.process_planar(inputs, outputs);
+    }
+
+    fn tail(&self) -> processor::Tail {
+        (0..self.channels).fold(processor::Tail::Samples(0), |tail, lane| {
+            tail.max(processor::decay_tail(self.params(lane).pole_magnitude()))
+        })
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+}
@@ -0,0 +1,230 @@
+//! Click-free parameter changes
+//!
+//! Swapping the coefficients of a running biquad makes zipper noise and transients.
+//! [`SmoothedProcess`] ramps the filter params towards their target instead,
+//! recomputing the coefficients at a control rate:
+//! - fc in the log domain, so that a ramp across decades sounds even
+//! - gain in dB
+//! - q linearly
+//!
+//! The direct form I topology (the default) is the most forgiving with changing
+//! coefficients: its state holds only past inputs and outputs.
+
+use crate::biquad;
+use crate::filter;
+use crate::processor::{self, Processor};
+use crate::sample::FloatOfMax1;
+
+/// Default ramp duration, in seconds
+pub const DEFAULT_RAMP_TIME: f64 = 0.02;
+/// Default number of samples between two coefficient updates
+pub const DEFAULT_CONTROL_PERIOD: usize = 16;
+
+pub struct SmoothedProcess {
+    process: biquad::Process,
+    filter_type: filter::Type,
+    fs: i32,
+    current: filter::Params,
+    target: filter::Params,
+    ramp_time: f64,
+    control_period: usize,
+    /// Per control step: ln(fc), q and gain_db increments
+    step: filter::Params,
+    /// Control steps left before reaching the target
+    steps_left: usize,
+    /// Samples left before the next control step
+    countdown: usize,
+}
+
+impl SmoothedProcess {
+    pub fn new(filter_params: filter::Params, filter_type: filter::Type, fs: i32) -> Self {
+        Self::with_topology(filter_params, filter_type, fs, biquad::Topology::default())
+    }
+
+    pub fn with_topology(
+        filter_params: filter::Params,
+        filter_type: filter::Type,
+        fs: i32,
+        topology: biquad::Topology,
+    ) -> Self {
+        let params = clamped(filter_params, fs);
+        Self {
+            process: biquad::Process::with_topology(
+                biquad::Params::from_audio_filter_params(params, filter_type, fs),
+                topology,
+            ),
+            filter_type,
+            fs,
+            current: params,
+            target: params,
+            ramp_time: DEFAULT_RAMP_TIME,
+            control_period: DEFAULT_CONTROL_PERIOD,
+            step: filter::Params {
+                fc: 0.0,
+                q: 0.0,
+                gain_db: 0.0,
+            },
+            steps_left: 0,
+            countdown: 0,
+        }
+    }
+
+    /**
+     * Ramp duration (in seconds) and number of samples between coefficient updates
+     *
+     * Applies to the next [`set_target`](SmoothedProcess::set_target).
+     * Panics if the control period is 0 or the ramp time is negative.
+     */
+    pub fn set_smoothing(&mut self, ramp_time: f64, control_period: usize) {
+        assert!(control_period > 0, "smoothing: control period of 0");
+        assert!(
+            ramp_time >= 0.0,
+            "smoothing: negative ramp time {}",
+            ramp_time
+        );
+        self.ramp_time = ramp_time;
+        self.control_period = control_period;
+    }
+
+    /// Params in use, possibly halfway through a ramp
+    pub fn current(&self) -> filter::Params {
+        self.current
+    }
+
+    pub fn target(&self) -> filter::Params {
+        self.target
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.steps_left > 0
+    }
+
+    pub fn params(&self) -> biquad::Params {
+        *self.process.params()
+    }
+
+    /**
+     * Ramping towards new params
+     *
+     * The params are clamped as in [`biquad::Params::from_audio_filter_params`].
+     * A zero ramp time applies them at the next sample.
+     */
+    pub fn set_target(&mut self, filter_params: filter::Params) {
+        self.target = clamped(filter_params, self.fs);
+        let ramp_samples = (self.ramp_time * self.fs as f64).round() as usize;
+        let steps = ramp_samples.div_ceil(self.control_period).max(1);
+        self.step = filter::Params {
+            fc: (self.target.fc.ln() - self.current.fc.ln()) / steps as f64,
+            q: (self.target.q - self.current.q) / steps as f64,
+            gain_db: (self.target.gain_db - self.current.gain_db) / steps as f64,
+        };
+        self.steps_left = steps;
+        self.countdown = 0;
+    }
+
+    /**
+     * Jumping to new params, without ramp (for initialization, not while playing)
+     */
+    pub fn set_params_immediately(&mut self, filter_params: filter::Params) {
+        self.target = clamped(filter_params, self.fs);
+        self.current = self.target;
+        self.steps_left = 0;
+        self.update_coefficients();
+    }
+
+    /**
+     * Clearing the filter memory and finishing any ramp
+     */
+    pub fn reset(&mut self) {
+        self.process.reset();
+        if self.is_smoothing() {
+            self.set_params_immediately(self.target);
+        }
+    }
+
+    pub fn tick(&mut self, x: f64) -> f64 {
+        if self.steps_left > 0 {
+            if self.countdown == 0 {
+                self.control_step();
+                self.countdown = self.control_period;
+            }
+            self.countdown -= 1;
+        }
+        self.process.tick(x)
+    }
+
+    /**
+     * Processing a block of samples
+     *
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block<T: FloatOfMax1<T>>(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = x.from_f64(self.tick(x.to_f64()));
+        }
+    }
+
+    fn control_step(&mut self) {
+        self.steps_left -= 1;
+        self.current = if self.steps_left == 0 {
+            // no accumulated rounding errors at the end of the ramp
+            self.target
+        } else {
+            filter::Params {
+                fc: (self.current.fc.ln() + self.step.fc).exp(),
+                q: self.current.q + self.step.q,
+                gain_db: self.current.gain_db + self.step.gain_db,
+            }
+        };
+        self.update_coefficients();
+    }
+
+    fn update_coefficients(&mut self) {
+        self.process
+            .set_params(biquad::Params::from_audio_filter_params(
+                self.current,
+                self.filter_type,
+                self.fs,
+            ));
+    }
+}
+
+/// The params actually used by the design, so that fc can be ramped in log
+fn clamped(filter_params: filter::Params, fs: i32) -> filter::Params {
+    if fs <= 0 || filter_params.fc.is_nan() {
+        // passthrough either way
+        filter_params
+    } else {
+        filter_params.clamped(fs)
+    }
+}
+
+impl filter::Filter for SmoothedProcess {
+    fn tick(&mut self, x: f64) -> f64 {
+        SmoothedProcess::tick(self, x)
+    }
+}
+
+impl Processor for SmoothedProcess {
+    fn reset(&mut self) {
+        SmoothedProcess::reset(self)
+    }
+
+    /**
+     * Jumping to the target params, redesigned (and clamped) for the new sample rate
+     */
+    fn set_sample_rate(&mut self, fs: i32) {
+        self.fs = fs;
+        self.set_params_immediately(self.target);
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(1, inputs, outputs);
+        SmoothedProcess::process_block(self, inputs[0], outputs[0]);
+    }
+
+    fn tail(&self) -> processor::Tail {
+        self.process.tail()
+    }
+}
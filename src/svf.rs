@@ -0,0 +1,270 @@
+//! State variable filters, zero delay feedback (topology preserving transform)
+//!
+//! Two trapezoidal integrators in a loop, solved without the unit delay in the feedback.
+//! The state is the integrators' memory, not past outputs, so the cutoff and the
+//! damping can change at every sample without the transients of a biquad.
+//! Tuned with the same prewarped frequency, each type has the response of the
+//! corresponding biquad.
+//!
+//! Credits: Andrew Simper, https://cytomic.com/files/dsp/SvfLinearTrapOptimised2.pdf
+//! and Robin Schmidt (see docs/dsp-reading.org)
+
+use crate::filter;
+use crate::processor::{self, Processor};
+use crate::sample::FloatOfMax1;
+use std::f64::consts::{PI, SQRT_2};
+
+/**
+ * Tuning and output mix
+ *
+ * The output is m0 * input + m1 * band + m2 * low,
+ * with band the (non normalized) band pass and low the low pass outputs.
+ */
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct Params {
+    /// Integrator gain, tan(π fc / fs)
+    pub g: f64,
+    /// Damping, 1/Q
+    pub k: f64,
+    pub m0: f64,
+    pub m1: f64,
+    pub m2: f64,
+}
+
+impl Params {
+    /**
+     * Same clamping and responses as [`biquad::Params::from_audio_filter_params`](crate::biquad::Params::from_audio_filter_params)
+     *
+     * For the cuts of the peak and shelves, the poles move: the filter is tuned
+     * at another frequency or damping than the given ones.
+     */
+    pub fn from_audio_filter_params(
+        filter_params: filter::Params,
+        filter_type: filter::Type,
+        fs: i32,
+    ) -> Params {
+        if fs <= 0 || filter_params.fc.is_nan() {
+            return Params::default();
+        }
+        let filter_params = filter_params.clamped(fs);
+        let g = (PI * filter_params.fc / fs as f64).tan();
+        let k = 1.0 / filter_params.q;
+        let v = 10.0f64.powf(filter_params.gain_db.abs() / 20.0);
+        let boost = filter_params.gain_db >= 0.0;
+
+        let params = |g, k, m0, m1, m2| Params { g, k, m0, m1, m2 };
+        match filter_type {
+            filter::Type::LowPass => params(g, k, 0.0, 0.0, 1.0),
+            filter::Type::HighPass => params(g, k, 1.0, -k, -1.0),
+            filter::Type::BandPass => params(g, k, 0.0, k, 0.0),
+            filter::Type::Notch => params(g, k, 1.0, -k, 0.0),
+            filter::Type::Peak if boost => params(g, k, 1.0, k * (v - 1.0), 0.0),
+            filter::Type::Peak => params(g, k * v, 1.0, k * (1.0 - v), 0.0),
+            // fixed Q of 1/sqrt(2), as the biquad shelves
+            filter::Type::LowShelf if boost => {
+                params(g, SQRT_2, 1.0, (2.0 * v).sqrt() - SQRT_2, v - 1.0)
+            }
+            filter::Type::LowShelf => params(
+                g * v.sqrt(),
+                SQRT_2,
+                1.0,
+                (2.0 / v).sqrt() - SQRT_2,
+                1.0 / v - 1.0,
+            ),
+            filter::Type::HighShelf if boost => {
+                params(g, SQRT_2, v, (2.0 * v).sqrt() - SQRT_2 * v, 1.0 - v)
+            }
+            filter::Type::HighShelf => params(
+                g / v.sqrt(),
+                SQRT_2,
+                1.0 / v,
+                (2.0 / v).sqrt() - SQRT_2 / v,
+                1.0 - 1.0 / v,
+            ),
+        }
+    }
+}
+
+impl Default for Params {
+    /// Passthrough
+    fn default() -> Self {
+        Params {
+            g: 0.0,
+            k: SQRT_2,
+            m0: 1.0,
+            m1: 0.0,
+            m2: 0.0,
+        }
+    }
+}
+
+/**
+ * All the outputs of one sample, as tuned by g and k (the mix is ignored)
+ */
+#[derive(Clone, Copy, Default, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct Outputs {
+    pub low_pass: f64,
+    /// Unity gain at fc
+    pub band_pass: f64,
+    pub high_pass: f64,
+    pub notch: f64,
+    /// Low minus high pass: the resonance, not the bell of [`filter::Type::Peak`]
+    pub peak: f64,
+    pub all_pass: f64,
+}
+
+/**
+ * Processing samples, the params can change at every sample
+ */
+pub struct Process {
+    params: Params,
+    // integrators' memory
+    ic1eq: f64,
+    ic2eq: f64,
+    /// What the params were designed from, to redesign them at another sample rate
+    design: Option<(filter::Params, filter::Type)>,
+}
+
+impl Process {
+    pub fn new(params: Params) -> Self {
+        Self {
+            params,
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+            design: None,
+        }
+    }
+
+    /**
+     * Designed as [`Params::from_audio_filter_params`]
+     *
+     * Unlike the processes of raw params, it is redesigned by
+     * [`Processor::set_sample_rate`].
+     */
+    pub fn from_audio_filter_params(
+        filter_params: filter::Params,
+        filter_type: filter::Type,
+        fs: i32,
+    ) -> Self {
+        let mut process = Self::new(Params::from_audio_filter_params(
+            filter_params,
+            filter_type,
+            fs,
+        ));
+        process.design = Some((filter_params, filter_type));
+        process
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /**
+     * Replacing the params, keeping the integrators' memory
+     *
+     * The process is then one of raw params: kept by [`Processor::set_sample_rate`].
+     */
+    pub fn set_params(&mut self, params: Params) {
+        self.params = params;
+        self.design = None;
+    }
+
+    /**
+     * Clearing the filter memory
+     */
+    pub fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+
+    /**
+     * Solving the loop for one sample: (band, low)
+     */
+    #[inline(always)]
+    fn solve(&mut self, x: f64) -> (f64, f64) {
+        let Params { g, k, .. } = self.params;
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let v3 = x - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+        (v1, v2)
+    }
+
+    /**
+     * The output mixed as set by the params
+     */
+    pub fn tick(&mut self, x: f64) -> f64 {
+        let (band, low) = self.solve(x);
+        self.params.m0 * x + self.params.m1 * band + self.params.m2 * low
+    }
+
+    /**
+     * All the outputs at once
+     */
+    pub fn tick_outputs(&mut self, x: f64) -> Outputs {
+        let (band, low) = self.solve(x);
+        let k = self.params.k;
+        let high = x - k * band - low;
+        Outputs {
+            low_pass: low,
+            band_pass: k * band,
+            high_pass: high,
+            notch: x - k * band,
+            peak: low - high,
+            all_pass: x - 2.0 * k * band,
+        }
+    }
+
+    /**
+     * Processing a block of samples
+     *
+     * Panics if the input and output lengths differ.
+     */
+    pub fn process_block<T: FloatOfMax1<T>>(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input/output length mismatch");
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = x.from_f64(self.tick(x.to_f64()));
+        }
+    }
+
+    pub fn process_in_place<T: FloatOfMax1<T>>(&mut self, samples: &mut [T]) {
+        for sample in samples.iter_mut() {
+            *sample = sample.from_f64(self.tick(sample.to_f64()));
+        }
+    }
+}
+
+impl filter::Filter for Process {
+    fn tick(&mut self, x: f64) -> f64 {
+        Process::tick(self, x)
+    }
+}
+
+impl Processor for Process {
+    fn reset(&mut self) {
+        Process::reset(self)
+    }
+
+    /**
+     * Redesigning the params if they come from a design, keeping raw params
+     */
+    fn set_sample_rate(&mut self, fs: i32) {
+        if let Some((filter_params, filter_type)) = self.design {
+            self.params = Params::from_audio_filter_params(filter_params, filter_type, fs);
+        }
+    }
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        processor::assert_buffers(1, inputs, outputs);
+        Process::process_block(self, inputs[0], outputs[0]);
+    }
+
+    fn tail(&self) -> processor::Tail {
+        processor::analog_decay_tail(&[1.0, self.params.k, 1.0], self.params.g)
+    }
+}
@@ -0,0 +1,304 @@
+//! WAV files, behind the `wav` feature (on by default)
+//!
+//! Any channel count, sample rate and bit depth (8, 16, 24, 32 bits integers,
+//! 32 bits floats), converted to and from planar f64 buffers with the
+//! [`sample`](crate::sample) module: the integers use the symmetric scaling and
+//! are rounded (or dithered with the writer's [`Quantizer`]s, one per channel).
+//!
+//! The files are read and written in blocks, so that large files can be streamed
+//! through a [`Processor`] without being loaded in memory.
+
+use crate::error::WavError;
+use crate::processor::Processor;
+use crate::sample::{Quantizer, Scaling, I24};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/**
+ * Sample format of the file
+ */
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub enum Format {
+    /// Unsigned in the file, centered on 128
+    Int8,
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+}
+
+impl Format {
+    pub fn bits(self) -> u16 {
+        match self {
+            Format::Int8 => 8,
+            Format::Int16 => 16,
+            Format::Int24 => 24,
+            Format::Int32 | Format::Float32 => 32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, std::cmp::PartialEq, std::fmt::Debug)]
+pub struct Spec {
+    pub channels: usize,
+    pub sample_rate: i32,
+    pub format: Format,
+}
+
+impl Spec {
+    fn from_hound(spec: hound::WavSpec) -> Result<Self, WavError> {
+        let float = spec.sample_format == hound::SampleFormat::Float;
+        let format = match (spec.bits_per_sample, float) {
+            (8, false) => Format::Int8,
+            (16, false) => Format::Int16,
+            (24, false) => Format::Int24,
+            (32, false) => Format::Int32,
+            (32, true) => Format::Float32,
+            (bits, float) => return Err(WavError::UnsupportedFormat { bits, float }),
+        };
+        Ok(Spec {
+            channels: spec.channels as usize,
+            sample_rate: spec.sample_rate as i32,
+            format,
+        })
+    }
+
+    fn to_hound(self) -> Result<hound::WavSpec, WavError> {
+        if self.channels == 0 || self.channels > u16::MAX as usize {
+            return Err(WavError::InvalidChannelCount {
+                channels: self.channels,
+            });
+        }
+        if self.sample_rate <= 0 {
+            return Err(WavError::InvalidSampleRate {
+                fs: self.sample_rate,
+            });
+        }
+        Ok(hound::WavSpec {
+            channels: self.channels as u16,
+            sample_rate: self.sample_rate as u32,
+            bits_per_sample: self.format.bits(),
+            sample_format: match self.format {
+                Format::Float32 => hound::SampleFormat::Float,
+                _ => hound::SampleFormat::Int,
+            },
+        })
+    }
+}
+
+fn check_channels(channels: usize, expected: usize) -> Result<(), WavError> {
+    if channels != expected {
+        return Err(WavError::ChannelMismatch { channels, expected });
+    }
+    Ok(())
+}
+
+pub struct Reader {
+    inner: hound::WavReader<BufReader<File>>,
+    spec: Spec,
+}
+
+impl Reader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, WavError> {
+        let inner = hound::WavReader::open(path)?;
+        let spec = Spec::from_hound(inner.spec())?;
+        Ok(Self { inner, spec })
+    }
+
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// Length of the file, in frames
+    pub fn frames(&self) -> usize {
+        self.inner.duration() as usize
+    }
+
+    /**
+     * Reading the next frames in one buffer per channel
+     *
+     * Returns the number of frames read: the length of the shortest buffer,
+     * less at the end of the file, 0 once it's over.
+     */
+    pub fn read_block(&mut self, buffers: &mut [&mut [f64]]) -> Result<usize, WavError> {
+        let channels = self.spec.channels;
+        check_channels(buffers.len(), channels)?;
+        let frames = buffers.iter().map(|b| b.len()).min().unwrap_or(0);
+        let format = self.spec.format;
+
+        let mut count = 0;
+        if format == Format::Float32 {
+            for sample in self.inner.samples::<f32>().take(frames * channels) {
+                buffers[count % channels][count / channels] = sample? as f64;
+                count += 1;
+            }
+        } else {
+            for sample in self.inner.samples::<i32>().take(frames * channels) {
+                let sample = sample?;
+                buffers[count % channels][count / channels] = match format {
+                    Format::Int8 => Scaling::Symmetric.to_f64(sample as i8),
+                    Format::Int16 => Scaling::Symmetric.to_f64(sample as i16),
+                    Format::Int24 => Scaling::Symmetric.to_f64(I24::new(sample)),
+                    _ => Scaling::Symmetric.to_f64(sample),
+                };
+                count += 1;
+            }
+        }
+        Ok(count / channels)
+    }
+
+    /**
+     * The rest of the file, one buffer per channel
+     */
+    pub fn read_all(&mut self) -> Result<Vec<Vec<f64>>, WavError> {
+        let frames = self.frames();
+        let mut buffers = vec![vec![0.0; frames]; self.spec.channels];
+        let read = {
+            let mut slices: Vec<&mut [f64]> = buffers.iter_mut().map(|b| &mut b[..]).collect();
+            self.read_block(&mut slices)?
+        };
+        for buffer in buffers.iter_mut() {
+            buffer.truncate(read);
+        }
+        Ok(buffers)
+    }
+}
+
+pub struct Writer {
+    inner: hound::WavWriter<BufWriter<File>>,
+    spec: Spec,
+    /// One per channel: the noise shaping feeds back the errors of its channel
+    quantizers: Vec<Quantizer>,
+}
+
+impl Writer {
+    /**
+     * Rounding the integer samples, without dither
+     */
+    pub fn create<P: AsRef<Path>>(path: P, spec: Spec) -> Result<Self, WavError> {
+        let inner = hound::WavWriter::create(path, spec.to_hound()?)?;
+        Ok(Self {
+            inner,
+            spec,
+            quantizers: (0..spec.channels)
+                .map(|_| Quantizer::new(Scaling::Symmetric))
+                .collect(),
+        })
+    }
+
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /**
+     * For the dither and the noise shaping of the integer samples of a channel
+     *
+     * Panics if the channel is out of range.
+     */
+    pub fn set_quantizer(&mut self, channel: usize, quantizer: Quantizer) {
+        assert!(channel < self.spec.channels, "wav: no channel {}", channel);
+        self.quantizers[channel] = quantizer;
+    }
+
+    /**
+     * Writing one buffer per channel, up to the length of the shortest
+     */
+    pub fn write_block(&mut self, buffers: &[&[f64]]) -> Result<(), WavError> {
+        check_channels(buffers.len(), self.spec.channels)?;
+        let frames = buffers.iter().map(|b| b.len()).min().unwrap_or(0);
+        for frame in 0..frames {
+            for (buffer, quantizer) in buffers.iter().zip(self.quantizers.iter_mut()) {
+                let x = buffer[frame];
+                match self.spec.format {
+                    Format::Int8 => self.inner.write_sample(quantizer.quantize::<i8>(x))?,
+                    Format::Int16 => self.inner.write_sample(quantizer.quantize::<i16>(x))?,
+                    Format::Int24 => self
+                        .inner
+                        .write_sample(quantizer.quantize::<I24>(x).value())?,
+                    Format::Int32 => self.inner.write_sample(quantizer.quantize::<i32>(x))?,
+                    Format::Float32 => self.inner.write_sample(x as f32)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Updating the header. Dropping the writer does it too, ignoring the errors
+     */
+    pub fn finalize(self) -> Result<(), WavError> {
+        self.inner.finalize()?;
+        Ok(())
+    }
+}
+
+/**
+ * The whole file, one buffer per channel
+ */
+pub fn read<P: AsRef<Path>>(path: P) -> Result<(Spec, Vec<Vec<f64>>), WavError> {
+    let mut reader = Reader::open(path)?;
+    let buffers = reader.read_all()?;
+    Ok((reader.spec(), buffers))
+}
+
+pub fn write<P: AsRef<Path>>(path: P, spec: Spec, buffers: &[&[f64]]) -> Result<(), WavError> {
+    let mut writer = Writer::create(path, spec)?;
+    writer.write_block(buffers)?;
+    writer.finalize()
+}
+
+/**
+ * Streaming the reader through the processor into the writer, by blocks
+ *
+ * The processor gets the sample rate of the file. The output has the length of the
+ * input: the tail is cut.
+ *
+ * Panics if the block size is 0.
+ */
+pub fn process(
+    reader: &mut Reader,
+    writer: &mut Writer,
+    processor: &mut dyn Processor,
+    block_size: usize,
+) -> Result<(), WavError> {
+    assert!(block_size > 0, "wav: block size 0");
+    let channels = reader.spec().channels;
+    check_channels(processor.channels(), channels)?;
+    check_channels(writer.spec().channels, channels)?;
+    processor.set_sample_rate(reader.spec().sample_rate);
+
+    let mut inputs = vec![vec![0.0; block_size]; channels];
+    let mut outputs = vec![vec![0.0; block_size]; channels];
+    loop {
+        let frames = {
+            let mut slices: Vec<&mut [f64]> = inputs.iter_mut().map(|b| &mut b[..]).collect();
+            reader.read_block(&mut slices)?
+        };
+        if frames == 0 {
+            return Ok(());
+        }
+        let input_slices: Vec<&[f64]> = inputs.iter().map(|b| &b[..frames]).collect();
+        let mut output_slices: Vec<&mut [f64]> =
+            outputs.iter_mut().map(|b| &mut b[..frames]).collect();
+        processor.process_block(&input_slices, &mut output_slices);
+        let output_slices: Vec<&[f64]> = output_slices.into_iter().map(|b| &*b).collect();
+        writer.write_block(&output_slices)?;
+    }
+}
+
+/**
+ * Filtering a file into another of the same format
+ */
+pub fn process_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    processor: &mut dyn Processor,
+    block_size: usize,
+) -> Result<(), WavError> {
+    let mut reader = Reader::open(input)?;
+    check_channels(processor.channels(), reader.spec().channels)?;
+    let mut writer = Writer::create(output, reader.spec())?;
+    process(&mut reader, &mut writer, processor, block_size)?;
+    writer.finalize()
+}
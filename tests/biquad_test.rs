@@ -2,15 +2,21 @@
 //! 
 //! See https://www.earlevel.com/main/2010/12/20/biquad-calculator/
 
+// the expected coefficients are calculated with q: 0.7071
+#![allow(clippy::approx_constant)]
+
 #[macro_use]
 extern crate more_asserts;
 
 #[allow(unused_variables)]
 mod common;
+#[allow(dead_code)]
 mod helper;
 
 use dsp_playground::biquad;
+use dsp_playground::error::FilterError;
 use dsp_playground::filter;
+use helper::filter_params;
 
 const PATH_WHITE_NOISE: &str = "tests/assets/white_noise_mono.wav";
 const PATH_SNAPSHOT_LOWPASS: &str = "tests/assets/snapshot_lowpass_fc_1000_Q_0.7071_gain_6.wav";
@@ -54,11 +60,130 @@ fn not_self_cci_less_than_1() {
 
 #[test]
 fn low_pass_snaphost() {
+    let biquad_params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let biquad_process = biquad::Process::new(biquad_params);
+
+    assert_low_pass_snapshot("low_pass_snaphost", biquad_process);
+}
+
+#[test]
+fn low_pass_snaphost_direct_form_2() {
+    let biquad_params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let biquad_process =
+        biquad::Process::with_topology(biquad_params, biquad::Topology::DirectFormII);
+
+    assert_low_pass_snapshot("low_pass_snaphost_direct_form_2", biquad_process);
+}
+
+#[test]
+fn low_pass_snaphost_transposed_direct_form_1() {
+    let biquad_params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let biquad_process =
+        biquad::Process::with_topology(biquad_params, biquad::Topology::TransposedDirectFormI);
+
+    assert_low_pass_snapshot("low_pass_snaphost_transposed_direct_form_1", biquad_process);
+}
+
+#[test]
+fn low_pass_snaphost_transposed_direct_form_2() {
+    let biquad_params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let biquad_process =
+        biquad::Process::with_topology(biquad_params, biquad::Topology::TransposedDirectFormII);
+
+    assert_low_pass_snapshot("low_pass_snaphost_transposed_direct_form_2", biquad_process);
+}
+
+#[test]
+fn topologies_agree_on_f64() {
+    let white_noise: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let topologies = [
+        biquad::Topology::DirectFormII,
+        biquad::Topology::TransposedDirectFormI,
+        biquad::Topology::TransposedDirectFormII,
+    ];
+
+    let mut reference = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    let mut others: Vec<biquad::Process> = topologies
+        .iter()
+        .map(|t| biquad::Process::with_topology(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6, *t))
+        .collect();
+
+    for s in &white_noise {
+        let x = *s as f64 / i16::MAX as f64;
+        let expected = reference.tick(x);
+        for process in others.iter_mut() {
+            assert_lt!((process.tick(x) - expected).abs(), 1e-12);
+        }
+    }
+}
+
+#[test]
+fn low_pass_snapshot_process_block() {
     let white_noise: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
     let white_noise_filtered_snapshot: Vec<i16> = helper::audio_file_samples(PATH_SNAPSHOT_LOWPASS);
 
-    let biquad_params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
-    let mut biquad_process = biquad::Process::new(biquad_params);
+    let mut biquad_process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    let mut filtered: Vec<i16> = vec![0; white_noise.len()];
+    // odd block size, so that the state has to carry over between blocks
+    for (sin, sout) in white_noise.chunks(100).zip(filtered.chunks_mut(100)) {
+        biquad_process.process_block(sin, sout);
+    }
+
+    helper::assert_snapshot_eq(
+        "low_pass_snapshot_process_block",
+        &white_noise,
+        &white_noise_filtered_snapshot,
+        &filtered,
+        *biquad_process.params(),
+    );
+}
+
+#[test]
+fn process_block_matches_process() {
+    let white_noise: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let input: Vec<f32> = white_noise
+        .iter()
+        .map(|s| *s as f32 / i16::MAX as f32)
+        .collect();
+
+    let topologies = [
+        biquad::Topology::DirectFormI,
+        biquad::Topology::DirectFormII,
+        biquad::Topology::TransposedDirectFormI,
+        biquad::Topology::TransposedDirectFormII,
+    ];
+    for topology in topologies.iter() {
+        let mut per_sample =
+            biquad::Process::with_topology(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6, *topology);
+        let mut block =
+            biquad::Process::with_topology(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6, *topology);
+        let mut in_place =
+            biquad::Process::with_topology(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6, *topology);
+
+        let expected: Vec<f32> = input.iter().map(|s| per_sample.process(s)).collect();
+
+        let mut output = vec![0.0f32; input.len()];
+        block.process_block(&input, &mut output);
+        assert_eq!(output, expected);
+
+        let mut buffer = input.clone();
+        in_place.process_in_place(&mut buffer);
+        assert_eq!(buffer, expected);
+    }
+}
+
+#[test]
+#[should_panic]
+fn process_block_length_mismatch() {
+    let mut biquad_process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    let input = [0.0f64; 8];
+    let mut output = [0.0f64; 4];
+    biquad_process.process_block(&input, &mut output);
+}
+
+fn assert_low_pass_snapshot(name: &str, mut biquad_process: biquad::Process) {
+    let white_noise: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let white_noise_filtered_snapshot: Vec<i16> = helper::audio_file_samples(PATH_SNAPSHOT_LOWPASS);
 
     let mut filtered: Vec<i16> = Vec::new();
 
@@ -67,11 +192,13 @@ fn low_pass_snaphost() {
         filtered.push(sout);
     }
 
-    let cci = helper::cross_correlation_index(&white_noise_filtered_snapshot, &filtered);
-    assert_gt!(cci, 0.9999);
-
-    let rmse = helper::rmse(&white_noise_filtered_snapshot, &filtered);
-    assert_lt!(rmse, 1.0);
+    helper::assert_snapshot_eq(
+        name,
+        &white_noise,
+        &white_noise_filtered_snapshot,
+        &filtered,
+        *biquad_process.params(),
+    );
 }
 #[test]
 fn write_low_pass_filtered_file() {
@@ -360,4 +487,254 @@ fn high_shelf_cut() {
         b2: 0.22744763765194606,
     };
     assert_eq!(params, expected);
-}
\ No newline at end of file
+}
+
+#[test]
+fn low_pass_poles_and_zeros() {
+    let params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+
+    let zeros = params.zeros();
+    assert_eq!(zeros.len(), 2);
+    for zero in zeros.iter() {
+        assert_lt!((zero.re + 1.0).abs(), 1e-6);
+        assert_lt!(zero.im.abs(), 1e-6);
+    }
+
+    let poles = params.poles();
+    assert_eq!(poles.len(), 2);
+    // complex conjugates, giving back the feedback coefficients
+    assert_lt!((poles[0].conj() - poles[1]).norm(), 1e-12);
+    assert_lt!((-(poles[0] + poles[1]).re - params.b1).abs(), 1e-12);
+    assert_lt!(((poles[0] * poles[1]).re - params.b2).abs(), 1e-12);
+
+    assert!(params.is_stable());
+    assert!(params.is_minimum_phase());
+}
+
+#[test]
+fn first_order_poles_and_zeros() {
+    let params = biquad::Params {
+        a0: 0.5,
+        a1: 0.25,
+        a2: 0.0,
+        b1: -0.5,
+        b2: 0.0,
+    };
+    assert_eq!(params.poles().len(), 1);
+    assert_lt!((params.poles()[0].re - 0.5).abs(), 1e-12);
+    assert_eq!(params.zeros().len(), 1);
+    assert_lt!((params.zeros()[0].re + 0.5).abs(), 1e-12);
+
+    // a pure delay has no zero
+    let delay = biquad::Params {
+        a0: 0.0,
+        a1: 1.0,
+        a2: 0.0,
+        b1: 0.0,
+        b2: 0.0,
+    };
+    assert!(delay.zeros().is_empty());
+}
+
+#[test]
+fn allpass_is_not_minimum_phase() {
+    let p = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let allpass = biquad::Params {
+        a0: p.b2,
+        a1: p.b1,
+        a2: 1.0,
+        b1: p.b1,
+        b2: p.b2,
+    };
+    assert!(allpass.is_stable());
+    assert!(!allpass.is_minimum_phase());
+    for zero in allpass.zeros().iter() {
+        assert_gt!(zero.norm(), 1.0);
+    }
+}
+
+#[test]
+fn unstable_poles() {
+    // b2 > 1: complex poles of magnitude sqrt(b2)
+    let params = biquad::Params {
+        a0: 1.0,
+        a1: 0.0,
+        a2: 0.0,
+        b1: -1.8,
+        b2: 1.2,
+    };
+    assert!(!params.is_stable());
+    assert!(!params.is_minimum_phase());
+
+    match params.validate() {
+        Err(FilterError::Unstable { pole_magnitude }) => {
+            assert_lt!((pole_magnitude - 1.2f64.sqrt()).abs(), 1e-12)
+        }
+        result => panic!("expected an unstable filter, got {:?}", result),
+    }
+    let result = biquad::Params::try_new(params.a0, params.a1, params.a2, params.b1, params.b2);
+    assert!(result.is_err());
+}
+
+#[test]
+fn try_new_validates_coefficients() {
+    let p = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    assert_eq!(biquad::Params::try_new(p.a0, p.a1, p.a2, p.b1, p.b2), Ok(p));
+
+    assert_eq!(
+        biquad::Params::try_new(f64::NAN, p.a1, p.a2, p.b1, p.b2),
+        Err(FilterError::NonFiniteCoefficient)
+    );
+    assert_eq!(
+        biquad::Params::try_new(p.a0, p.a1, p.a2, p.b1, f64::INFINITY),
+        Err(FilterError::NonFiniteCoefficient)
+    );
+    // poles on the unit circle: an oscillator, not a filter
+    let error = biquad::Params::try_new(1.0, 0.0, 0.0, 0.0, 1.0).unwrap_err();
+    assert_eq!(
+        error,
+        FilterError::Unstable {
+            pole_magnitude: 1.0
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "unstable filter: pole of magnitude 1 (must be < 1)"
+    );
+}
+
+#[test]
+fn filter_params_builder() {
+    let params = filter::Params::builder(1_000.0).build(44100).unwrap();
+    assert_eq!(
+        params,
+        filter_params(1_000.0, std::f64::consts::FRAC_1_SQRT_2, 0.0)
+    );
+
+    let params = filter::Params::builder(200.0)
+        .q(2.0)
+        .gain_db(-3.0)
+        .build(48000)
+        .unwrap();
+    assert_eq!(params, filter_params(200.0, 2.0, -3.0));
+}
+
+#[test]
+fn filter_params_errors() {
+    assert_eq!(
+        filter::Params::builder(1_000.0).build(0),
+        Err(FilterError::InvalidSampleRate { fs: 0 })
+    );
+    assert_eq!(
+        filter::Params::builder(-1.0).build(44100),
+        Err(FilterError::InvalidFrequency { fc: -1.0 })
+    );
+    assert!(matches!(
+        filter::Params::builder(f64::NAN).build(44100),
+        Err(FilterError::InvalidFrequency { .. })
+    ));
+    assert_eq!(
+        filter::Params::builder(22_050.0).build(44100),
+        Err(FilterError::FrequencyAboveNyquist {
+            fc: 22_050.0,
+            nyquist: 22_050.0
+        })
+    );
+    assert_eq!(
+        filter::Params::builder(1_000.0).q(0.0).build(44100),
+        Err(FilterError::InvalidQ { q: 0.0 })
+    );
+    assert!(matches!(
+        filter::Params::builder(1_000.0)
+            .gain_db(f64::NAN)
+            .build(44100),
+        Err(FilterError::NonFiniteGain { .. })
+    ));
+    assert_eq!(
+        FilterError::FrequencyAboveNyquist {
+            fc: 30_000.0,
+            nyquist: 22_050.0
+        }
+        .to_string(),
+        "frequency 30000 above Nyquist (must be < 22050)"
+    );
+
+    let result = biquad::Params::try_from_audio_filter_params(
+        filter_params(1_000.0, -0.7071, 0.0),
+        filter::Type::LowPass,
+        44100,
+    );
+    assert_eq!(result, Err(FilterError::InvalidQ { q: -0.7071 }));
+}
+
+#[test]
+fn try_from_audio_filter_params_matches_infallible() {
+    let params = filter_params(1_000.0, 0.7071, 6.0);
+    assert_eq!(
+        biquad::Params::try_from_audio_filter_params(params, filter::Type::LowPass, 44100),
+        Ok(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6)
+    );
+}
+
+#[test]
+fn invalid_params_are_clamped() {
+    let all_types = [
+        filter::Type::LowPass,
+        filter::Type::HighPass,
+        filter::Type::BandPass,
+        filter::Type::Notch,
+        filter::Type::Peak,
+        filter::Type::LowShelf,
+        filter::Type::HighShelf,
+    ];
+    let all_params = [
+        filter_params(0.0, 0.7071, 6.0),
+        filter_params(-100.0, 0.7071, 6.0),
+        filter_params(22_050.0, 0.7071, 6.0),
+        filter_params(1e9, 0.7071, 6.0),
+        filter_params(1_000.0, -0.7071, 6.0),
+        filter_params(1_000.0, 0.0, 6.0),
+        filter_params(1_000.0, f64::NAN, 6.0),
+        filter_params(1_000.0, f64::INFINITY, 6.0),
+        filter_params(f64::NAN, 0.7071, 6.0),
+        filter_params(1_000.0, 0.7071, f64::NAN),
+        filter_params(1_000.0, 0.7071, f64::INFINITY),
+    ];
+    for filter_type in all_types.iter() {
+        for params in all_params.iter() {
+            let biquad = biquad::Params::from_audio_filter_params(*params, *filter_type, 44100);
+            assert_eq!(
+                biquad.validate(),
+                Ok(()),
+                "{:?} {:?}: {:?}",
+                filter_type,
+                params,
+                biquad
+            );
+        }
+    }
+
+    assert_eq!(
+        filter_params(1e9, -1.0, f64::NAN).clamped(44100),
+        filter_params(filter::MAX_RELATIVE_FC * 44100.0, filter::MIN_Q, 0.0)
+    );
+    assert_eq!(
+        filter_params(f64::NAN, f64::INFINITY, 6.0).clamped(44100),
+        filter_params(filter::MAX_RELATIVE_FC * 44100.0, filter::MAX_Q, 6.0)
+    );
+
+    // passthrough
+    let passthrough = biquad::Params::default();
+    for (params, fs) in [
+        (filter_params(1_000.0, 0.7071, 6.0), 0),
+        (filter_params(1_000.0, 0.7071, 6.0), -44100),
+        (filter_params(f64::NAN, 0.7071, 6.0), 44100),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            biquad::Params::from_audio_filter_params(*params, filter::Type::Peak, *fs),
+            passthrough
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Cascade (second order sections) tests
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::cascade::Cascade;
+use dsp_playground::filter;
+use dsp_playground::sample::{Quantizer, Scaling};
+use helper::PATH_WHITE_NOISE;
+use std::f64::consts::FRAC_1_SQRT_2;
+
+const PATH_SNAPSHOT_LOWPASS: &str = "tests/assets/snapshot_lowpass_fc_1000_Q_0.7071_gain_6.wav";
+
+#[test]
+fn single_section_snapshot() {
+    let white_noise: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let white_noise_filtered_snapshot: Vec<i16> = helper::audio_file_samples(PATH_SNAPSHOT_LOWPASS);
+
+    let mut cascade = Cascade::new(vec![biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6]);
+    let mut filtered: Vec<i16> = vec![0; white_noise.len()];
+    cascade.process_block(&white_noise, &mut filtered);
+
+    let rmse = helper::rmse(&white_noise_filtered_snapshot, &filtered);
+    assert_lt!(rmse, 1.0);
+}
+
+#[test]
+fn single_section_dithered_snapshot() {
+    let white_noise: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let white_noise_filtered_snapshot: Vec<i16> = helper::audio_file_samples(PATH_SNAPSHOT_LOWPASS);
+
+    let mut cascade = Cascade::new(vec![biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6]);
+    let mut quantizer = Quantizer::with_tpdf_dither(Scaling::Symmetric, 42);
+    let mut filtered: Vec<i16> = vec![0; white_noise.len()];
+    cascade.process_block_quantized(&white_noise, &mut filtered, &mut quantizer);
+
+    // the snapshot is rounded: the dither adds its noise (0.5 LSB RMS) on top
+    let rmse = helper::rmse(&white_noise_filtered_snapshot, &filtered);
+    assert_gt!(rmse, 0.2);
+    assert_lt!(rmse, 1.0);
+}
+
+#[test]
+fn matches_chained_processes() {
+    let white_noise: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let input: Vec<f64> = white_noise
+        .iter()
+        .map(|s| *s as f64 / i16::MAX as f64)
+        .collect();
+
+    let filters = [
+        (
+            filter::Params {
+                fc: 1_000.0,
+                q: FRAC_1_SQRT_2,
+                gain_db: 0.0,
+            },
+            filter::Type::LowPass,
+        ),
+        (
+            filter::Params {
+                fc: 3_000.0,
+                q: 2.0,
+                gain_db: -6.0,
+            },
+            filter::Type::Peak,
+        ),
+    ];
+    let mut first = biquad::Process::new(biquad::Params::from_audio_filter_params(
+        filters[0].0,
+        filters[0].1,
+        44100,
+    ));
+    let mut second = biquad::Process::new(biquad::Params::from_audio_filter_params(
+        filters[1].0,
+        filters[1].1,
+        44100,
+    ));
+    let expected: Vec<f64> = input
+        .iter()
+        .map(|x| 0.5 * second.tick(first.tick(*x)))
+        .collect();
+
+    let mut cascade = Cascade::from_audio_filter_params(&filters, 44100);
+    cascade.gain = 0.5;
+    assert_eq!(cascade.len(), 2);
+
+    let mut output = vec![0.0; input.len()];
+    cascade.process_block(&input, &mut output);
+    for (o, e) in output.iter().zip(expected.iter()) {
+        assert_lt!((o - e).abs(), 1e-15);
+    }
+
+    cascade.reset();
+    let per_sample: Vec<f64> = input.iter().map(|x| cascade.process(x)).collect();
+    assert_eq!(per_sample, output);
+
+    cascade.reset();
+    let mut buffer = input.clone();
+    cascade.process_in_place(&mut buffer);
+    assert_eq!(buffer, output);
+}
+
+#[test]
+fn gain_db() {
+    let mut cascade = Cascade::new(vec![biquad::Params::default()]);
+    assert_eq!(cascade.gain_db(), 0.0);
+
+    cascade.set_gain_db(-6.0);
+    assert_lt!((cascade.gain - 0.501187).abs(), 1e-6);
+    assert_lt!((cascade.gain_db() + 6.0).abs(), 1e-12);
+    assert_eq!(cascade.tick(1.0), cascade.gain);
+}
+
+#[test]
+fn empty_cascade_is_passthrough() {
+    let mut cascade = Cascade::new(vec![]);
+    assert!(cascade.is_empty());
+
+    let mut samples = [0.1f32, -0.5, 0.25];
+    cascade.process_in_place(&mut samples);
+    assert_eq!(samples, [0.1f32, -0.5, 0.25]);
+}
@@ -0,0 +1,172 @@
+//! Character filter tests
+
+#[macro_use]
+extern crate more_asserts;
+
+use dsp_playground::biquad;
+use dsp_playground::character::{CharacterFilter, Korg35, Ms20, Sem};
+use dsp_playground::filter::{self, Filter};
+use std::f64::consts::PI;
+
+const FS: i32 = 44100;
+
+fn all(filter_type: filter::Type, cutoff: f64, resonance: f64) -> Vec<Box<dyn CharacterFilter>> {
+    vec![
+        Box::new(Ms20::new(filter_type, cutoff, resonance, FS)),
+        Box::new(Korg35::new(filter_type, cutoff, resonance, FS)),
+        Box::new(Sem::new(filter_type, cutoff, resonance, FS)),
+    ]
+}
+
+fn sine(f: f64, amplitude: f64) -> Vec<f64> {
+    (0..FS as usize)
+        .map(|i| amplitude * (2.0 * PI * f * i as f64 / FS as f64).sin())
+        .collect()
+}
+
+fn peak(xs: &[f64]) -> f64 {
+    xs.iter().fold(0.0f64, |max, x| max.max(x.abs()))
+}
+
+/// Steady state gain (dB) of a small sine
+fn gain_db(filter: &mut dyn CharacterFilter, f: f64) -> f64 {
+    filter.reset();
+    let mut samples = sine(f, 0.001);
+    filter.tick_block(&mut samples);
+    20.0 * (peak(&samples[FS as usize / 2..]) / 0.001).log10()
+}
+
+/// Frequency from the (interpolated) rising zero crossings
+fn frequency(xs: &[f64]) -> f64 {
+    let crossings: Vec<f64> = xs
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+        .map(|(i, pair)| i as f64 + pair[0] / (pair[0] - pair[1]))
+        .collect();
+    let periods = (crossings.len() - 1) as f64;
+    FS as f64 * periods / (crossings[crossings.len() - 1] - crossings[0])
+}
+
+#[test]
+fn low_pass() {
+    for filter in all(filter::Type::LowPass, 1_000.0, 0.0).iter_mut() {
+        assert_lt!(gain_db(filter.as_mut(), 20.0).abs(), 0.1);
+        // at least 12 dB/oct
+        assert_lt!(gain_db(filter.as_mut(), 10_000.0), -38.0);
+    }
+}
+
+#[test]
+fn high_pass() {
+    for filter in all(filter::Type::HighPass, 1_000.0, 0.0).iter_mut() {
+        assert_lt!(gain_db(filter.as_mut(), 15_000.0).abs(), 0.5);
+        assert_lt!(gain_db(filter.as_mut(), 100.0), -38.0);
+    }
+}
+
+#[test]
+fn sem_band_pass_and_notch() {
+    let mut band_pass = Sem::new(filter::Type::BandPass, 2_000.0, 0.5, FS);
+    let mut notch = Sem::new(filter::Type::Notch, 2_000.0, 0.5, FS);
+    assert_lt!(gain_db(&mut band_pass, 2_000.0).abs(), 0.1);
+    assert_lt!(gain_db(&mut band_pass, 100.0), -20.0);
+    assert_lt!(gain_db(&mut notch, 2_000.0), -40.0);
+    assert_lt!(gain_db(&mut notch, 100.0).abs(), 0.1);
+}
+
+#[test]
+fn resonance_boosts_the_cutoff() {
+    for (flat, resonant) in all(filter::Type::LowPass, 1_000.0, 0.0)
+        .iter_mut()
+        .zip(all(filter::Type::LowPass, 1_000.0, 0.8).iter_mut())
+    {
+        assert_gt!(
+            gain_db(resonant.as_mut(), 1_000.0),
+            gain_db(flat.as_mut(), 1_000.0) + 6.0
+        );
+    }
+}
+
+#[test]
+fn sallen_keys_self_oscillate_at_cutoff() {
+    let filters: Vec<Box<dyn CharacterFilter>> = vec![
+        Box::new(Ms20::new(filter::Type::LowPass, 500.0, 1.0, FS)),
+        Box::new(Korg35::new(filter::Type::LowPass, 500.0, 1.0, FS)),
+        Box::new(Korg35::new(filter::Type::HighPass, 500.0, 1.0, FS)),
+    ];
+    for mut filter in filters.into_iter() {
+        let mut samples = vec![0.0; 2 * FS as usize];
+        samples[0] = 0.1;
+        filter.tick_block(&mut samples);
+
+        let steady = &samples[FS as usize..];
+        // bounded by the saturation
+        assert_gt!(peak(steady), 0.1);
+        assert_lt!(peak(steady), 2.0);
+        assert_lt!((frequency(steady) / 500.0 - 1.0).abs(), 0.1);
+    }
+}
+
+#[test]
+fn sem_does_not_self_oscillate() {
+    let mut sem = Sem::new(filter::Type::LowPass, 500.0, 1.0, FS);
+    let mut samples = vec![0.0; FS as usize];
+    samples[0] = 1.0;
+    sem.tick_block(&mut samples);
+    assert_lt!(peak(&samples[FS as usize / 2..]), 1e-6);
+}
+
+#[test]
+fn drive_saturates() {
+    for filter in all(filter::Type::LowPass, 5_000.0, 0.0).iter_mut() {
+        let input = sine(100.0, 0.9);
+
+        filter.set_drive(0.1);
+        filter.reset();
+        let mut clean = input.clone();
+        filter.tick_block(&mut clean);
+
+        filter.set_drive(10.0);
+        filter.reset();
+        let mut driven = input.clone();
+        filter.tick_block(&mut driven);
+
+        assert_lt!((peak(&clean) - 0.9).abs(), 0.05);
+        assert_lt!(peak(&driven), 0.5);
+        assert_eq!(filter.drive(), 10.0);
+    }
+}
+
+#[test]
+fn controls_are_clamped() {
+    for filter in all(filter::Type::LowPass, 1e9, 2.0).iter_mut() {
+        assert_eq!(filter.cutoff(), 0.45 * FS as f64);
+        assert_eq!(filter.resonance(), 1.0);
+        filter.set_cutoff(-1.0);
+        filter.set_resonance(-1.0);
+        filter.set_drive(0.0);
+        assert_gt!(filter.cutoff(), 0.0);
+        assert_eq!(filter.resonance(), 0.0);
+        assert_gt!(filter.drive(), 0.0);
+    }
+}
+
+#[test]
+fn biquad_behind_the_same_trait() {
+    let params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let mut boxed: Box<dyn Filter> = Box::new(biquad::Process::new(params));
+    let mut direct = biquad::Process::new(params);
+
+    let mut samples = sine(3_000.0, 0.5);
+    boxed.tick_block(&mut samples);
+    for (x, y) in sine(3_000.0, 0.5).iter().zip(samples.iter()) {
+        assert_eq!(direct.tick(*x), *y);
+    }
+}
+
+#[test]
+#[should_panic]
+fn unsupported_type() {
+    Ms20::new(filter::Type::Peak, 1_000.0, 0.0, FS);
+}
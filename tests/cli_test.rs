@@ -0,0 +1,307 @@
+//! Command line tool tests (with the `wav` feature)
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::cascade::Cascade;
+use dsp_playground::filter;
+use dsp_playground::wav;
+use helper::PATH_WHITE_NOISE;
+use std::process::{Command, Output};
+
+fn temp_path(name: &str) -> String {
+    format!("tests/assets/temp_cli_{}.wav", name)
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dsp-playground"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn low_pass_without_dither() {
+    let path = temp_path("lowpass");
+    let output = run(&[
+        "filter",
+        PATH_WHITE_NOISE,
+        &path,
+        "--filter",
+        "lowpass:1200:0.5:6",
+        "--dither",
+        "none",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let white_noise = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let mut expected = vec![0; white_noise.len()];
+    biquad::Process::from_audio_filter_params(
+        filter::Params {
+            fc: 1200.0,
+            q: 0.5,
+            gain_db: 6.0,
+        },
+        filter::Type::LowPass,
+        44100,
+    )
+    .process_block(&white_noise, &mut expected);
+    assert_eq!(helper::audio_file_samples(&path), expected);
+}
+
+#[test]
+fn chain_to_float() {
+    let path = temp_path("chain");
+    let output = run(&[
+        "filter",
+        PATH_WHITE_NOISE,
+        &path,
+        "-f",
+        "highpass:80",
+        "-f",
+        "peak:3000:2:-4",
+        "-f",
+        "highshelf:8000:0.7:3",
+        "-b",
+        "32f",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let (input_spec, input) = wav::read(PATH_WHITE_NOISE).unwrap();
+    let mut cascade = Cascade::from_audio_filter_params(
+        &[
+            (
+                filter::Params::builder(80.0).build(44100).unwrap(),
+                filter::Type::HighPass,
+            ),
+            (
+                filter::Params::builder(3000.0)
+                    .q(2.0)
+                    .gain_db(-4.0)
+                    .build(44100)
+                    .unwrap(),
+                filter::Type::Peak,
+            ),
+            (
+                filter::Params::builder(8000.0)
+                    .q(0.7)
+                    .gain_db(3.0)
+                    .build(44100)
+                    .unwrap(),
+                filter::Type::HighShelf,
+            ),
+        ],
+        44100,
+    );
+    let mut expected = vec![0.0; input[0].len()];
+    cascade.process_block(&input[0], &mut expected);
+
+    let (spec, filtered) = wav::read(&path).unwrap();
+    assert_eq!(
+        spec,
+        wav::Spec {
+            format: wav::Format::Float32,
+            ..input_spec
+        }
+    );
+    for (y, e) in filtered[0].iter().zip(expected.iter()) {
+        assert!((y - e).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn stereo_24_bits_to_16_bits() {
+    let input_path = temp_path("stereo_input");
+    let (_, white_noise) = wav::read(PATH_WHITE_NOISE).unwrap();
+    let left = &white_noise[0];
+    let right: Vec<f64> = left.iter().rev().cloned().collect();
+    wav::write(
+        &input_path,
+        wav::Spec {
+            channels: 2,
+            sample_rate: 48000,
+            format: wav::Format::Int24,
+        },
+        &[left, &right],
+    )
+    .unwrap();
+
+    let path = temp_path("stereo");
+    let output = run(&[
+        "filter",
+        &input_path,
+        &path,
+        "-f",
+        "lowpass:2000",
+        "-b",
+        "16",
+        "-s",
+        "wannamaker9",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let (spec, filtered) = wav::read(&path).unwrap();
+    assert_eq!(
+        spec,
+        wav::Spec {
+            channels: 2,
+            sample_rate: 48000,
+            format: wav::Format::Int16,
+        }
+    );
+    // each channel filtered on its own, at the file's sample rate, within the shaped dither
+    let (_, input) = wav::read(&input_path).unwrap();
+    for (channel, filtered) in input.iter().zip(filtered.iter()) {
+        let mut process = biquad::Process::from_audio_filter_params(
+            filter::Params::builder(2000.0).build(48000).unwrap(),
+            filter::Type::LowPass,
+            48000,
+        );
+        let mut expected = vec![0.0; channel.len()];
+        process.process_block(channel, &mut expected);
+        for (y, e) in filtered.iter().zip(expected.iter()) {
+            assert!((y - e).abs() * 32767.0 < 20.0);
+        }
+    }
+}
+
+#[test]
+fn more_channels_than_a_graph() {
+    let input_path = temp_path("ten_channels_input");
+    let (_, white_noise) = wav::read(PATH_WHITE_NOISE).unwrap();
+    // a different signal in each channel
+    let channels: Vec<Vec<f64>> = (0..10)
+        .map(|channel| {
+            let mut samples = white_noise[0].clone();
+            samples.rotate_left(channel * 100);
+            samples
+        })
+        .collect();
+    let slices: Vec<&[f64]> = channels.iter().map(|c| &c[..]).collect();
+    wav::write(
+        &input_path,
+        wav::Spec {
+            channels: 10,
+            sample_rate: 44100,
+            format: wav::Format::Float32,
+        },
+        &slices,
+    )
+    .unwrap();
+
+    let path = temp_path("ten_channels");
+    let output = run(&["filter", &input_path, &path, "-f", "lowpass:1000"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let (spec, filtered) = wav::read(&path).unwrap();
+    assert_eq!(spec.channels, 10);
+    let (_, input) = wav::read(&input_path).unwrap();
+    for (channel, filtered) in input.iter().zip(filtered.iter()) {
+        let mut process = biquad::Process::from_audio_filter_params(
+            helper::low_pass_params(1000.0),
+            filter::Type::LowPass,
+            44100,
+        );
+        let mut expected = vec![0.0; channel.len()];
+        process.process_block(channel, &mut expected);
+        for (y, e) in filtered.iter().zip(expected.iter()) {
+            assert!((y - e).abs() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn plot_of_two_chains() {
+    let path = "tests/assets/temp_cli_plot.svg";
+    let output = run(&[
+        "plot",
+        path,
+        "-c",
+        "highpass:80,peak:3000:2:-4",
+        "-c",
+        "lowshelf:200:0.7:3",
+        "-t",
+        "eq",
+        "--no-phase",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let svg = std::fs::read_to_string(path).unwrap();
+    assert_eq!(svg.matches("<polyline").count(), 2);
+    for label in [
+        ">eq<",
+        ">highpass:80,peak:3000:2:-4<",
+        ">lowshelf:200:0.7:3<",
+    ]
+    .iter()
+    {
+        assert!(svg.contains(label), "{}", label);
+    }
+    assert!(!svg.contains(">deg<"));
+}
+
+#[test]
+fn errors() {
+    let output = run(&[]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("error: missing command"));
+
+    let help = run(&["help"]);
+    assert!(help.status.success());
+    assert!(String::from_utf8_lossy(&help.stdout).starts_with("usage: dsp-playground filter"));
+
+    for (args, message) in [
+        (
+            vec!["filter", PATH_WHITE_NOISE],
+            "expected an input and an output",
+        ),
+        (
+            vec!["filter", PATH_WHITE_NOISE, "out.wav", "-f", "lowpas:1000"],
+            "unknown filter type 'lowpas'",
+        ),
+        (
+            vec!["filter", PATH_WHITE_NOISE, "out.wav", "-f", "peak:1k"],
+            "invalid fc '1k'",
+        ),
+        (
+            vec!["filter", PATH_WHITE_NOISE, "out.wav", "-b", "12"],
+            "invalid bit depth '12'",
+        ),
+        (
+            vec!["filter", PATH_WHITE_NOISE, "out.wav", "-f"],
+            "missing value of -f",
+        ),
+        (
+            vec!["filter", PATH_WHITE_NOISE, "out.wav", "-f", "lowpass:30000"],
+            "filter 1: frequency 30000 above Nyquist",
+        ),
+        (
+            vec!["filter", "tests/assets/missing.wav", "out.wav"],
+            "error: wav: ",
+        ),
+        (vec!["plot", "out.svg"], "nothing to plot: missing --curve"),
+        (
+            vec!["plot", "out.svg", "-c", "lowpass:1000,peak:30000"],
+            "curve 1, filter 2: frequency 30000 above Nyquist",
+        ),
+    ]
+    .iter()
+    {
+        let output = run(args);
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(
+            stderr(&output).contains(message),
+            "{:?}: {}",
+            args,
+            stderr(&output)
+        );
+    }
+    // nothing written on errors
+    assert!(!std::path::Path::new("out.wav").exists());
+    assert!(!std::path::Path::new("out.svg").exists());
+}
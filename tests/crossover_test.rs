@@ -0,0 +1,139 @@
+//! Linkwitz-Riley crossover tests
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::cascade::Cascade;
+use dsp_playground::crossover::{Crossover, Order};
+use helper::{white_noise, FS};
+use std::f64::consts::PI;
+
+fn energy(xs: &[f64]) -> f64 {
+    xs.iter().map(|x| x * x).sum()
+}
+
+fn split(crossover: &mut Crossover, input: &[f64]) -> Vec<Vec<f64>> {
+    let mut bands = vec![vec![0.0; input.len()]; crossover.bands()];
+    {
+        let mut outputs: Vec<&mut [f64]> = bands.iter_mut().map(|b| b.as_mut_slice()).collect();
+        crossover.process_block(input, &mut outputs);
+    }
+    bands
+}
+
+#[test]
+fn summed_bands_are_allpass() {
+    let input = white_noise();
+    let all_frequencies: [&[f64]; 3] = [&[1_000.0], &[200.0, 2_000.0], &[100.0, 800.0, 5_000.0]];
+
+    for order in [Order::LR2, Order::LR4, Order::LR8].iter() {
+        for frequencies in all_frequencies.iter() {
+            let mut crossover = Crossover::new(*order, frequencies, FS);
+            assert_eq!(crossover.bands(), frequencies.len() + 1);
+
+            let bands = split(&mut crossover, &input);
+            let sum: Vec<f64> = (0..input.len())
+                .map(|i| bands.iter().map(|band| band[i]).sum())
+                .collect();
+
+            // identical to running the input through the allpasses
+            let mut allpass = Cascade::new(crossover.allpass_sections());
+            for (x, s) in input.iter().zip(sum.iter()) {
+                assert_lt!(
+                    (allpass.tick(*x) - s).abs(),
+                    1e-9,
+                    "{:?} {:?}",
+                    order,
+                    frequencies
+                );
+            }
+
+            // flat magnitude: same energy as the white noise input
+            let ratio = energy(&sum) / energy(&input);
+            assert_lt!((ratio - 1.0).abs(), 0.01, "{:?} {:?}", order, frequencies);
+        }
+    }
+}
+
+#[test]
+fn allpass_sections_have_unity_magnitude() {
+    for order in [Order::LR2, Order::LR4, Order::LR8].iter() {
+        let crossover = Crossover::new(*order, &[150.0, 1_500.0, 9_000.0], FS);
+        for f in [20.0, 150.0, 1_000.0, 5_000.0, 20_000.0].iter() {
+            let w = 2.0 * PI * f / FS as f64;
+            let magnitude: f64 = crossover
+                .allpass_sections()
+                .iter()
+                .map(|p| {
+                    let num_re = p.a0 + p.a1 * w.cos() + p.a2 * (2.0 * w).cos();
+                    let num_im = -p.a1 * w.sin() - p.a2 * (2.0 * w).sin();
+                    let den_re = 1.0 + p.b1 * w.cos() + p.b2 * (2.0 * w).cos();
+                    let den_im = -p.b1 * w.sin() - p.b2 * (2.0 * w).sin();
+                    (num_re.hypot(num_im)) / (den_re.hypot(den_im))
+                })
+                .product();
+            assert_lt!((magnitude - 1.0).abs(), 1e-12);
+        }
+    }
+}
+
+#[test]
+fn bands_are_minus_6_db_at_crossover() {
+    let fc = 1_000.0;
+    let input: Vec<f64> = (0..FS as usize)
+        .map(|i| (2.0 * PI * fc * i as f64 / FS as f64).sin())
+        .collect();
+
+    for order in [Order::LR2, Order::LR4, Order::LR8].iter() {
+        let mut crossover = Crossover::new(*order, &[fc], FS);
+        let bands = split(&mut crossover, &input);
+        for band in bands.iter() {
+            // steady state peak, after the first half second
+            let peak = band[FS as usize / 2..]
+                .iter()
+                .fold(0.0f64, |max, x| max.max(x.abs()));
+            assert_lt!((20.0 * peak.log10() + 6.0206).abs(), 0.01, "{:?}", order);
+        }
+    }
+}
+
+#[test]
+fn bands_separate_the_spectrum() {
+    let input = white_noise();
+    let mut crossover = Crossover::new(Order::LR4, &[500.0, 5_000.0], FS);
+    let bands = split(&mut crossover, &input);
+
+    // white noise: the energy is proportional to the bandwidth
+    let total = energy(&input);
+    let low = energy(&bands[0]) / total;
+    let mid = energy(&bands[1]) / total;
+    let high = energy(&bands[2]) / total;
+    assert_lt!(low, 0.05);
+    assert_lt!(mid, high);
+    assert_gt!(high, 0.7);
+}
+
+#[test]
+fn process_block_matches_tick() {
+    let input = white_noise();
+    let mut block = Crossover::new(Order::LR8, &[300.0, 3_000.0], FS);
+    let mut per_sample = Crossover::new(Order::LR8, &[300.0, 3_000.0], FS);
+
+    let bands = split(&mut block, &input);
+    let mut samples = [0.0; 3];
+    for (i, x) in input.iter().enumerate() {
+        per_sample.tick(*x, &mut samples);
+        for (band, s) in bands.iter().zip(samples.iter()) {
+            assert_eq!(band[i], *s);
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn frequencies_must_increase() {
+    Crossover::new(Order::LR4, &[2_000.0, 1_000.0], FS);
+}
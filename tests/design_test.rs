@@ -0,0 +1,593 @@
+//! Filter design tests
+
+#[macro_use]
+extern crate more_asserts;
+
+use dsp_playground::biquad;
+use dsp_playground::design;
+use dsp_playground::filter;
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+const FS: i32 = 48000;
+
+fn params(fc: f64) -> filter::Params {
+    filter::Params {
+        fc,
+        q: FRAC_1_SQRT_2,
+        gain_db: 0.0,
+    }
+}
+
+/**
+ * |H(e^jw)| of the cascade, in dB
+ */
+fn magnitude_db(sections: &[biquad::Params], f: f64) -> f64 {
+    let w = 2.0 * PI * f / FS as f64;
+    let (c1, s1, c2, s2) = (w.cos(), -w.sin(), (2.0 * w).cos(), -(2.0 * w).sin());
+    let mut magnitude = 1.0;
+    for p in sections {
+        let num_re = p.a0 + p.a1 * c1 + p.a2 * c2;
+        let num_im = p.a1 * s1 + p.a2 * s2;
+        let den_re = 1.0 + p.b1 * c1 + p.b2 * c2;
+        let den_im = p.b1 * s1 + p.b2 * s2;
+        magnitude *=
+            ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt();
+    }
+    20.0 * magnitude.log10()
+}
+
+/**
+ * Analytical magnitude of the (prewarped) digital Butterworth filter
+ */
+fn butterworth_db(order: usize, fc: f64, f: f64, filter_type: filter::Type) -> f64 {
+    let ratio = (PI * f / FS as f64).tan() / (PI * fc / FS as f64).tan();
+    let ratio = match filter_type {
+        filter::Type::HighPass => 1.0 / ratio,
+        _ => ratio,
+    };
+    -10.0 * (1.0 + ratio.powi(2 * order as i32)).log10()
+}
+
+#[test]
+fn butterworth_q_values() {
+    let q = design::butterworth_q(4);
+    assert_eq!(q.len(), 2);
+    assert_lt!((q[0] - 0.541196).abs(), 1e-6);
+    assert_lt!((q[1] - 1.306563).abs(), 1e-6);
+
+    assert_lt!((design::butterworth_q(2)[0] - FRAC_1_SQRT_2).abs(), 1e-12);
+    assert!(design::butterworth_q(1).is_empty());
+    assert_eq!(design::butterworth_q(7).len(), 3);
+}
+
+#[test]
+fn butterworth_order_2_is_a_single_biquad() {
+    let sections = design::butterworth(2, params(1_000.0), filter::Type::LowPass, FS);
+    let expected =
+        biquad::Params::from_audio_filter_params(params(1_000.0), filter::Type::LowPass, FS);
+    assert_eq!(sections.len(), 1);
+    assert_lt!((sections[0].a0 - expected.a0).abs(), 1e-15);
+    assert_lt!((sections[0].b1 - expected.b1).abs(), 1e-15);
+    assert_lt!((sections[0].b2 - expected.b2).abs(), 1e-15);
+}
+
+#[test]
+fn butterworth_sections_count() {
+    for order in 1..=design::MAX_ORDER {
+        let sections = design::butterworth(order, params(1_000.0), filter::Type::LowPass, FS);
+        assert_eq!(sections.len(), order / 2 + order % 2);
+    }
+}
+
+#[test]
+fn butterworth_minus_3_db_at_fc() {
+    for order in 1..=design::MAX_ORDER {
+        for filter_type in [filter::Type::LowPass, filter::Type::HighPass].iter() {
+            let sections = design::butterworth(order, params(1_000.0), *filter_type, FS);
+            let db = magnitude_db(&sections, 1_000.0);
+            assert_lt!(
+                (db + 3.0103).abs(),
+                1e-3,
+                "order {} {:?}",
+                order,
+                filter_type
+            );
+        }
+    }
+}
+
+#[test]
+fn butterworth_matches_analytical_magnitude() {
+    let frequencies = [
+        20.0, 100.0, 500.0, 900.0, 1_100.0, 2_000.0, 8_000.0, 20_000.0,
+    ];
+    for order in 1..=design::MAX_ORDER {
+        for filter_type in [filter::Type::LowPass, filter::Type::HighPass].iter() {
+            let sections = design::butterworth(order, params(1_000.0), *filter_type, FS);
+            for f in frequencies.iter() {
+                let expected = butterworth_db(order, 1_000.0, *f, *filter_type);
+                let db = magnitude_db(&sections, *f);
+                // relative, the stopband goes down to hundreds of dB
+                assert_lt!((db - expected).abs(), 1e-6 * expected.abs().max(1.0));
+            }
+        }
+    }
+}
+
+#[test]
+fn butterworth_rolloff_slope() {
+    // far from both fc and nyquist the slope approaches 6 dB/oct per order
+    for order in 1..=8 {
+        let low_pass = design::butterworth(order, params(100.0), filter::Type::LowPass, FS);
+        let slope = magnitude_db(&low_pass, 2_000.0) - magnitude_db(&low_pass, 1_000.0);
+        assert_lt!((slope + 6.0206 * order as f64).abs(), 0.05 * order as f64);
+
+        let high_pass = design::butterworth(order, params(10_000.0), filter::Type::HighPass, FS);
+        let slope = magnitude_db(&high_pass, 500.0) - magnitude_db(&high_pass, 250.0);
+        assert_lt!((slope - 6.0206 * order as f64).abs(), 0.05 * order as f64);
+    }
+}
+
+#[test]
+fn butterworth_odd_order_at_and_above_nyquist() {
+    let max_fc = filter::MAX_RELATIVE_FC * FS as f64;
+    for fc in [FS as f64 / 2.0, FS as f64, 2.0 * FS as f64].iter() {
+        for filter_type in [filter::Type::LowPass, filter::Type::HighPass].iter() {
+            let sections = design::butterworth(3, params(*fc), *filter_type, FS);
+            // every section at the clamped cutoff, the first order one included
+            assert_eq!(
+                sections,
+                design::butterworth(3, params(max_fc), *filter_type, FS)
+            );
+            for p in sections.iter() {
+                assert!(p.validate().is_ok(), "{} {:?}: {:?}", fc, filter_type, p);
+            }
+            assert_lt!((magnitude_db(&sections, max_fc) + 3.0103).abs(), 1e-3);
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn butterworth_order_out_of_range() {
+    design::butterworth(17, params(1_000.0), filter::Type::LowPass, FS);
+}
+
+#[test]
+#[should_panic]
+fn butterworth_unsupported_type() {
+    design::butterworth(4, params(1_000.0), filter::Type::Peak, FS);
+}
+
+/// f_low, f_high: geometrically centered at fc, fc / q apart
+fn band_edges(fc: f64, q: f64) -> (f64, f64) {
+    let bandwidth = fc / q;
+    let f_low = (-bandwidth + (bandwidth * bandwidth + 4.0 * fc * fc).sqrt()) / 2.0;
+    (f_low, f_low + bandwidth)
+}
+
+/// linearly spaced frequencies, inclusive
+fn frequencies(from: f64, to: f64) -> Vec<f64> {
+    (0..=500)
+        .map(|i| from + (to - from) * i as f64 / 500.0)
+        .collect()
+}
+
+fn band_params(fc: f64, q: f64) -> filter::Params {
+    filter::Params {
+        fc,
+        q,
+        gain_db: 0.0,
+    }
+}
+
+#[test]
+fn chebyshev1_passband_ripple() {
+    let ripple = 1.0;
+    for order in 1..=10 {
+        let low_pass =
+            design::chebyshev1(order, ripple, params(1_000.0), filter::Type::LowPass, FS);
+        assert_lt!((magnitude_db(&low_pass, 1_000.0) + ripple).abs(), 1e-6);
+        let dc = if order % 2 == 1 { 0.0 } else { -ripple };
+        assert_lt!((magnitude_db(&low_pass, 0.0) - dc).abs(), 1e-6);
+        for f in frequencies(0.0, 1_000.0) {
+            let db = magnitude_db(&low_pass, f);
+            assert_le!(db, 1e-6, "order {} f {}", order, f);
+            assert_ge!(db, -ripple - 1e-6, "order {} f {}", order, f);
+        }
+        // the stopband keeps falling
+        assert_lt!(
+            magnitude_db(&low_pass, 4_000.0),
+            magnitude_db(&low_pass, 2_000.0)
+        );
+
+        let high_pass =
+            design::chebyshev1(order, ripple, params(1_000.0), filter::Type::HighPass, FS);
+        assert_lt!((magnitude_db(&high_pass, 1_000.0) + ripple).abs(), 1e-6);
+        for f in frequencies(1_000.0, 23_000.0) {
+            let db = magnitude_db(&high_pass, f);
+            assert_le!(db, 1e-6);
+            assert_ge!(db, -ripple - 1e-6);
+        }
+    }
+}
+
+#[test]
+fn chebyshev2_stopband_attenuation() {
+    let attenuation = 40.0;
+    for order in 1..=10 {
+        let low_pass = design::chebyshev2(
+            order,
+            attenuation,
+            params(2_000.0),
+            filter::Type::LowPass,
+            FS,
+        );
+        assert_lt!(magnitude_db(&low_pass, 0.0).abs(), 1e-6);
+        assert_lt!((magnitude_db(&low_pass, 2_000.0) + attenuation).abs(), 1e-6);
+        for f in frequencies(2_000.0, 24_000.0) {
+            assert_le!(
+                magnitude_db(&low_pass, f),
+                -attenuation + 1e-6,
+                "order {} f {}",
+                order,
+                f
+            );
+        }
+        // monotonic passband
+        let passband: Vec<f64> = frequencies(0.0, 1_000.0)
+            .iter()
+            .map(|f| magnitude_db(&low_pass, *f))
+            .collect();
+        for pair in passband.windows(2) {
+            assert_le!(pair[1], pair[0] + 1e-9);
+        }
+
+        let high_pass = design::chebyshev2(
+            order,
+            attenuation,
+            params(2_000.0),
+            filter::Type::HighPass,
+            FS,
+        );
+        assert_lt!(magnitude_db(&high_pass, 24_000.0 - 1e-9).abs(), 1e-6);
+        for f in frequencies(0.0, 2_000.0) {
+            assert_le!(magnitude_db(&high_pass, f), -attenuation + 1e-6);
+        }
+    }
+}
+
+#[test]
+fn elliptic_ripple_and_attenuation() {
+    let (ripple, attenuation) = (0.5, 60.0);
+    for order in 2..=8 {
+        let low_pass = design::elliptic(
+            order,
+            ripple,
+            attenuation,
+            params(1_000.0),
+            filter::Type::LowPass,
+            FS,
+        );
+        assert_lt!((magnitude_db(&low_pass, 1_000.0) + ripple).abs(), 1e-6);
+        for f in frequencies(0.0, 1_000.0) {
+            let db = magnitude_db(&low_pass, f);
+            assert_le!(db, 1e-6, "order {} f {}", order, f);
+            assert_ge!(db, -ripple - 1e-6, "order {} f {}", order, f);
+        }
+
+        let stopband = frequencies(1_000.0, 24_000.0);
+        let edge = stopband
+            .iter()
+            .position(|f| magnitude_db(&low_pass, *f) <= -attenuation)
+            .expect("stopband not reached");
+        for f in &stopband[edge..] {
+            assert_le!(
+                magnitude_db(&low_pass, *f),
+                -attenuation + 1e-6,
+                "order {} f {}",
+                order,
+                f
+            );
+        }
+        // equiripple: the stopband peaks touch the attenuation spec
+        let peak = stopband[edge..]
+            .iter()
+            .map(|f| magnitude_db(&low_pass, *f))
+            .fold(f64::MIN, f64::max);
+        assert_gt!(peak, -attenuation - 1.0);
+    }
+
+    // narrower transition than a chebyshev of the same order
+    let elliptic = design::elliptic(
+        4,
+        ripple,
+        attenuation,
+        params(1_000.0),
+        filter::Type::LowPass,
+        FS,
+    );
+    let chebyshev = design::chebyshev1(4, ripple, params(1_000.0), filter::Type::LowPass, FS);
+    let edge = |sections: &[biquad::Params]| {
+        frequencies(1_000.0, 24_000.0)
+            .into_iter()
+            .find(|f| magnitude_db(sections, *f) <= -attenuation)
+            .unwrap()
+    };
+    assert_lt!(edge(&elliptic), 0.75 * edge(&chebyshev));
+}
+
+#[test]
+fn band_pass_designs() {
+    let (fc, q) = (1_000.0, 2.0);
+    let (f_low, f_high) = band_edges(fc, q);
+    let (ripple, attenuation) = (1.0, 50.0);
+
+    let chebyshev = design::chebyshev1(4, ripple, band_params(fc, q), filter::Type::BandPass, FS);
+    let elliptic = design::elliptic(
+        4,
+        ripple,
+        attenuation,
+        band_params(fc, q),
+        filter::Type::BandPass,
+        FS,
+    );
+    for sections in [chebyshev, elliptic].iter() {
+        assert_eq!(sections.len(), 4);
+        assert_lt!((magnitude_db(sections, f_low) + ripple).abs(), 1e-6);
+        assert_lt!((magnitude_db(sections, f_high) + ripple).abs(), 1e-6);
+        for f in frequencies(f_low, f_high) {
+            let db = magnitude_db(sections, f);
+            assert_le!(db, 1e-6);
+            assert_ge!(db, -ripple - 1e-6);
+        }
+        assert_lt!(magnitude_db(sections, 100.0), -30.0);
+        assert_lt!(magnitude_db(sections, 10_000.0), -30.0);
+    }
+
+    let chebyshev2 = design::chebyshev2(
+        4,
+        attenuation,
+        band_params(fc, q),
+        filter::Type::BandPass,
+        FS,
+    );
+    for f in frequencies(20.0, f_low)
+        .iter()
+        .chain(frequencies(f_high, 23_000.0).iter())
+    {
+        assert_le!(magnitude_db(&chebyshev2, *f), -attenuation + 1e-6);
+    }
+    assert_lt!(magnitude_db(&chebyshev2, fc).abs(), 1e-6);
+}
+
+#[test]
+fn band_stop_designs() {
+    let (fc, q) = (1_000.0, 2.0);
+    let (f_low, f_high) = band_edges(fc, q);
+    let attenuation = 40.0;
+
+    let chebyshev2 =
+        design::chebyshev2(4, attenuation, band_params(fc, q), filter::Type::Notch, FS);
+    assert_eq!(chebyshev2.len(), 4);
+    for f in frequencies(f_low, f_high) {
+        assert_le!(magnitude_db(&chebyshev2, f), -attenuation + 1e-6);
+    }
+    assert_lt!(magnitude_db(&chebyshev2, 0.0).abs(), 1e-6);
+    assert_lt!(magnitude_db(&chebyshev2, 24_000.0 - 1e-9).abs(), 1e-6);
+
+    let ripple = 0.5;
+    let elliptic = design::elliptic(
+        4,
+        ripple,
+        attenuation,
+        band_params(fc, q),
+        filter::Type::Notch,
+        FS,
+    );
+    assert_lt!((magnitude_db(&elliptic, f_low) + ripple).abs(), 1e-6);
+    assert_lt!((magnitude_db(&elliptic, f_high) + ripple).abs(), 1e-6);
+    for f in frequencies(0.0, f_low) {
+        assert_ge!(magnitude_db(&elliptic, f), -ripple - 1e-6);
+    }
+    assert_lt!(magnitude_db(&elliptic, fc), -attenuation);
+}
+
+#[test]
+fn designs_are_stable() {
+    let types = [
+        filter::Type::LowPass,
+        filter::Type::HighPass,
+        filter::Type::BandPass,
+        filter::Type::Notch,
+    ];
+    for order in 1..=design::MAX_ORDER {
+        for filter_type in types.iter() {
+            let designs = [
+                design::chebyshev1(order, 1.0, band_params(500.0, 1.0), *filter_type, FS),
+                design::chebyshev2(order, 60.0, band_params(500.0, 1.0), *filter_type, FS),
+                design::elliptic(order, 1.0, 60.0, band_params(500.0, 1.0), *filter_type, FS),
+            ];
+            for sections in designs.iter() {
+                for p in sections {
+                    // stability triangle
+                    assert_lt!(p.b2.abs(), 1.0, "order {} {:?}", order, filter_type);
+                    assert_lt!(p.b1.abs(), 1.0 + p.b2, "order {} {:?}", order, filter_type);
+                }
+            }
+        }
+    }
+}
+
+/// Phase of the cascade, wrapped into (-π, π]
+fn phase(sections: &[biquad::Params], f: f64) -> f64 {
+    let w = 2.0 * PI * f / FS as f64;
+    let (c1, s1, c2, s2) = (w.cos(), -w.sin(), (2.0 * w).cos(), -(2.0 * w).sin());
+    let mut phase = 0.0;
+    for p in sections {
+        let num = (p.a1 * s1 + p.a2 * s2).atan2(p.a0 + p.a1 * c1 + p.a2 * c2);
+        let den = (p.b1 * s1 + p.b2 * s2).atan2(1.0 + p.b1 * c1 + p.b2 * c2);
+        phase += num - den;
+    }
+    wrap(phase)
+}
+
+fn wrap(phase: f64) -> f64 {
+    let wrapped = phase.rem_euclid(2.0 * PI);
+    if wrapped > PI {
+        wrapped - 2.0 * PI
+    } else {
+        wrapped
+    }
+}
+
+/// Group delay in samples, from the phase difference around f
+fn group_delay(sections: &[biquad::Params], f: f64) -> f64 {
+    let df = 0.01;
+    let dw = 2.0 * PI * df / FS as f64;
+    -wrap(phase(sections, f + df) - phase(sections, f)) / dw
+}
+
+#[test]
+fn bessel_magnitude_norm_minus_3_db_at_fc() {
+    for order in 1..=design::BESSEL_MAX_ORDER {
+        for filter_type in [filter::Type::LowPass, filter::Type::HighPass].iter() {
+            let sections = design::bessel(
+                order,
+                design::BesselNorm::Magnitude,
+                params(2_000.0),
+                *filter_type,
+                FS,
+            );
+            assert_eq!(sections.len(), order / 2 + order % 2);
+            assert_lt!((magnitude_db(&sections, 2_000.0) + 3.0103).abs(), 1e-6);
+        }
+    }
+}
+
+#[test]
+fn bessel_phase_norm_butterworth_asymptotes() {
+    for order in 1..=design::BESSEL_MAX_ORDER {
+        let bessel = design::bessel(
+            order,
+            design::BesselNorm::Phase,
+            params(100.0),
+            filter::Type::LowPass,
+            FS,
+        );
+        let butterworth = design::butterworth(order, params(100.0), filter::Type::LowPass, FS);
+        assert_lt!(magnitude_db(&bessel, 0.0).abs(), 1e-9);
+        assert_lt!(
+            (magnitude_db(&bessel, 10_000.0) - magnitude_db(&butterworth, 10_000.0)).abs(),
+            0.05,
+            "order {}",
+            order
+        );
+    }
+}
+
+#[test]
+fn bessel_second_order_q() {
+    // θ2(s) = s^2 + 3s + 3: Q = 1/sqrt(3)
+    let sections = design::bessel(
+        2,
+        design::BesselNorm::Delay,
+        params(1_000.0),
+        filter::Type::LowPass,
+        FS,
+    );
+    let expected = biquad::Params::from_audio_filter_params(
+        filter::Params {
+            fc: FS as f64 / PI * (3.0f64.sqrt() * (PI * 1_000.0 / FS as f64).tan()).atan(),
+            q: 1.0 / 3.0f64.sqrt(),
+            gain_db: 0.0,
+        },
+        filter::Type::LowPass,
+        FS,
+    );
+    assert_lt!((sections[0].a0 - expected.a0).abs(), 1e-12);
+    assert_lt!((sections[0].b1 - expected.b1).abs(), 1e-12);
+    assert_lt!((sections[0].b2 - expected.b2).abs(), 1e-12);
+}
+
+#[test]
+fn bessel_flat_group_delay() {
+    let fc = 1_000.0;
+    for order in 3..=design::BESSEL_MAX_ORDER {
+        let bessel = design::bessel(
+            order,
+            design::BesselNorm::Magnitude,
+            params(fc),
+            filter::Type::LowPass,
+            FS,
+        );
+        let butterworth = design::butterworth(order, params(fc), filter::Type::LowPass, FS);
+
+        let deviation = |sections: &[biquad::Params]| {
+            let dc = group_delay(sections, 1.0);
+            let max = frequencies(1.0, fc / 2.0)
+                .iter()
+                .map(|f| (group_delay(sections, *f) - dc).abs())
+                .fold(0.0, f64::max);
+            max / dc
+        };
+        let bessel_deviation = deviation(&bessel);
+        assert_lt!(bessel_deviation, 0.01, "order {}", order);
+        assert_lt!(
+            bessel_deviation * 10.0,
+            deviation(&butterworth),
+            "order {}",
+            order
+        );
+    }
+}
+
+#[test]
+#[should_panic]
+fn bessel_order_out_of_range() {
+    design::bessel(
+        11,
+        design::BesselNorm::Phase,
+        params(1_000.0),
+        filter::Type::LowPass,
+        FS,
+    );
+}
+
+#[test]
+#[should_panic(expected = "chebyshev1: edges")]
+fn chebyshev1_fc_at_nyquist() {
+    design::chebyshev1(4, 1.0, params(FS as f64 / 2.0), filter::Type::LowPass, FS);
+}
+
+#[test]
+#[should_panic(expected = "elliptic: edges")]
+fn elliptic_band_above_nyquist() {
+    // the upper edge of a wide band past fs/2
+    design::elliptic(
+        4,
+        1.0,
+        60.0,
+        band_params(20_000.0, 1.0),
+        filter::Type::BandPass,
+        FS,
+    );
+}
+
+#[test]
+#[should_panic(expected = "chebyshev2: edges")]
+fn chebyshev2_notch_above_nyquist() {
+    design::chebyshev2(4, 60.0, band_params(23_000.0, 2.0), filter::Type::Notch, FS);
+}
+
+#[test]
+#[should_panic(expected = "chebyshev1: invalid ripple")]
+fn chebyshev1_without_ripple() {
+    design::chebyshev1(4, 0.0, params(1_000.0), filter::Type::LowPass, FS);
+}
+
+#[test]
+#[should_panic(expected = "elliptic: attenuation")]
+fn elliptic_attenuation_below_the_ripple() {
+    design::elliptic(4, 3.0, 1.0, params(1_000.0), filter::Type::LowPass, FS);
+}
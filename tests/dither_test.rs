@@ -0,0 +1,218 @@
+//! Dither and noise shaping tests: the spectrum of the quantization error
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::complex::Complex;
+use dsp_playground::dither::{Dither, Ditherer, NoiseShaping};
+use dsp_playground::sample::{FloatOfMax1, Quantizer, Scaling};
+use helper::PATH_WHITE_NOISE;
+use std::f64::consts::PI;
+
+const FS: f64 = 44100.0;
+/// DFT size
+const N: usize = 512;
+
+/// White noise around -40 dBFS: a signal much larger than the LSB
+fn input() -> Vec<f64> {
+    helper::audio_file_samples(PATH_WHITE_NOISE)
+        .iter()
+        .map(|s| s.to_f64() * 0.01)
+        .collect()
+}
+
+/// Error of the 16 bit conversion, in LSB
+fn error(dither: Dither, shaping: NoiseShaping, input: &[f64]) -> Vec<f64> {
+    let mut quantizer =
+        Quantizer::with_ditherer(Scaling::Symmetric, Ditherer::new(dither, shaping, 1234));
+    let mut output = vec![0i16; input.len()];
+    quantizer.quantize_block(input, &mut output);
+    output
+        .iter()
+        .zip(input.iter())
+        .map(|(y, x)| *y as f64 - x * i16::MAX as f64)
+        .collect()
+}
+
+/// Power spectral density per bin, in LSB², averaged over Hann windowed blocks of N samples
+///
+/// Without the window, the shaped noise leaks from the high frequencies into the notches.
+fn spectrum(signal: &[f64]) -> Vec<f64> {
+    let window: Vec<f64> = (0..N)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / N as f64).cos())
+        .collect();
+    let window_power: f64 = window.iter().map(|w| w * w).sum();
+    let mut psd = vec![0.0; N / 2];
+    let blocks = signal.len() / N;
+    for block in signal.chunks_exact(N) {
+        for (k, p) in psd.iter_mut().enumerate() {
+            let bin = block.iter().zip(window.iter()).enumerate().fold(
+                Complex::from(0.0),
+                |sum, (n, (x, w))| {
+                    sum + Complex::unit(-2.0 * PI * (k * n) as f64 / N as f64) * (x * w)
+                },
+            );
+            *p += bin.norm_sqr() / (window_power * blocks as f64);
+        }
+    }
+    psd
+}
+
+/// |1 - H(e^jw)|²
+fn ntf(shaping: NoiseShaping, f: f64) -> f64 {
+    let w = 2.0 * PI * f / FS;
+    let h = shaping
+        .coefficients()
+        .iter()
+        .enumerate()
+        .fold(Complex::from(0.0), |sum, (i, h)| {
+            sum + Complex::unit(-w * (i + 1) as f64) * *h
+        });
+    (Complex::from(1.0) - h).norm_sqr()
+}
+
+/// Average of the bins from f_min to f_max
+fn band(psd: &[f64], f_min: f64, f_max: f64) -> f64 {
+    let bins: Vec<f64> = psd
+        .iter()
+        .enumerate()
+        .filter(|(k, _)| {
+            let f = *k as f64 * FS / N as f64;
+            f >= f_min && f < f_max
+        })
+        .map(|(_, p)| *p)
+        .collect();
+    bins.iter().sum::<f64>() / bins.len() as f64
+}
+
+fn band_ntf(shaping: NoiseShaping, f_min: f64, f_max: f64) -> f64 {
+    let psd: Vec<f64> = (0..N / 2)
+        .map(|k| ntf(shaping, k as f64 * FS / N as f64))
+        .collect();
+    band(&psd, f_min, f_max)
+}
+
+fn variance(signal: &[f64]) -> f64 {
+    signal.iter().map(|x| x * x).sum::<f64>() / signal.len() as f64
+}
+
+const BANDS: [(f64, f64); 4] = [
+    (0.0, 2_000.0),
+    (2_000.0, 5_000.0),
+    (5_000.0, 15_000.0),
+    (15_000.0, 22_050.0),
+];
+
+#[test]
+fn error_variance() {
+    let input = input();
+    // rounding (1/12) plus the dither
+    for (dither, expected) in [
+        (Dither::Rpdf, 1.0 / 6.0),
+        (Dither::Tpdf, 1.0 / 4.0),
+        (Dither::HighPassTpdf, 1.0 / 4.0),
+    ]
+    .iter()
+    {
+        let error = error(*dither, NoiseShaping::None, &input);
+        let ratio = variance(&error) / expected;
+        assert_gt!(ratio, 0.9, "{:?}", dither);
+        assert_lt!(ratio, 1.1, "{:?}", dither);
+        // no bias
+        assert_lt!((error.iter().sum::<f64>() / error.len() as f64).abs(), 0.01);
+    }
+}
+
+#[test]
+fn tpdf_is_white() {
+    let psd = spectrum(&error(Dither::Tpdf, NoiseShaping::None, &input()));
+    for (f_min, f_max) in BANDS.iter() {
+        let ratio = band(&psd, *f_min, *f_max) / 0.25;
+        assert_gt!(ratio, 0.85, "{} to {} Hz", f_min, f_max);
+        assert_lt!(ratio, 1.15, "{} to {} Hz", f_min, f_max);
+    }
+}
+
+#[test]
+fn high_pass_tpdf() {
+    let psd = spectrum(&error(Dither::HighPassTpdf, NoiseShaping::None, &input()));
+    // the rounding is white, the dither is 1/12 |1 - z^-1|²
+    for (f_min, f_max) in BANDS.iter() {
+        let expected = (1.0 + band_ntf(NoiseShaping::ErrorFeedback, *f_min, *f_max)) / 12.0;
+        let ratio = band(&psd, *f_min, *f_max) / expected;
+        assert_gt!(ratio, 0.85, "{} to {} Hz", f_min, f_max);
+        assert_lt!(ratio, 1.15, "{} to {} Hz", f_min, f_max);
+    }
+    assert_lt!(band(&psd, 0.0, 2_000.0), 0.1);
+}
+
+#[test]
+fn shaped_spectra_follow_the_curves() {
+    let input = input();
+    for shaping in [
+        NoiseShaping::ErrorFeedback,
+        NoiseShaping::FWeighted,
+        NoiseShaping::Wannamaker9,
+    ]
+    .iter()
+    {
+        let psd = spectrum(&error(Dither::Tpdf, *shaping, &input));
+        for (f_min, f_max) in BANDS.iter() {
+            let expected = 0.25 * band_ntf(*shaping, *f_min, *f_max);
+            let ratio = band(&psd, *f_min, *f_max) / expected;
+            assert_gt!(ratio, 0.8, "{:?}, {} to {} Hz", shaping, f_min, f_max);
+            assert_lt!(ratio, 1.25, "{:?}, {} to {} Hz", shaping, f_min, f_max);
+        }
+    }
+}
+
+#[test]
+fn f_weighted_curves_are_quieter_where_the_ear_is_sensitive() {
+    let input = input();
+    let flat = band(
+        &spectrum(&error(Dither::Tpdf, NoiseShaping::None, &input)),
+        2_000.0,
+        5_000.0,
+    );
+    let mut previous = flat;
+    for shaping in [NoiseShaping::FWeighted, NoiseShaping::Wannamaker9].iter() {
+        let psd = spectrum(&error(Dither::Tpdf, *shaping, &input));
+        let sensitive = band(&psd, 2_000.0, 5_000.0);
+        assert_lt!(sensitive, previous, "{:?}", shaping);
+        assert_gt!(band(&psd, 15_000.0, 22_050.0), flat, "{:?}", shaping);
+        previous = sensitive;
+    }
+    // more than 10 dB below the flat TPDF
+    assert_lt!(previous, flat / 10.0);
+}
+
+#[test]
+fn rounding_without_dither() {
+    let mut ditherer = Ditherer::new(Dither::None, NoiseShaping::None, 1);
+    assert_eq!(ditherer.quantize(0.4), 0.0);
+    assert_eq!(ditherer.quantize(-0.6), -1.0);
+
+    // first order error feedback: the errors average out
+    let mut ditherer = Ditherer::new(Dither::None, NoiseShaping::ErrorFeedback, 1);
+    let outputs: Vec<f64> = (0..10).map(|_| ditherer.quantize(0.3)).collect();
+    assert_eq!(outputs.iter().sum::<f64>(), 3.0);
+    assert!(outputs.iter().all(|y| *y == 0.0 || *y == 1.0));
+
+    ditherer.reset();
+    assert_eq!(ditherer.quantize(0.4), 0.0);
+    assert_eq!(ditherer.dither(), Dither::None);
+    assert_eq!(ditherer.shaping(), NoiseShaping::ErrorFeedback);
+}
+
+#[test]
+fn stable_on_clipping() {
+    // full scale square wave: the i16 saturates, the fed back error does not grow
+    let input: Vec<f64> = (0..10_000)
+        .map(|n| if (n / 50) % 2 == 0 { 1.0 } else { -1.0 })
+        .collect();
+    let error = error(Dither::Tpdf, NoiseShaping::Wannamaker9, &input);
+    assert!(error.iter().all(|e| e.abs() < 100.0));
+}
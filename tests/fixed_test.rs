@@ -0,0 +1,173 @@
+//! Fixed point biquad tests, against the floating point biquad
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::error::FilterError;
+use dsp_playground::fixed;
+use helper::{low_pass, PATH_WHITE_NOISE};
+
+const PATH_SNAPSHOT_LOWPASS: &str = "tests/assets/snapshot_lowpass_fc_1000_Q_0.7071_gain_6.wav";
+
+#[test]
+fn q15_matches_the_float_snapshot() {
+    let white_noise = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let snapshot = helper::audio_file_samples(PATH_SNAPSHOT_LOWPASS);
+
+    for error_feedback in [true, false].iter() {
+        let mut process =
+            fixed::Process::<i16>::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6).unwrap();
+        process.set_error_feedback(*error_feedback);
+        let mut filtered = vec![0; white_noise.len()];
+        process.process_block(&white_noise, &mut filtered);
+
+        // including the error of the coefficients' quantization
+        let rmse = helper::rmse(&snapshot, &filtered);
+        assert_lt!(rmse, 6.0, "error feedback: {}", error_feedback);
+        assert_gt!(helper::cross_correlation_index(&snapshot, &filtered), 0.999);
+
+        // only the arithmetic: the rounding noise goes through the poles,
+        // unless shaped by the error feedback
+        let mut float = biquad::Process::new(process.quantized_params());
+        let mut expected = vec![0; white_noise.len()];
+        float.process_block(&white_noise, &mut expected);
+        let rmse = helper::rmse(&expected, &filtered);
+        assert_lt!(rmse, if *error_feedback { 1.0 } else { 4.0 });
+    }
+}
+
+#[test]
+fn q31_matches_the_float_process() {
+    let white_noise: Vec<i32> = helper::audio_file_samples(PATH_WHITE_NOISE)
+        .iter()
+        .map(|s| (*s as i32) << 16)
+        .collect();
+    let params = low_pass(1_000.0);
+
+    let mut process = fixed::Process::<i32>::new(params).unwrap();
+    let mut filtered = vec![0; white_noise.len()];
+    process.process_block(&white_noise, &mut filtered);
+
+    let scale = 2f64.powi(31);
+    let mut float = biquad::Process::new(params);
+    let squared_error: f64 = white_noise
+        .iter()
+        .zip(filtered.iter())
+        .map(|(x, y)| (float.tick(*x as f64 / scale) - *y as f64 / scale).powi(2))
+        .sum();
+    let rmse = (squared_error / white_noise.len() as f64).sqrt();
+    // a few LSB of Q31
+    assert_lt!(rmse, 1e-8);
+}
+
+#[test]
+fn error_feedback_removes_the_limit_cycles() {
+    let white_noise = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let mut input = white_noise;
+    input.extend(vec![0; 44100]);
+
+    let tail = |error_feedback| {
+        let mut process = fixed::Process::<i16>::new(low_pass(100.0)).unwrap();
+        process.set_error_feedback(error_feedback);
+        let mut output = vec![0; input.len()];
+        process.process_block(&input, &mut output);
+        output[output.len() - 100..].to_vec()
+    };
+    // rounding: stuck at a DC offset
+    assert!(tail(false).iter().all(|y| *y != 0));
+    assert!(tail(true).iter().all(|y| *y == 0));
+}
+
+#[test]
+fn headroom_shift() {
+    // b1 close to -2
+    let params = low_pass(1_000.0);
+    let process = fixed::Process::<i16>::new(params).unwrap();
+    assert_eq!(process.shift(), 1);
+    assert!(process.error_feedback());
+    assert_eq!(
+        fixed::Process::<i16>::with_shift(params, 0).err(),
+        Some(FilterError::CoefficientOverflow {
+            coefficient: params.b1,
+            shift: 0
+        })
+    );
+
+    for shift in [1, 4, 8].iter() {
+        let quantized = fixed::Process::<i16>::with_shift(params, *shift)
+            .unwrap()
+            .quantized_params();
+        let lsb = 2f64.powi(*shift as i32 - 15);
+        for (q, c) in [
+            (quantized.a0, params.a0),
+            (quantized.a1, params.a1),
+            (quantized.a2, params.a2),
+            (quantized.b1, params.b1),
+            (quantized.b2, params.b2),
+        ]
+        .iter()
+        {
+            assert_le!((q - c).abs(), lsb / 2.0);
+        }
+    }
+
+    let unquantizable = biquad::Params {
+        a0: f64::NAN,
+        ..params
+    };
+    assert_eq!(
+        fixed::Process::<i32>::new(unquantizable).err(),
+        Some(FilterError::NonFiniteCoefficient)
+    );
+}
+
+#[test]
+fn saturates() {
+    let gain_of_2 = biquad::Params {
+        a0: 2.0,
+        ..biquad::Params::default()
+    };
+    let mut process = fixed::Process::<i16>::new(gain_of_2).unwrap();
+    assert_eq!(process.shift(), 2);
+    let mut samples = vec![1_000, 30_000, -30_000, i16::MIN];
+    process.process_in_place(&mut samples);
+    assert_eq!(samples, vec![2_000, i16::MAX, i16::MIN, i16::MIN]);
+
+    let mut process = fixed::Process::<i32>::new(gain_of_2).unwrap();
+    process.reset();
+    assert_eq!(process.tick(i32::MAX / 2 + 1), i32::MAX);
+}
+
+#[test]
+fn full_scale_sums_fit_the_accumulator() {
+    // every coefficient close to ±2, every sample at full scale
+    let params = biquad::Params {
+        a0: 1.99,
+        a1: 1.99,
+        a2: 1.99,
+        b1: -1.99,
+        b2: -1.99,
+    };
+    let mut q15 = fixed::Process::<i16>::with_shift(params, 1).unwrap();
+    let mut q31 = fixed::Process::<i32>::with_shift(params, 1).unwrap();
+    for _ in 0..4 {
+        assert_eq!(q15.tick(i16::MAX), i16::MAX);
+        assert_eq!(q31.tick(i32::MAX), i32::MAX);
+    }
+    let mut q15 = fixed::Process::<i16>::with_shift(params, 1).unwrap();
+    let mut q31 = fixed::Process::<i32>::with_shift(params, 1).unwrap();
+    for _ in 0..4 {
+        assert_eq!(q15.tick(i16::MIN), i16::MIN);
+        assert_eq!(q31.tick(i32::MIN), i32::MIN);
+    }
+}
+
+#[test]
+#[should_panic(expected = "fixed: headroom shift 29 of Q31")]
+fn no_fractional_bit_left_to_the_q31_products() {
+    let _ = fixed::Process::<i32>::with_shift(biquad::Params::default(), 29);
+}
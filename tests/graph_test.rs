@@ -0,0 +1,293 @@
+//! Processing graph tests
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::cascade::Cascade;
+use dsp_playground::error::GraphError;
+use dsp_playground::filter;
+use dsp_playground::graph::{self, Endpoint, Graph};
+use dsp_playground::processor::{Processor, Tail};
+use helper::{low_pass, low_pass_params, run, white_noise, FS};
+
+fn assert_close(a: &[f64], b: &[f64]) {
+    assert_eq!(a.len(), b.len());
+    for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+        assert!((a - b).abs() < 1e-12, "sample {}: {} != {}", i, a, b);
+    }
+}
+
+/// Per channel gains without tail, reporting a latency (without delaying)
+struct Gains {
+    gains: Vec<f64>,
+    latency: usize,
+}
+
+impl Processor for Gains {
+    fn reset(&mut self) {}
+
+    fn set_sample_rate(&mut self, _fs: i32) {}
+
+    fn process_block(&mut self, inputs: &[&[f64]], outputs: &mut [&mut [f64]]) {
+        for ((input, output), gain) in inputs.iter().zip(outputs.iter_mut()).zip(self.gains.iter())
+        {
+            for (x, y) in input.iter().zip(output.iter_mut()) {
+                *y = x * gain;
+            }
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.latency
+    }
+
+    fn tail(&self) -> Tail {
+        Tail::Samples(0)
+    }
+
+    fn channels(&self) -> usize {
+        self.gains.len()
+    }
+}
+
+fn gain(gain: f64) -> Box<dyn Processor> {
+    Box::new(Gains {
+        gains: vec![gain],
+        latency: 0,
+    })
+}
+
+#[test]
+fn serial_matches_cascade() {
+    let input = white_noise();
+    let sections = vec![low_pass(1_000.0), low_pass(3_000.0)];
+    let expected = run(&mut Cascade::new(sections.clone()), &input);
+
+    // the max block size is smaller than the input
+    let mut graph = Graph::serial(
+        sections
+            .into_iter()
+            .map(|params| Box::new(biquad::Process::new(params)) as Box<dyn Processor>)
+            .collect(),
+        1,
+        64,
+    )
+    .unwrap();
+    assert_close(&run(&mut graph, &input), &expected);
+
+    let mut passthrough = Graph::serial(vec![], 1, 64).unwrap();
+    assert_close(&run(&mut passthrough, &input), &input);
+}
+
+#[test]
+fn parallel_mixes_the_branches() {
+    let input = white_noise();
+    let expected = run(&mut biquad::Process::new(low_pass(1_000.0)), &input);
+
+    let mut graph = Graph::parallel(
+        vec![
+            (Box::new(biquad::Process::new(low_pass(1_000.0))), 0.25),
+            (Box::new(biquad::Process::new(low_pass(1_000.0))), 0.75),
+        ],
+        1,
+        256,
+    )
+    .unwrap();
+    assert_close(&run(&mut graph, &input), &expected);
+}
+
+#[test]
+fn dry_wet() {
+    let input = white_noise();
+    let processed = run(&mut biquad::Process::new(low_pass(1_000.0)), &input);
+
+    let mut graph = Graph::dry_wet(
+        Box::new(biquad::Process::new(low_pass(1_000.0))),
+        0.3,
+        1,
+        256,
+    )
+    .unwrap();
+    let expected: Vec<f64> = input
+        .iter()
+        .zip(processed.iter())
+        .map(|(dry, wet)| 0.7 * dry + 0.3 * wet)
+        .collect();
+    assert_close(&run(&mut graph, &input), &expected);
+
+    // fully dry, through the edge gains
+    graph.set_gain(graph::DRY_EDGE, 1.0);
+    graph.set_gain(graph::WET_EDGE, 0.0);
+    graph.reset();
+    assert_eq!(graph.gain(graph::DRY_EDGE), 1.0);
+    assert_close(&run(&mut graph, &input), &input);
+}
+
+#[test]
+fn feedback_comb() {
+    // y[n] = x[n] + 0.5 y[n - 10], in blocks longer than the delay
+    let mut builder = Graph::builder(1);
+    let node = builder.add(gain(1.0));
+    builder.connect(Endpoint::Input, Endpoint::Node(node), 1.0);
+    builder.connect(Endpoint::Node(node), Endpoint::Output, 1.0);
+    builder.feedback(node, node, 0.5, 10);
+    let mut graph = builder.build(512).unwrap();
+    assert_eq!(graph.tail(), Tail::Infinite);
+
+    let mut impulse = vec![0.0; 100];
+    impulse[0] = 1.0;
+    let response = run(&mut graph, &impulse);
+    for (i, y) in response.iter().enumerate() {
+        let expected = if i % 10 == 0 {
+            0.5f64.powi(i as i32 / 10)
+        } else {
+            0.0
+        };
+        assert_eq!(*y, expected, "sample {}", i);
+    }
+
+    // same output whatever the host block size
+    graph.reset();
+    let input = white_noise();
+    let expected = run(&mut graph, &input);
+    graph.reset();
+    let mut output = vec![0.0; input.len()];
+    for (x, y) in input.chunks(7).zip(output.chunks_mut(7)) {
+        graph.process_block(&[x], &mut [y]);
+    }
+    assert_close(&output, &expected);
+}
+
+#[test]
+fn feedback_between_nodes() {
+    // a -> b -> output, with b fed back into a
+    let mut builder = Graph::builder(1);
+    let a = builder.add(gain(1.0));
+    let b = builder.add(gain(-1.0));
+    builder.connect(Endpoint::Node(a), Endpoint::Node(b), 1.0);
+    builder.connect(Endpoint::Input, Endpoint::Node(a), 1.0);
+    builder.connect(Endpoint::Node(b), Endpoint::Output, 1.0);
+    builder.feedback(b, a, 0.5, 1);
+    let mut graph = builder.build(64).unwrap();
+
+    // y[n] = -(x[n] + 0.5 y[n - 1])
+    let response = run(&mut graph, &[1.0, 0.0, 0.0, 0.0]);
+    assert_eq!(response, vec![-1.0, 0.5, -0.25, 0.125]);
+}
+
+#[test]
+fn stereo() {
+    let mut graph = Graph::serial(
+        vec![Box::new(Gains {
+            gains: vec![0.5, 2.0],
+            latency: 0,
+        })],
+        2,
+        64,
+    )
+    .unwrap();
+    assert_eq!(graph.channels(), 2);
+    let (left, right) = (vec![1.0; 100], vec![-1.0; 100]);
+    let mut outputs = vec![vec![0.0; 100]; 2];
+    {
+        let (out_left, out_right) = outputs.split_at_mut(1);
+        graph.process_block(&[&left, &right], &mut [&mut out_left[0], &mut out_right[0]]);
+    }
+    assert!(outputs[0].iter().all(|y| *y == 0.5));
+    assert!(outputs[1].iter().all(|y| *y == -2.0));
+}
+
+#[test]
+fn set_sample_rate_reaches_the_nodes() {
+    let input = white_noise();
+    let designed = |fs| {
+        biquad::Process::from_audio_filter_params(
+            low_pass_params(1_000.0),
+            filter::Type::LowPass,
+            fs,
+        )
+    };
+    let inner = Graph::serial(vec![Box::new(designed(FS))], 1, 64).unwrap();
+    let mut graph = Graph::serial(vec![Box::new(inner)], 1, 64).unwrap();
+    graph.set_sample_rate(96000);
+    let expected = run(&mut designed(96000), &input);
+    assert_close(&run(&mut graph, &input), &expected);
+}
+
+#[test]
+fn latency_and_tail() {
+    let delayed = |latency| -> Box<dyn Processor> {
+        Box::new(Gains {
+            gains: vec![1.0],
+            latency,
+        })
+    };
+    let graph = Graph::parallel(vec![(delayed(3), 1.0), (delayed(5), 1.0)], 1, 64).unwrap();
+    assert_eq!(graph.latency(), 5);
+    let graph = Graph::serial(vec![delayed(3), delayed(5)], 1, 64).unwrap();
+    assert_eq!(graph.latency(), 8);
+
+    let filter = biquad::Process::new(low_pass(1_000.0));
+    let section_tail = match filter.tail() {
+        Tail::Samples(samples) => samples,
+        Tail::Infinite => panic!("infinite tail"),
+    };
+    let graph = Graph::serial(
+        vec![
+            Box::new(biquad::Process::new(low_pass(1_000.0))),
+            Box::new(biquad::Process::new(low_pass(1_000.0))),
+        ],
+        1,
+        64,
+    )
+    .unwrap();
+    assert_eq!(graph.tail(), Tail::Samples(2 * section_tail));
+    let graph = Graph::dry_wet(Box::new(filter), 0.5, 1, 64).unwrap();
+    assert_eq!(graph.tail(), Tail::Samples(section_tail));
+}
+
+#[test]
+fn invalid_graphs() {
+    assert_eq!(
+        Graph::serial(vec![], 0, 64).err(),
+        Some(GraphError::InvalidChannelCount { channels: 0 })
+    );
+    assert_eq!(
+        Graph::serial(vec![], 1, 0).err(),
+        Some(GraphError::InvalidBlockSize)
+    );
+    assert_eq!(
+        Graph::serial(vec![gain(1.0)], 2, 64).err(),
+        Some(GraphError::ChannelMismatch {
+            node: 0,
+            channels: 1,
+            expected: 2
+        })
+    );
+
+    let mut builder = Graph::builder(1);
+    let a = builder.add(gain(1.0));
+    let b = builder.add(gain(1.0));
+    builder.connect(Endpoint::Node(a), Endpoint::Node(b), 1.0);
+    builder.connect(Endpoint::Node(b), Endpoint::Node(a), 1.0);
+    assert_eq!(builder.build(64).err(), Some(GraphError::Cycle { node: 0 }));
+
+    let mut builder = Graph::builder(1);
+    let a = builder.add(gain(1.0));
+    builder.feedback(a, a, 1.0, 0);
+    assert_eq!(builder.build(64).err(), Some(GraphError::ZeroDelayFeedback));
+
+    let mut builder = Graph::builder(1);
+    builder.connect(Endpoint::Output, Endpoint::Input, 1.0);
+    assert_eq!(builder.build(64).err(), Some(GraphError::InvalidEdge));
+
+    let mut other = Graph::builder(1);
+    let foreign = other.add(gain(1.0));
+    let mut builder = Graph::builder(1);
+    builder.connect(Endpoint::Input, Endpoint::Node(foreign), 1.0);
+    assert_eq!(
+        builder.build(64).err(),
+        Some(GraphError::UnknownNode { node: 0 })
+    );
+}
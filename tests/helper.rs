@@ -2,23 +2,86 @@
 //!
 //!
 
+#![allow(clippy::ptr_arg, clippy::needless_return, clippy::unnecessary_cast)]
+
+use dsp_playground::biquad;
+use dsp_playground::complex::Complex;
+use dsp_playground::filter;
+use dsp_playground::plot::Plot;
+use dsp_playground::processor::Processor;
+use dsp_playground::response::{self, Point};
 use std::f64;
 
+/// Sample rate of the audio files in tests/assets
+pub const FS: i32 = 44100;
+
+pub const PATH_WHITE_NOISE: &str = "tests/assets/white_noise_mono.wav";
+
+/**
+ * The 16 bit samples of a WAV file, interleaved
+ *
+ * Read with the [`wav`](dsp_playground::wav) module when the feature is on.
+ */
+#[cfg(feature = "wav")]
+pub fn audio_file_samples(path: &str) -> Vec<i16> {
+    use dsp_playground::sample::Scaling;
+
+    let (spec, buffers) = dsp_playground::wav::read(path).unwrap();
+    assert_eq!(spec.format, dsp_playground::wav::Format::Int16, "{}", path);
+    let frames = buffers.first().map_or(0, |b| b.len());
+    (0..frames)
+        .flat_map(|n| {
+            buffers
+                .iter()
+                .map(move |b| Scaling::Symmetric.from_f64(b[n]))
+        })
+        .collect()
+}
+
+/**
+ * The 16 bit samples of a WAV file, interleaved
+ */
+#[cfg(not(feature = "wav"))]
 pub fn audio_file_samples(path: &str) -> Vec<i16> {
     let mut reader = hound::WavReader::open(path).unwrap();
-    let samples = reader.samples::<i16>();
-    let mut samples_vec: Vec<i16> = Vec::new();
-
-    let mut count = 0;
-    {
-        let sampels_vec2 = &mut samples_vec;
-        samples.for_each(|s| {
-            count += 1;
-            sampels_vec2.push(s.unwrap());
-        });
-    }
+    reader.samples::<i16>().map(|s| s.unwrap()).collect()
+}
+
+/**
+ * The white noise file, from -1.0 to 1.0
+ */
+pub fn white_noise() -> Vec<f64> {
+    audio_file_samples(PATH_WHITE_NOISE)
+        .iter()
+        .map(|s| *s as f64 / i16::MAX as f64)
+        .collect()
+}
+
+pub fn filter_params(fc: f64, q: f64, gain_db: f64) -> filter::Params {
+    filter::Params { fc, q, gain_db }
+}
+
+/**
+ * Butterworth low pass: q = 1/sqrt(2), no gain
+ */
+pub fn low_pass_params(fc: f64) -> filter::Params {
+    filter_params(fc, f64::consts::FRAC_1_SQRT_2, 0.0)
+}
+
+/**
+ * Butterworth low pass coefficients, at the sample rate of the audio files
+ */
+pub fn low_pass(fc: f64) -> biquad::Params {
+    biquad::Params::from_audio_filter_params(low_pass_params(fc), filter::Type::LowPass, FS)
+}
 
-    samples_vec
+/**
+ * What a host does: mono in, mono out, through any processor
+ */
+pub fn run(processor: &mut dyn Processor, input: &[f64]) -> Vec<f64> {
+    let mut output = vec![0.0; input.len()];
+    processor.process_block(&[input], &mut [&mut output]);
+    output
 }
 
 /**
@@ -100,3 +163,113 @@ pub fn mean(xs: &Vec<i16>) -> f64 {
 
     sum as f64 / xs.len() as f64
 }
+
+/**
+ * Asserting the design of the filter, with a plot of both responses on failure
+ *
+ * The plot is written to tests/assets/temp_plot_<name>.svg, to compare by eye with
+ * https://www.earlevel.com/main/2010/12/20/biquad-calculator/
+ */
+pub fn assert_design_eq(
+    name: &str,
+    params: filter::Params,
+    filter_type: filter::Type,
+    fs: i32,
+    expected: biquad::Params,
+) {
+    let actual = biquad::Params::from_audio_filter_params(params, filter_type, fs);
+    if actual == expected {
+        return;
+    }
+    let path = plot_path(name);
+    Plot::new(fs)
+        .title(name)
+        .curve("actual", &actual)
+        .curve("expected", &expected)
+        .write(&path)
+        .unwrap();
+    panic!(
+        "{}: {:?} != {:?} (plot in {})",
+        name, actual, expected, path
+    );
+}
+
+/**
+ * Asserting a filtered audio file against its snapshot, with a plot on failure
+ *
+ * The plot overlays the designed response and the ones measured from the input to
+ * the snapshot and to the filtered samples (in tests/assets/temp_plot_<name>.svg).
+ */
+pub fn assert_snapshot_eq(
+    name: &str,
+    input: &Vec<i16>,
+    snapshot: &Vec<i16>,
+    filtered: &Vec<i16>,
+    params: biquad::Params,
+) {
+    let cci = cross_correlation_index(snapshot, filtered);
+    let rmse = rmse(snapshot, filtered);
+    if cci > 0.9999 && rmse < 1.0 {
+        return;
+    }
+    let path = plot_path(name);
+    let mut plot = Plot::new(FS)
+        .title(name)
+        .phase(false)
+        .curve("designed", &params);
+    plot.add_points("snapshot", measured_response(input, snapshot));
+    plot.add_points("filtered", measured_response(input, filtered));
+    plot.write(&path).unwrap();
+    panic!(
+        "{}: cci {} (> 0.9999), rmse {} (< 1.0) (plot in {})",
+        name, cci, rmse, path
+    );
+}
+
+fn plot_path(name: &str) -> String {
+    format!("tests/assets/temp_plot_{}.svg", name)
+}
+
+/**
+ * Magnitude response from the input to the output, averaged over windowed segments
+ *
+ * The cross spectrum over the input spectrum (H1 estimate), without the phase.
+ */
+fn measured_response(input: &Vec<i16>, output: &Vec<i16>) -> Vec<Point> {
+    const SEGMENT: usize = 4096;
+    let window: Vec<f64> = (0..SEGMENT)
+        .map(|n| 0.5 - 0.5 * (2.0 * f64::consts::PI * n as f64 / SEGMENT as f64).cos())
+        .collect();
+    let spectrum = |segment: &[i16], f: f64| {
+        let w = 2.0 * f64::consts::PI * f / FS as f64;
+        segment
+            .iter()
+            .zip(window.iter())
+            .enumerate()
+            .fold(Complex::ZERO, |sum, (n, (x, h))| {
+                sum + Complex::unit(-w * n as f64) * (*x as f64 * h)
+            })
+    };
+
+    response::log_frequencies(20.0, FS as f64 / 2.0, 128)
+        .into_iter()
+        .map(|f| {
+            let mut cross = Complex::ZERO;
+            let mut power = 0.0;
+            for (x, y) in input
+                .chunks_exact(SEGMENT)
+                .zip(output.chunks_exact(SEGMENT))
+            {
+                let x = spectrum(x, f);
+                cross = cross + spectrum(y, f) * x.conj();
+                power += x.norm_sqr();
+            }
+            Point {
+                f,
+                magnitude_db: 20.0 * (cross.norm() / power).log10(),
+                phase: 0.0,
+                group_delay: 0.0,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,290 @@
+//! Ladder filter tests
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::ladder::{DiodeLadder, TransistorLadder};
+use dsp_playground::sample::{Quantizer, Scaling};
+use helper::{white_noise, FS, PATH_WHITE_NOISE};
+use std::f64::consts::PI;
+
+/// Output after an impulse, then silence
+fn impulse_response(ladder: &mut TransistorLadder, length: usize) -> Vec<f64> {
+    (0..length)
+        .map(|i| ladder.tick(if i == 0 { 1.0 } else { 0.0 }))
+        .collect()
+}
+
+/// Frequency from the (interpolated) rising zero crossings
+fn frequency(xs: &[f64]) -> f64 {
+    let crossings: Vec<f64> = xs
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+        .map(|(i, pair)| i as f64 + pair[0] / (pair[0] - pair[1]))
+        .collect();
+    let periods = (crossings.len() - 1) as f64;
+    FS as f64 * periods / (crossings[crossings.len() - 1] - crossings[0])
+}
+
+fn peak(xs: &[f64]) -> f64 {
+    xs.iter().fold(0.0f64, |max, x| max.max(x.abs()))
+}
+
+#[test]
+fn self_oscillation_tracks_cutoff() {
+    for cutoff in [110.0, 220.0, 440.0, 880.0, 1_760.0, 3_520.0].iter() {
+        let mut ladder = TransistorLadder::new(*cutoff, 1.0, FS);
+        let output = impulse_response(&mut ladder, 2 * FS as usize);
+
+        // steady oscillation over the last second
+        let steady = &output[FS as usize..];
+        assert_gt!(peak(steady), 0.1, "{} Hz", cutoff);
+        let f = frequency(steady);
+        assert_lt!((f / cutoff - 1.0).abs(), 0.05, "{} Hz: {} Hz", cutoff, f);
+    }
+}
+
+#[test]
+fn no_self_oscillation_at_low_resonance() {
+    for resonance in [0.0, 0.5, 0.8].iter() {
+        let mut ladder = TransistorLadder::new(1_000.0, *resonance, FS);
+        let output = impulse_response(&mut ladder, FS as usize);
+        assert_lt!(peak(&output[FS as usize / 2..]), 1e-9, "{}", resonance);
+    }
+}
+
+#[test]
+fn low_pass_response() {
+    // small signals: linear, a DC gain of 1 / (1 + 40/9 resonance)
+    for resonance in [0.0, 0.5].iter() {
+        let mut ladder = TransistorLadder::new(1_000.0, *resonance, FS);
+        let mut y = 0.0;
+        for _ in 0..FS {
+            y = ladder.tick(0.001);
+        }
+        let expected = 0.001 / (1.0 + 40.0 / 9.0 * resonance);
+        assert_lt!((y - expected).abs(), 1e-6 * expected, "{}", resonance);
+    }
+
+    // -24 dB/oct: a decade above the cutoff is -80 dB
+    let sine = |f: f64| -> Vec<f64> {
+        (0..FS as usize)
+            .map(|i| 0.01 * (2.0 * PI * f * i as f64 / FS as f64).sin())
+            .collect()
+    };
+    let mut ladder = TransistorLadder::new(200.0, 0.0, FS);
+    let mut output = sine(2_000.0);
+    ladder.process_in_place(&mut output);
+    let gain_db = 20.0 * (peak(&output[FS as usize / 2..]) / 0.01).log10();
+    assert_lt!((gain_db + 80.0).abs(), 2.0);
+}
+
+#[test]
+fn saturates_large_inputs() {
+    let input: Vec<f64> = white_noise().iter().map(|x| 100.0 * x).collect();
+    let mut ladder = TransistorLadder::new(5_000.0, 0.95, FS);
+    let mut output = vec![0.0; input.len()];
+    ladder.process_block(&input, &mut output);
+    assert!(output.iter().all(|y| y.is_finite()));
+    assert_lt!(peak(&output), 30.0);
+}
+
+#[test]
+fn parameters_are_clamped() {
+    let mut ladder = TransistorLadder::new(1e9, 2.0, FS);
+    assert_eq!(ladder.cutoff(), 0.45 * FS as f64);
+    assert_eq!(ladder.resonance(), 1.0);
+    ladder.set_cutoff(-10.0);
+    ladder.set_resonance(-1.0);
+    assert_gt!(ladder.cutoff(), 0.0);
+    assert_eq!(ladder.resonance(), 0.0);
+}
+
+#[test]
+fn process_block_matches_tick() {
+    let input = white_noise();
+    let mut block = TransistorLadder::new(800.0, 0.7, FS);
+    let mut per_sample = TransistorLadder::new(800.0, 0.7, FS);
+    let mut output = vec![0.0; input.len()];
+    block.process_block(&input, &mut output);
+    for (x, y) in input.iter().zip(output.iter()) {
+        assert_eq!(per_sample.tick(*x), *y);
+    }
+
+    block.reset();
+    let mut in_place = input.clone();
+    block.process_in_place(&mut in_place);
+    assert_eq!(in_place, output);
+}
+
+const PATH_SNAPSHOT_DIODE_LADDER: &str = "tests/assets/snapshot_diode_ladder_reference.wav";
+
+/// Sweep of tests/assets/diode_ladder_reference.cpp: 0.005 to 0.5 (normalized)
+fn sweep(i: usize, n: usize) -> f64 {
+    0.005 * 100f64.powf(i as f64 / n as f64)
+}
+
+#[test]
+fn diode_ladder_matches_reference() {
+    let input = white_noise();
+    let reference: Vec<f32> = hound::WavReader::open(PATH_SNAPSHOT_DIODE_LADDER)
+        .unwrap()
+        .samples::<f32>()
+        .map(|s| s.unwrap())
+        .collect();
+    assert_eq!(reference.len(), input.len());
+
+    let n = input.len();
+    let half = n / 2;
+    let mut ladder = DiodeLadder::new();
+    ladder.set_q(0.9);
+    for (i, (x, expected)) in input.iter().zip(reference.iter()).enumerate() {
+        if i == half {
+            // self oscillating: no reset
+            ladder.reset();
+        }
+        if i == half + half / 2 {
+            ladder.set_q(0.3);
+            ladder.reset();
+        }
+        let y = ladder.tick(0.5 * x, sweep(i, n));
+        assert_lt!(
+            (y - *expected as f64).abs(),
+            1e-6 * y.abs().max(1.0),
+            "sample {}",
+            i
+        );
+    }
+}
+
+#[test]
+fn diode_ladder_reset_keeps_self_oscillation() {
+    let mut ladder = DiodeLadder::new();
+    ladder.set_q(0.9);
+    let mut ringing = 0.0;
+    for i in 0..FS {
+        ringing = ladder.tick(if i == 0 { 1.0 } else { 0.0 }, 0.05);
+    }
+    assert_gt!(ringing.abs(), 0.0);
+    ladder.reset();
+    assert_ne!(ladder.tick(0.0, 0.05), 0.0);
+
+    ladder.set_q(0.8);
+    ladder.reset();
+    assert_eq!(ladder.tick(0.0, 0.05), 0.0);
+}
+
+#[test]
+fn diode_ladder_feedback_high_pass() {
+    // DC: without the high pass, the feedback takes away most of the low end
+    let dc_gain = |hpf_cutoff: f64| {
+        let mut ladder = DiodeLadder::new();
+        ladder.set_q(0.5);
+        ladder.set_feedback_hpf_cutoff(hpf_cutoff);
+        let mut y = 0.0;
+        for _ in 0..FS {
+            y = ladder.tick(0.001, 0.1);
+        }
+        y / 0.001
+    };
+    let without = dc_gain(0.0);
+    let with = dc_gain(0.005);
+    assert_lt!(without, 1.0);
+    // no feedback at DC: only the gain compensation 1 + k/2
+    assert_lt!((with - 6.0).abs(), 0.01);
+
+    // off is the reference
+    let input = white_noise();
+    let mut reference = DiodeLadder::new();
+    let mut turned_off = DiodeLadder::new();
+    reference.set_q(0.7);
+    turned_off.set_q(0.7);
+    turned_off.set_feedback_hpf_cutoff(0.01);
+    turned_off.set_feedback_hpf_cutoff(0.0);
+    for x in input.iter() {
+        assert_eq!(reference.tick(*x, 0.2), turned_off.tick(*x, 0.2));
+    }
+}
+
+#[test]
+fn diode_ladder_blocks() {
+    let input = white_noise();
+    let mut per_sample = DiodeLadder::new();
+    per_sample.set_q(0.6);
+    let expected: Vec<f64> = input.iter().map(|x| per_sample.tick(*x, 0.1)).collect();
+
+    let mut ladder = DiodeLadder::new();
+    ladder.set_q(0.6);
+    let mut output = vec![0.0; input.len()];
+    ladder.process_block(&input, &mut output, 0.1);
+    assert_eq!(output, expected);
+
+    // f32
+    ladder.reset();
+    let mut samples: Vec<f32> = input.iter().map(|x| *x as f32).collect();
+    ladder.process_in_place(&mut samples, 0.1);
+    for (y, expected) in samples.iter().zip(expected.iter()) {
+        assert_lt!((*y as f64 - expected).abs(), 1e-5);
+    }
+}
+
+#[test]
+fn diode_ladder_parameters_are_clamped() {
+    let input = white_noise();
+    let run = |q: f64, hpf_cutoff: f64, fc: f64| {
+        let mut ladder = DiodeLadder::new();
+        ladder.set_q(q);
+        ladder.set_feedback_hpf_cutoff(hpf_cutoff);
+        let mut output = vec![0.0; input.len()];
+        ladder.process_block(&input, &mut output, fc);
+        output
+    };
+    assert_eq!(run(1.5, 0.0, 0.1), run(1.0, 0.0, 0.1));
+    assert_eq!(run(-1.0, -0.5, 0.1), run(0.0, 0.0, 0.1));
+    assert_eq!(run(0.5, 2.0, 0.1), run(0.5, 0.99, 0.1));
+    assert_eq!(run(0.5, 0.0, 1.0), run(0.5, 0.0, 0.99));
+    for fc in [-1.0, 0.0, 1.0, 10.0, f64::NAN].iter() {
+        assert!(run(0.9, 0.01, *fc).iter().all(|y| y.is_finite()), "{}", fc);
+    }
+}
+
+#[test]
+fn quantized_blocks() {
+    let input: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let mut rounded = vec![0i16; input.len()];
+    let mut output = vec![0i16; input.len()];
+
+    // without dither, the same rounding as process_block
+    TransistorLadder::new(800.0, 0.7, FS).process_block(&input, &mut rounded);
+    TransistorLadder::new(800.0, 0.7, FS).process_block_quantized(
+        &input,
+        &mut output,
+        &mut Quantizer::new(Scaling::Symmetric),
+    );
+    assert_eq!(output, rounded);
+
+    DiodeLadder::new().process_block(&input, &mut rounded, 0.1);
+    DiodeLadder::new().process_block_quantized(
+        &input,
+        &mut output,
+        &mut Quantizer::new(Scaling::Symmetric),
+        0.1,
+    );
+    assert_eq!(output, rounded);
+
+    // dithered, within a few LSB
+    DiodeLadder::new().process_block_quantized(
+        &input,
+        &mut output,
+        &mut Quantizer::with_tpdf_dither(Scaling::Symmetric, 42),
+        0.1,
+    );
+    assert_ne!(output, rounded);
+    for (y, r) in output.iter().zip(rounded.iter()) {
+        assert_le!((*y as i32 - *r as i32).abs(), 2);
+    }
+}
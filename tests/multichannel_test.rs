@@ -0,0 +1,110 @@
+//! Multichannel biquad tests
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::filter;
+use dsp_playground::processor::Processor;
+use helper::{low_pass, white_noise};
+use std::f64::consts::FRAC_1_SQRT_2;
+
+fn mono(params: biquad::Params, input: &[f64]) -> Vec<f64> {
+    let mut output = vec![0.0; input.len()];
+    biquad::Process::new(params).process_block(input, &mut output);
+    output
+}
+
+fn interleave(left: &[f64], right: &[f64]) -> Vec<f64> {
+    left.iter()
+        .zip(right.iter())
+        .flat_map(|(l, r)| vec![*l, *r])
+        .collect()
+}
+
+#[test]
+fn channels_do_not_bleed() {
+    let noise = white_noise();
+    let silence = vec![0.0; noise.len()];
+    let mut process = biquad::MultichannelProcess::new(low_pass(1_000.0), 2);
+    assert_eq!(process.channels(), 2);
+
+    let mut left = vec![0.0; noise.len()];
+    let mut right = vec![0.0; noise.len()];
+    process.process_planar(&[&noise, &silence], &mut [&mut left, &mut right]);
+    assert_eq!(left, mono(low_pass(1_000.0), &noise));
+    assert!(right.iter().all(|s| *s == 0.0));
+}
+
+#[test]
+fn interleaved_matches_planar() {
+    let noise = white_noise();
+    let reversed: Vec<f64> = noise.iter().rev().cloned().collect();
+    let params = vec![low_pass(1_000.0), low_pass(5_000.0)];
+    let expected = interleave(&mono(params[0], &noise), &mono(params[1], &reversed));
+
+    let input = interleave(&noise, &reversed);
+    let mut process = biquad::MultichannelProcess::with_channel_params(params.clone());
+    let mut output = vec![0.0; input.len()];
+    process.process_interleaved(&input, &mut output);
+    assert_eq!(output, expected);
+
+    let mut process = biquad::MultichannelProcess::with_channel_params(params);
+    let mut samples = input;
+    process.process_interleaved_in_place(&mut samples);
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn shared_and_per_channel_params() {
+    let mut process = biquad::MultichannelProcess::new(low_pass(1_000.0), 3);
+    process.set_params(low_pass(2_000.0));
+    process
+        .channel_mut(1)
+        .unwrap()
+        .set_params(low_pass(3_000.0));
+    let params: Vec<biquad::Params> = (0..3)
+        .map(|channel| *process.channel_mut(channel).unwrap().params())
+        .collect();
+    assert_eq!(
+        params,
+        vec![low_pass(2_000.0), low_pass(3_000.0), low_pass(2_000.0)]
+    );
+    assert!(process.channel_mut(3).is_none());
+}
+
+#[test]
+fn as_a_processor() {
+    let noise = white_noise();
+    let filter_params = filter::Params {
+        fc: 1_000.0,
+        q: FRAC_1_SQRT_2,
+        gain_db: 0.0,
+    };
+    let mut process = biquad::MultichannelProcess::from_audio_filter_params(
+        filter_params,
+        filter::Type::LowPass,
+        44100,
+        2,
+    );
+    process.set_sample_rate(96000);
+    let params =
+        biquad::Params::from_audio_filter_params(filter_params, filter::Type::LowPass, 96000);
+
+    let processor: &mut dyn Processor = &mut process;
+    assert_eq!(processor.channels(), 2);
+    assert_eq!(processor.tail(), biquad::Process::new(params).tail());
+    let mut left = vec![0.0; noise.len()];
+    let mut right = vec![0.0; noise.len()];
+    processor.process_block(&[&noise, &noise], &mut [&mut left, &mut right]);
+    assert_eq!(left, mono(params, &noise));
+    assert_eq!(right, left);
+}
+
+#[test]
+#[should_panic(expected = "not a multiple of 2 channels")]
+fn incomplete_interleaved_frame() {
+    let mut process = biquad::MultichannelProcess::new(low_pass(1_000.0), 2);
+    let mut samples = vec![0.0; 5];
+    process.process_interleaved_in_place(&mut samples);
+}
@@ -0,0 +1,203 @@
+//! SVG plots tests
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::cascade::Cascade;
+use dsp_playground::filter;
+use dsp_playground::plot::Plot;
+use dsp_playground::response;
+use helper::{FS, PATH_WHITE_NOISE};
+
+fn design(fc: f64, q: f64, gain_db: f64, filter_type: filter::Type) -> biquad::Params {
+    biquad::Params::from_audio_filter_params(filter::Params { fc, q, gain_db }, filter_type, FS)
+}
+
+/// The points of each polyline, as (x, y)
+fn polylines(svg: &str) -> Vec<Vec<(f64, f64)>> {
+    svg.lines()
+        .filter(|line| line.starts_with("<polyline"))
+        .map(|line| {
+            let points = line.split("points=\"").nth(1).unwrap();
+            points
+                .trim_end_matches("\"/>")
+                .split(' ')
+                .map(|point| {
+                    let mut xy = point.split(',').map(|v| v.parse::<f64>().unwrap());
+                    (xy.next().unwrap(), xy.next().unwrap())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn labels(svg: &str) -> Vec<String> {
+    svg.split("<text")
+        .skip(1)
+        .map(|text| text[text.find('>').unwrap() + 1..text.find("</text>").unwrap()].to_string())
+        .collect()
+}
+
+#[test]
+fn overlaid_curves() {
+    let low_pass = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let cascade = Cascade::from_audio_filter_params(
+        &[
+            (
+                filter::Params::builder(80.0).build(FS).unwrap(),
+                filter::Type::HighPass,
+            ),
+            (
+                filter::Params::builder(3000.0)
+                    .q(2.0)
+                    .gain_db(9.0)
+                    .build(FS)
+                    .unwrap(),
+                filter::Type::Peak,
+            ),
+        ],
+        FS,
+    );
+    let sections = [low_pass, design(5000.0, 1.0, 0.0, filter::Type::HighPass)];
+    let svg = Plot::new(FS)
+        .title("eq & <filters>")
+        .curve("low pass", &low_pass)
+        .curve("cascade", &cascade)
+        .curve("sections", &sections[..])
+        .to_svg();
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(!svg.contains("NaN") && !svg.contains("inf"));
+
+    // magnitudes, then phases
+    let polylines = polylines(&svg);
+    assert_eq!(polylines.len(), 6);
+    for polyline in polylines.iter() {
+        assert_eq!(polyline.len(), 512);
+        // 20 Hz to fs/2 on the width of the panel
+        assert_eq!(polyline[0].0, 60.0);
+        assert_eq!(polyline[511].0, 780.0);
+    }
+    // the low pass goes down
+    assert!(polylines[0][511].1 > polylines[0][0].1);
+
+    let labels = labels(&svg);
+    assert_eq!(labels[0], "eq &amp; &lt;filters&gt;");
+    for label in [
+        "20", "100", "1k", "10k", "20k", "dB", "deg", "low pass", "cascade",
+    ]
+    .iter()
+    {
+        assert!(labels.contains(&label.to_string()), "{}", label);
+    }
+    // the auto range includes the peak
+    assert!(labels.contains(&"12".to_string()));
+}
+
+#[test]
+fn zeros_and_ranges() {
+    let notch = design(1000.0, 2.0, 0.0, filter::Type::Notch);
+    let plot = Plot::new(FS)
+        .frequency_range(1000.0, 10_000.0)
+        .db_range(-30.0, 10.0)
+        .phase(false)
+        .curve("notch", &notch);
+    let svg = plot.to_svg();
+    assert!(!svg.contains("NaN") && !svg.contains("inf"));
+    assert!(svg.contains(&format!("height=\"{}\"", plot.height())));
+
+    let polylines = polylines(&svg);
+    assert_eq!(polylines.len(), 1);
+    // the zero at 1 kHz is clamped to the bottom of the panel (40 + 320)
+    assert_eq!(polylines[0][0].1, 360.0);
+
+    let labels = labels(&svg);
+    assert!(labels.contains(&"-30".to_string()) && labels.contains(&"10".to_string()));
+    assert!(labels.contains(&"2k".to_string()) && labels.contains(&"5k".to_string()));
+    assert!(!labels.contains(&"deg".to_string()));
+}
+
+#[test]
+fn measured_points() {
+    let frequencies = response::log_frequencies(20.0, 20_000.0, 10);
+    let points = response::evaluate(&biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6, &frequencies, FS);
+    let mut plot = Plot::new(FS).frequency_range(20.0, 20_000.0);
+    plot.add_points("measured", points);
+    plot.add_curve("designed", &biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+
+    let polylines = polylines(&plot.to_svg());
+    assert_eq!(polylines[0].len(), 10);
+    // on the designed curve
+    assert_eq!(polylines[0][0], polylines[1][0]);
+    assert_eq!(polylines[0][9], polylines[1][511]);
+}
+
+#[test]
+fn artifact_of_a_failed_design() {
+    let path = "tests/assets/temp_plot_failed_design.svg";
+    let _ = std::fs::remove_file(path);
+    let low_pass = |fc| filter::Params {
+        fc,
+        q: 2.0,
+        gain_db: 0.0,
+    };
+    let expected = design(1000.0, 2.0, 0.0, filter::Type::LowPass);
+
+    helper::assert_design_eq(
+        "passing_design",
+        low_pass(1000.0),
+        filter::Type::LowPass,
+        FS,
+        expected,
+    );
+    let result = std::panic::catch_unwind(|| {
+        helper::assert_design_eq(
+            "failed_design",
+            low_pass(1100.0),
+            filter::Type::LowPass,
+            FS,
+            expected,
+        )
+    });
+    assert!(result.is_err());
+
+    let svg = std::fs::read_to_string(path).unwrap();
+    let labels = labels(&svg);
+    assert!(labels.contains(&"actual".to_string()) && labels.contains(&"expected".to_string()));
+    assert!(!std::path::Path::new("tests/assets/temp_plot_passing_design.svg").exists());
+}
+
+#[test]
+fn artifact_of_a_failed_audio_snapshot() {
+    let path = "tests/assets/temp_plot_failed_audio_snapshot.svg";
+    let _ = std::fs::remove_file(path);
+    let white_noise = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let snapshot =
+        helper::audio_file_samples("tests/assets/snapshot_lowpass_fc_1000_Q_0.7071_gain_6.wav");
+    let params = design(1100.0, 0.7, 6.0, filter::Type::LowPass);
+    let mut process = biquad::Process::new(params);
+    let filtered: Vec<i16> = white_noise.iter().map(|s| process.process(s)).collect();
+
+    let result = std::panic::catch_unwind(|| {
+        helper::assert_snapshot_eq(
+            "failed_audio_snapshot",
+            &white_noise,
+            &snapshot,
+            &filtered,
+            params,
+        )
+    });
+    assert!(result.is_err());
+
+    let svg = std::fs::read_to_string(path).unwrap();
+    let labels = labels(&svg);
+    for label in ["designed", "snapshot", "filtered"].iter() {
+        assert!(labels.contains(&label.to_string()), "{}", label);
+    }
+    // magnitude only, the measured curves at their own frequencies
+    let polylines = polylines(&svg);
+    assert_eq!(polylines.len(), 3);
+    assert_eq!(polylines[1].len(), polylines[2].len());
+}
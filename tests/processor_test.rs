@@ -0,0 +1,403 @@
+//! Processor trait tests: hosting the blocks generically
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::cascade::Cascade;
+use dsp_playground::character::{Korg35, Ms20, Sem};
+use dsp_playground::crossover::{self, Crossover};
+use dsp_playground::filter::{self, Filter};
+use dsp_playground::fixed;
+use dsp_playground::ladder::{DiodeLadder, TransistorLadder};
+use dsp_playground::processor::{self, Processor, Tail};
+use dsp_playground::smoothing::SmoothedProcess;
+use dsp_playground::svf;
+use helper::{low_pass, low_pass_params, run, white_noise};
+use std::f64::consts::FRAC_1_SQRT_2;
+
+#[test]
+fn same_output_as_the_concrete_types() {
+    let input = white_noise();
+    let params = low_pass(1_000.0);
+
+    let mut expected = vec![0.0; input.len()];
+    biquad::Process::new(params).process_block(&input, &mut expected);
+
+    let mut processors: Vec<Box<dyn Processor>> = vec![
+        Box::new(biquad::Process::new(params)),
+        Box::new(Cascade::new(vec![params])),
+        Box::new(SmoothedProcess::new(
+            low_pass_params(1_000.0),
+            filter::Type::LowPass,
+            44100,
+        )),
+    ];
+    for processor in processors.iter_mut() {
+        assert_eq!(processor.channels(), 1);
+        assert_eq!(processor.latency(), 0);
+        assert_eq!(run(processor.as_mut(), &input), expected);
+    }
+}
+
+#[test]
+fn every_module_is_hosted() {
+    let input = white_noise();
+    let mut hosted: Vec<(&str, Box<dyn Processor>, Vec<f64>)> = vec![];
+    let mut output = vec![0.0; input.len()];
+
+    let svf_params = svf::Params::from_audio_filter_params(
+        low_pass_params(1_000.0),
+        filter::Type::LowPass,
+        44100,
+    );
+    svf::Process::new(svf_params).process_block(&input, &mut output);
+    hosted.push((
+        "svf",
+        Box::new(svf::Process::new(svf_params)),
+        output.clone(),
+    ));
+
+    TransistorLadder::new(800.0, 0.7, 44100).process_block(&input, &mut output);
+    let ladder = TransistorLadder::new(800.0, 0.7, 44100);
+    hosted.push(("transistor ladder", Box::new(ladder), output.clone()));
+
+    let diode_ladder = || {
+        let mut ladder = DiodeLadder::new();
+        ladder.set_q(0.5);
+        ladder
+    };
+    diode_ladder().process_block(&input, &mut output, 0.1);
+    let mut ladder = diode_ladder();
+    ladder.set_cutoff(0.1);
+    hosted.push(("diode ladder", Box::new(ladder), output.clone()));
+
+    type New = fn() -> Box<dyn Filter>;
+    let character: Vec<(&str, New)> = vec![
+        ("ms20", || {
+            Box::new(Ms20::new(filter::Type::LowPass, 1_000.0, 0.5, 44100))
+        }),
+        ("korg35", || {
+            Box::new(Korg35::new(filter::Type::HighPass, 1_000.0, 0.5, 44100))
+        }),
+        ("sem", || {
+            Box::new(Sem::new(filter::Type::BandPass, 1_000.0, 0.5, 44100))
+        }),
+    ];
+    for (name, filter) in character {
+        let mut expected = input.clone();
+        filter().tick_block(&mut expected);
+        hosted.push((name, filter(), expected));
+    }
+
+    let params = low_pass(1_000.0);
+    let fixed_input: Vec<i16> = input.iter().map(|x| (x * 32768.0).round() as i16).collect();
+    let mut fixed_output = vec![0i16; input.len()];
+    fixed::Process::<i16>::new(params)
+        .unwrap()
+        .process_block(&fixed_input, &mut fixed_output);
+    let expected = fixed_output.iter().map(|y| *y as f64 / 32768.0).collect();
+    let process = fixed::Process::<i16>::new(params).unwrap();
+    hosted.push(("fixed", Box::new(process), expected));
+
+    let gains = [1.0, 0.5, 2.0];
+    let mut bands = vec![vec![0.0; input.len()]; 3];
+    let mut outputs: Vec<&mut [f64]> = bands.iter_mut().map(|b| &mut b[..]).collect();
+    Crossover::new(crossover::Order::LR4, &[200.0, 2_000.0], 44100)
+        .process_block(&input, &mut outputs);
+    let expected = (0..input.len())
+        .map(|i| (0..3).map(|band| gains[band] * bands[band][i]).sum())
+        .collect();
+    let mut crossover = Crossover::new(crossover::Order::LR4, &[200.0, 2_000.0], 44100);
+    for (band, gain) in gains.iter().enumerate() {
+        crossover.set_band_gain(band, *gain);
+    }
+    hosted.push(("crossover", Box::new(crossover), expected));
+
+    for (name, processor, expected) in hosted.iter_mut() {
+        assert_eq!(processor.channels(), 1, "{}", name);
+        assert_eq!(run(processor.as_mut(), &input), *expected, "{}", name);
+        processor.reset();
+        assert_eq!(run(processor.as_mut(), &input), *expected, "{}", name);
+    }
+}
+
+#[test]
+fn set_sample_rate_redesigns() {
+    let designed_at = |fs| {
+        biquad::Params::from_audio_filter_params(
+            low_pass_params(1_000.0),
+            filter::Type::LowPass,
+            fs,
+        )
+    };
+
+    let mut process = biquad::Process::from_audio_filter_params(
+        low_pass_params(1_000.0),
+        filter::Type::LowPass,
+        44100,
+    );
+    assert_eq!(*process.params(), designed_at(44100));
+    process.set_sample_rate(96000);
+    assert_eq!(*process.params(), designed_at(96000));
+
+    let mut cascade = Cascade::from_audio_filter_params(
+        &[
+            (low_pass_params(1_000.0), filter::Type::LowPass),
+            (low_pass_params(1_000.0), filter::Type::LowPass),
+        ],
+        44100,
+    );
+    cascade.set_sample_rate(96000);
+    assert!(cascade.params().all(|params| *params == designed_at(96000)));
+
+    let mut svf = svf::Process::from_audio_filter_params(
+        low_pass_params(1_000.0),
+        filter::Type::LowPass,
+        44100,
+    );
+    svf.set_sample_rate(96000);
+    assert_eq!(
+        *svf.params(),
+        svf::Params::from_audio_filter_params(
+            low_pass_params(1_000.0),
+            filter::Type::LowPass,
+            96000
+        )
+    );
+
+    let mut smoothed = SmoothedProcess::new(low_pass_params(1_000.0), filter::Type::LowPass, 44100);
+    smoothed.set_target(low_pass_params(2_000.0));
+    smoothed.set_sample_rate(96000);
+    assert!(!smoothed.is_smoothing());
+    assert_eq!(
+        smoothed.params(),
+        biquad::Params::from_audio_filter_params(
+            low_pass_params(2_000.0),
+            filter::Type::LowPass,
+            96000
+        )
+    );
+}
+
+#[test]
+fn raw_coefficients_are_kept() {
+    let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    process.set_sample_rate(96000);
+    assert_eq!(*process.params(), biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+
+    let svf_params = svf::Params::from_audio_filter_params(
+        low_pass_params(1_000.0),
+        filter::Type::LowPass,
+        44100,
+    );
+    let mut svf = svf::Process::new(svf_params);
+    svf.set_sample_rate(96000);
+    assert_eq!(*svf.params(), svf_params);
+}
+
+#[test]
+fn set_params_are_kept_by_set_sample_rate() {
+    let mut process = biquad::Process::from_audio_filter_params(
+        low_pass_params(1_000.0),
+        filter::Type::LowPass,
+        44100,
+    );
+    process.set_params(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    process.set_sample_rate(96000);
+    assert_eq!(*process.params(), biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+
+    let mut svf = svf::Process::from_audio_filter_params(
+        low_pass_params(1_000.0),
+        filter::Type::LowPass,
+        44100,
+    );
+    let svf_params = svf::Params::from_audio_filter_params(
+        low_pass_params(2_000.0),
+        filter::Type::HighPass,
+        44100,
+    );
+    svf.set_params(svf_params);
+    svf.set_sample_rate(96000);
+    assert_eq!(*svf.params(), svf_params);
+
+    let mut multichannel = biquad::MultichannelProcess::from_audio_filter_params(
+        low_pass_params(1_000.0),
+        filter::Type::LowPass,
+        44100,
+        2,
+    );
+    multichannel.set_params(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    multichannel.set_sample_rate(96000);
+    for channel in 0..2 {
+        assert_eq!(
+            *multichannel.channel_mut(channel).unwrap().params(),
+            biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6
+        );
+    }
+
+    let mut cascade = Cascade::from_audio_filter_params(
+        &[
+            (low_pass_params(1_000.0), filter::Type::LowPass),
+            (low_pass_params(1_000.0), filter::Type::LowPass),
+        ],
+        44100,
+    );
+    cascade
+        .section_mut(1)
+        .unwrap()
+        .set_params(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    cascade.set_sample_rate(96000);
+    let params: Vec<biquad::Params> = cascade.params().cloned().collect();
+    assert_eq!(
+        params,
+        vec![
+            biquad::Params::from_audio_filter_params(
+                low_pass_params(1_000.0),
+                filter::Type::LowPass,
+                96000
+            ),
+            biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6
+        ]
+    );
+}
+
+#[test]
+fn impulse_decays_within_the_tail() {
+    for q in [FRAC_1_SQRT_2, 10.0] {
+        let params = filter::Params {
+            fc: 100.0,
+            q,
+            gain_db: 0.0,
+        };
+        let mut process =
+            biquad::Process::from_audio_filter_params(params, filter::Type::LowPass, 44100);
+        let tail = match process.tail() {
+            Tail::Samples(samples) => samples,
+            Tail::Infinite => panic!("infinite tail for q {}", q),
+        };
+        let mut impulse = vec![0.0; 2 * tail];
+        impulse[0] = 1.0;
+        let response = run(&mut process, &impulse);
+        let max_after_tail = response[tail..].iter().fold(0.0f64, |m, s| m.max(s.abs()));
+        assert_lt!(max_after_tail, processor::TAIL_THRESHOLD);
+    }
+}
+
+#[test]
+fn small_signals_decay_within_the_tail_of_the_nonlinear_filters() {
+    let diode_ladder = |q: f64| {
+        let mut ladder = DiodeLadder::new();
+        ladder.set_q(q);
+        ladder.set_cutoff(0.05);
+        ladder
+    };
+    let mut filters: Vec<(&str, Box<dyn Processor>)> = vec![
+        (
+            "svf",
+            Box::new(svf::Process::new(svf::Params::from_audio_filter_params(
+                filter::Params {
+                    fc: 200.0,
+                    q: 5.0,
+                    gain_db: 0.0,
+                },
+                filter::Type::BandPass,
+                44100,
+            ))),
+        ),
+        (
+            "transistor ladder",
+            Box::new(TransistorLadder::new(500.0, 0.6, 44100)),
+        ),
+        ("diode ladder", Box::new(diode_ladder(0.5))),
+        (
+            "ms20",
+            Box::new(Ms20::new(filter::Type::LowPass, 500.0, 0.6, 44100)),
+        ),
+        (
+            "korg35",
+            Box::new(Korg35::new(filter::Type::LowPass, 500.0, 0.6, 44100)),
+        ),
+        (
+            "sem",
+            Box::new(Sem::new(filter::Type::LowPass, 500.0, 0.9, 44100)),
+        ),
+        (
+            "crossover",
+            Box::new(Crossover::new(
+                crossover::Order::LR8,
+                &[100.0, 1_000.0],
+                44100,
+            )),
+        ),
+    ];
+    let amplitude = 1e-3;
+    for (name, filter) in filters.iter_mut() {
+        let tail = match filter.tail() {
+            Tail::Samples(samples) => samples,
+            Tail::Infinite => panic!("{}: infinite tail", name),
+        };
+        let mut impulse = vec![0.0; 2 * tail];
+        impulse[0] = amplitude;
+        let response = run(filter.as_mut(), &impulse);
+        let max_after_tail = response[tail..].iter().fold(0.0f64, |m, s| m.max(s.abs()));
+        assert_lt!(
+            max_after_tail,
+            amplitude * processor::TAIL_THRESHOLD,
+            "{}",
+            name
+        );
+    }
+
+    // self oscillating
+    assert_eq!(
+        TransistorLadder::new(500.0, 1.0, 44100).tail(),
+        Tail::Infinite
+    );
+    assert_eq!(diode_ladder(1.0).tail(), Tail::Infinite);
+    assert_eq!(
+        Ms20::new(filter::Type::LowPass, 500.0, 1.0, 44100).tail(),
+        Tail::Infinite
+    );
+}
+
+#[test]
+fn tails() {
+    assert_eq!(processor::decay_tail(0.0), Tail::Samples(2));
+    assert_eq!(processor::decay_tail(1.0), Tail::Infinite);
+    assert_eq!(processor::decay_tail(f64::NAN), Tail::Infinite);
+
+    // two poles at 0.5: 20 samples to fall under -120 dB, plus the delay line
+    let double_pole = biquad::Params {
+        a0: 1.0,
+        a1: 0.0,
+        a2: 0.0,
+        b1: -1.0,
+        b2: 0.25,
+    };
+    assert_eq!(biquad::Process::new(double_pole).tail(), Tail::Samples(22));
+
+    let cascade = Cascade::new(vec![double_pole, double_pole]);
+    assert_eq!(cascade.tail(), Tail::Samples(44));
+
+    let marginal = biquad::Params {
+        b2: 1.0,
+        ..biquad::Params::default()
+    };
+    let cascade = Cascade::new(vec![double_pole, marginal]);
+    assert_eq!(cascade.tail(), Tail::Infinite);
+
+    assert_eq!(Cascade::new(vec![]).tail(), Tail::Samples(0));
+}
+
+#[test]
+#[should_panic(expected = "expected 1 input channels")]
+fn channel_count_mismatch() {
+    let mut process = biquad::Process::new(biquad::Params::default());
+    let (left, right) = (vec![0.0; 4], vec![0.0; 4]);
+    let mut output = vec![0.0; 4];
+    Processor::process_block(&mut process, &[&left, &right], &mut [&mut output]);
+}
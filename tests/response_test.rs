@@ -0,0 +1,210 @@
+//! Frequency response tests
+
+#[macro_use]
+extern crate more_asserts;
+
+use dsp_playground::biquad;
+use dsp_playground::cascade::Cascade;
+use dsp_playground::design;
+use dsp_playground::filter;
+use dsp_playground::response::{self, FrequencyResponse};
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+const FS: i32 = 44100;
+
+fn biquad(fc: f64, q: f64, gain_db: f64, filter_type: filter::Type) -> biquad::Params {
+    biquad::Params::from_audio_filter_params(filter::Params { fc, q, gain_db }, filter_type, FS)
+}
+
+fn butterworth(order: usize, fc: f64, filter_type: filter::Type) -> Vec<biquad::Params> {
+    design::butterworth(
+        order,
+        filter::Params {
+            fc,
+            q: 0.0,
+            gain_db: 0.0,
+        },
+        filter_type,
+        FS,
+    )
+}
+
+/// A pure delay of one sample
+const DELAY: biquad::Params = biquad::Params {
+    a0: 0.0,
+    a1: 1.0,
+    a2: 0.0,
+    b1: 0.0,
+    b2: 0.0,
+};
+
+#[test]
+fn low_pass_minus_3_db_at_fc() {
+    let params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    assert_lt!(params.magnitude_db(0.0, FS).abs(), 1e-9);
+    assert_lt!((params.magnitude_db(1_000.0, FS) + 3.0103).abs(), 1e-3);
+    assert_lt!(params.magnitude_db(10_000.0, FS), -38.0);
+    // second order: a quarter turn at fc
+    assert_lt!((params.phase(1_000.0, FS) + PI / 2.0).abs(), 1e-3);
+}
+
+#[test]
+fn peak_gain_at_fc() {
+    let params = biquad(2_000.0, 1.0, 6.0, filter::Type::Peak);
+    assert_lt!((params.magnitude_db(2_000.0, FS) - 6.0).abs(), 1e-9);
+    assert_lt!(params.magnitude_db(20.0, FS).abs(), 0.01);
+    assert_lt!(params.phase(2_000.0, FS).abs(), 1e-9);
+
+    let params = biquad(2_000.0, 1.0, -12.0, filter::Type::Peak);
+    assert_lt!((params.magnitude_db(2_000.0, FS) + 12.0).abs(), 1e-9);
+}
+
+#[test]
+fn notch_rejects_fc() {
+    let params = biquad(5_000.0, 2.0, 0.0, filter::Type::Notch);
+    assert_lt!(params.magnitude(5_000.0, FS), 1e-9);
+    assert_lt!(params.magnitude_db(50.0, FS).abs(), 0.01);
+}
+
+#[test]
+fn response_of_delay() {
+    for f in [0.0, 100.0, 5_000.0, 20_000.0].iter() {
+        let w = 2.0 * PI * f / FS as f64;
+        let h = DELAY.response(*f, FS);
+        assert_lt!((h.re - w.cos()).abs(), 1e-12);
+        assert_lt!((h.im + w.sin()).abs(), 1e-12);
+        assert_lt!((DELAY.group_delay(*f, FS) - 1.0).abs(), 1e-12);
+        assert_lt!((DELAY.phase_delay(*f, FS) - 1.0).abs(), 1e-12);
+    }
+}
+
+#[test]
+fn group_delay_is_phase_derivative() {
+    let all_sections = [
+        vec![biquad(1_000.0, FRAC_1_SQRT_2, 0.0, filter::Type::LowPass)],
+        vec![biquad(300.0, 4.0, 0.0, filter::Type::HighPass)],
+        vec![biquad(3_000.0, 0.5, 0.0, filter::Type::BandPass)],
+        vec![biquad(800.0, 0.7, 9.0, filter::Type::LowShelf)],
+        butterworth(7, 2_000.0, filter::Type::LowPass),
+    ];
+    let df = 0.01;
+    let dw = 2.0 * PI * df / FS as f64;
+
+    for sections in all_sections.iter() {
+        for f in [50.0, 500.0, 1_500.0, 4_000.0, 15_000.0].iter() {
+            let derivative = -(sections.unwrapped_phase(f + df, FS)
+                - sections.unwrapped_phase(f - df, FS))
+                / (2.0 * dw);
+            let group_delay = sections.group_delay(*f, FS);
+            assert_lt!(
+                (derivative - group_delay).abs(),
+                1e-4 * group_delay.abs().max(1.0),
+                "{:?} at {} Hz",
+                sections,
+                f
+            );
+        }
+    }
+}
+
+#[test]
+fn unwrapped_phase_is_continuous() {
+    // 8th order low pass: 8 quarter turns from DC to Nyquist
+    let sections = butterworth(8, 1_000.0, filter::Type::LowPass);
+    let frequencies = response::log_frequencies(1.0, 22_000.0, 2_000);
+    let phases: Vec<f64> = frequencies
+        .iter()
+        .map(|f| sections.unwrapped_phase(*f, FS))
+        .collect();
+
+    assert_lt!(phases[0].abs(), 0.01);
+    for (pair, f) in phases.windows(2).zip(frequencies.iter()) {
+        assert_lt!(pair[1], pair[0], "{} Hz", f);
+        assert_lt!(pair[0] - pair[1], 0.2, "{} Hz", f);
+    }
+    assert_lt!((phases[phases.len() - 1] + 4.0 * PI).abs(), 0.01);
+    // N eighths of a turn at fc
+    assert_lt!((sections.unwrapped_phase(1_000.0, FS) + 2.0 * PI).abs(), 1e-6);
+
+    // the wrapped phase agrees, up to whole turns
+    for (f, phase) in frequencies.iter().zip(phases.iter()) {
+        let turns = (phase - sections.phase(*f, FS)) / (2.0 * PI);
+        assert_lt!((turns - turns.round()).abs(), 1e-9);
+    }
+}
+
+#[test]
+fn phase_delay_of_allpass() {
+    // first order allpass: (c + z^-1) / (1 + c z^-1), a zero outside of the unit circle
+    let c = -0.5;
+    let allpass = biquad::Params {
+        a0: c,
+        a1: 1.0,
+        a2: 0.0,
+        b1: c,
+        b2: 0.0,
+    };
+    let dc_delay = (1.0 - c) / (1.0 + c);
+
+    assert_lt!(allpass.unwrapped_phase(0.0, FS).abs(), 1e-9);
+    assert_lt!((allpass.phase_delay(0.0, FS) - dc_delay).abs(), 1e-9);
+    assert_lt!((allpass.phase_delay(1.0, FS) - dc_delay).abs(), 1e-6);
+    // half a turn at Nyquist: a delay of one sample
+    assert_lt!(
+        (allpass.phase_delay(FS as f64 / 2.0 - 1e-6, FS) - 1.0).abs(),
+        1e-6
+    );
+    for f in [100.0, 1_000.0, 10_000.0].iter() {
+        assert_lt!((allpass.magnitude_db(*f, FS)).abs(), 1e-12);
+    }
+}
+
+#[test]
+fn cascade_response() {
+    let sections = butterworth(5, 700.0, filter::Type::HighPass);
+    let mut cascade = Cascade::new(sections.clone());
+    cascade.gain = -2.0;
+
+    for f in [20.0, 700.0, 5_000.0].iter() {
+        let expected = sections.response(*f, FS) * -2.0;
+        let actual = cascade.response(*f, FS);
+        assert_lt!((expected - actual).norm(), 1e-12);
+        assert_eq!(cascade.group_delay(*f, FS), sections.group_delay(*f, FS));
+    }
+    assert_lt!(
+        (cascade.magnitude_db(700.0, FS) - (20.0 * 2f64.log10() - 3.0103)).abs(),
+        1e-3
+    );
+}
+
+#[test]
+fn log_frequencies_grid() {
+    let frequencies = response::log_frequencies(20.0, 20_000.0, 31);
+    assert_eq!(frequencies.len(), 31);
+    assert_lt!((frequencies[0] - 20.0).abs(), 1e-12);
+    assert_lt!((frequencies[30] - 20_000.0).abs(), 1e-9);
+    // 10 points per decade
+    assert_lt!((frequencies[10] - 200.0).abs(), 1e-9);
+    for pair in frequencies.windows(2) {
+        assert_lt!((pair[1] / pair[0] - 10f64.powf(0.1)).abs(), 1e-12);
+    }
+
+    assert!(response::log_frequencies(20.0, 20_000.0, 0).is_empty());
+    assert_eq!(response::log_frequencies(20.0, 20_000.0, 1), vec![20.0]);
+}
+
+#[test]
+fn evaluate_on_grid() {
+    let params = biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6;
+    let frequencies = response::log_frequencies(100.0, 10_000.0, 3);
+    let points = response::evaluate(&params, &frequencies, FS);
+
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[1].f, frequencies[1]);
+    assert_lt!((points[1].magnitude_db + 3.0103).abs(), 1e-3);
+    assert_eq!(points[2].phase, params.unwrapped_phase(frequencies[2], FS));
+    assert_eq!(
+        points[0].group_delay,
+        params.group_delay(frequencies[0], FS)
+    );
+}
@@ -0,0 +1,195 @@
+//! Sample format tests
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::sample::{FloatOfMax1, I24Packed, IntegerSample, Quantizer, Scaling, I24};
+use helper::PATH_WHITE_NOISE;
+
+/// Every value (or a sweep over i32) back and forth
+fn assert_round_trip<T: IntegerSample>(values: impl Iterator<Item = i32> + Clone) {
+    for scaling in [Scaling::Symmetric, Scaling::Asymmetric].iter() {
+        for value in values.clone() {
+            let x = scaling.to_f64(T::from_signed(value));
+            let back: T = scaling.from_f64(x);
+            assert_eq!(back.to_signed(), value, "{:?}", scaling);
+        }
+    }
+}
+
+#[test]
+fn round_trips() {
+    assert_round_trip::<u8>(-128..128);
+    assert_round_trip::<i8>(-128..128);
+    assert_round_trip::<i16>(-32768..32768);
+    assert_round_trip::<u16>(-32768..32768);
+    assert_round_trip::<I24>((I24::MIN..=I24::MAX).step_by(97));
+    assert_round_trip::<I24Packed>((I24::MIN..=I24::MAX).step_by(97));
+    assert_round_trip::<i32>((i32::MIN..=i32::MAX).step_by(65_537));
+}
+
+#[test]
+fn scalings() {
+    assert_eq!(Scaling::default(), Scaling::Symmetric);
+
+    assert_eq!(Scaling::Symmetric.to_f64(127i8), 1.0);
+    assert_eq!(Scaling::Symmetric.to_f64(-127i8), -1.0);
+    assert_lt!(Scaling::Symmetric.to_f64(-128i8), -1.0);
+    assert_eq!(Scaling::Asymmetric.to_f64(-128i8), -1.0);
+    assert_lt!(Scaling::Asymmetric.to_f64(127i8), 1.0);
+
+    // unsigned formats are centered
+    assert_eq!(Scaling::Symmetric.to_f64(128u8), 0.0);
+    assert_eq!(Scaling::Symmetric.to_f64(255u8), 1.0);
+    assert_eq!(Scaling::Asymmetric.to_f64(0u8), -1.0);
+    assert_eq!(Scaling::Symmetric.to_f64(32768u16), 0.0);
+    assert_eq!(Scaling::Asymmetric.to_f64(0u16), -1.0);
+
+    assert_eq!(Scaling::Symmetric.to_f64(I24::new(I24::MAX)), 1.0);
+    assert_eq!(Scaling::Asymmetric.to_f64(i32::MIN), -1.0);
+
+    // the FloatOfMax1 convention
+    assert_eq!(i16::MAX.to_f64(), 1.0);
+    assert_eq!(0i16.from_f64(-1.0), -i16::MAX);
+}
+
+#[test]
+fn rounding_and_saturation() {
+    assert_eq!(0i16.from_f64(0.4 / i16::MAX as f64), 0);
+    assert_eq!(0i16.from_f64(0.6 / i16::MAX as f64), 1);
+    assert_eq!(0i16.from_f64(-0.6 / i16::MAX as f64), -1);
+
+    // no wrap around above 1.0
+    assert_eq!(0i16.from_f64(1.0001), i16::MAX);
+    assert_eq!(0i16.from_f64(-1.5), i16::MIN);
+    assert_eq!(0u8.from_f64(2.0), 255);
+    assert_eq!(0u8.from_f64(-2.0), 0);
+    assert_eq!(0i32.from_f64(1.0001), i32::MAX);
+    assert_eq!(I24::default().from_f64(1.5), I24::new(I24::MAX));
+    assert_eq!(I24Packed::default().from_f64(-1.5).to_signed(), I24::MIN);
+    assert_eq!(0i8.from_f64(f64::NAN), 0);
+    assert_eq!(0u16.from_f64(f64::NAN), 32768);
+}
+
+#[test]
+fn i24() {
+    assert_eq!(I24::new(1 << 24).value(), I24::MAX);
+    assert_eq!(I24::new(-(1 << 24)).value(), I24::MIN);
+
+    // little endian, sign extended
+    assert_eq!(I24Packed([0x01, 0x02, 0x03]).to_signed(), 0x030201);
+    assert_eq!(I24Packed([0xff, 0xff, 0xff]).to_signed(), -1);
+    assert_eq!(I24Packed([0x00, 0x00, 0x80]).to_signed(), I24::MIN);
+    assert_eq!(I24Packed::from(I24::new(-2)), I24Packed([0xfe, 0xff, 0xff]));
+    assert_eq!(I24::from(I24Packed([0xfe, 0xff, 0xff])), I24::new(-2));
+}
+
+#[test]
+fn tpdf_dither() {
+    let count = 100_000;
+    let x = 0.3 / i16::MAX as f64;
+
+    let mut quantizer = Quantizer::new(Scaling::Symmetric);
+    assert_eq!(quantizer.quantize::<i16>(x), 0);
+
+    // the error is within ±1.5 LSB and without bias
+    let mut quantizer = Quantizer::with_tpdf_dither(Scaling::Symmetric, 42);
+    let input = vec![x; count];
+    let mut output = vec![0i16; count];
+    quantizer.quantize_block(&input, &mut output);
+    assert!(output.iter().all(|y| (-1..=1).contains(y)));
+    let mean = output.iter().map(|y| *y as f64).sum::<f64>() / count as f64;
+    assert_lt!((mean - 0.3).abs(), 0.01);
+
+    // reproducible
+    let mut again = vec![0i16; count];
+    Quantizer::with_tpdf_dither(Scaling::Symmetric, 42).quantize_block(&input, &mut again);
+    assert_eq!(again, output);
+}
+
+#[test]
+fn every_format_through_a_biquad() {
+    let white_noise: Vec<f64> = helper::audio_file_samples(PATH_WHITE_NOISE)
+        .iter()
+        .map(|s| s.to_f64() * 0.5)
+        .collect();
+    let mut expected = vec![0.0; white_noise.len()];
+    biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6)
+        .process_block(&white_noise, &mut expected);
+
+    fn filtered<T: IntegerSample + Default + Clone>(input: &[f64]) -> Vec<f64> {
+        let samples: Vec<T> = input
+            .iter()
+            .map(|x| Scaling::Symmetric.from_f64(*x))
+            .collect();
+        let mut output = vec![T::default(); samples.len()];
+        biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6)
+            .process_block(&samples, &mut output);
+        output.iter().map(|y| y.to_f64()).collect()
+    }
+    let max_error = |output: Vec<f64>| {
+        output
+            .iter()
+            .zip(expected.iter())
+            .fold(0.0f64, |max, (y, e)| max.max((y - e).abs()))
+    };
+
+    // within a few LSB of each format
+    assert_lt!(max_error(filtered::<u8>(&white_noise)), 0.05);
+    assert_lt!(max_error(filtered::<i8>(&white_noise)), 0.05);
+    assert_lt!(max_error(filtered::<u16>(&white_noise)), 2e-4);
+    assert_lt!(max_error(filtered::<I24>(&white_noise)), 1e-6);
+    assert_lt!(max_error(filtered::<I24Packed>(&white_noise)), 1e-6);
+    assert_lt!(max_error(filtered::<i32>(&white_noise)), 1e-8);
+}
+
+#[test]
+fn scaling_and_dither_on_the_processing_path() {
+    let input: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE)
+        .iter()
+        .map(|s| s / 2)
+        .collect();
+
+    // without dither, the symmetric quantizer is the FloatOfMax1 path
+    let mut rounded = vec![0i16; input.len()];
+    biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6)
+        .process_block(&input, &mut rounded);
+    let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    let mut output = vec![0i16; input.len()];
+    Quantizer::new(Scaling::Symmetric).process_block(&input, &mut output, |x| process.tick(x));
+    assert_eq!(output, rounded);
+
+    // the asymmetric scaling both ways, and the dither on the way back
+    let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    let mut expected = vec![0i16; input.len()];
+    let mut quantizer = Quantizer::with_tpdf_dither(Scaling::Asymmetric, 42);
+    for (x, y) in input.iter().zip(expected.iter_mut()) {
+        *y = quantizer.quantize(process.tick(Scaling::Asymmetric.to_f64(*x)));
+    }
+    let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    let mut quantizer = Quantizer::with_tpdf_dither(Scaling::Asymmetric, 42);
+    quantizer.process_block(&input, &mut output, |x| process.tick(x));
+    assert_eq!(output, expected);
+    assert_ne!(output, rounded);
+}
+
+#[test]
+fn biquad_quantized_block() {
+    let input: Vec<i16> = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let mut expected = vec![0i16; input.len()];
+    let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    Quantizer::with_tpdf_dither(Scaling::Asymmetric, 7)
+        .process_block(&input, &mut expected, |x| process.tick(x));
+
+    let mut output = vec![0i16; input.len()];
+    biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6).process_block_quantized(
+        &input,
+        &mut output,
+        &mut Quantizer::with_tpdf_dither(Scaling::Asymmetric, 7),
+    );
+    assert_eq!(output, expected);
+}
@@ -0,0 +1,177 @@
+//! SIMD biquads tests: bit for bit against the scalar transposed direct form II
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::filter;
+use dsp_playground::processor::Processor;
+use dsp_playground::simd::{Backend, SimdProcess};
+use helper::{white_noise, FS};
+
+fn backends() -> Vec<Backend> {
+    [Backend::Scalar, Backend::Sse2, Backend::Avx]
+        .iter()
+        .cloned()
+        .filter(|backend| backend.is_supported())
+        .collect()
+}
+
+/// Different types and frequencies per lane
+fn lane_params(channels: usize) -> Vec<biquad::Params> {
+    let types = [
+        filter::Type::LowPass,
+        filter::Type::HighPass,
+        filter::Type::Peak,
+        filter::Type::LowShelf,
+        filter::Type::BandPass,
+    ];
+    (0..channels)
+        .map(|lane| {
+            biquad::Params::from_audio_filter_params(
+                filter::Params {
+                    fc: 100.0 * (lane + 1) as f64,
+                    q: 0.5 + lane as f64,
+                    gain_db: 6.0,
+                },
+                types[lane % types.len()],
+                FS,
+            )
+        })
+        .collect()
+}
+
+fn scalar(params: biquad::Params, input: &[f64]) -> Vec<f64> {
+    let mut process =
+        biquad::Process::with_topology(params, biquad::Topology::TransposedDirectFormII);
+    let mut output = vec![0.0; input.len()];
+    process.process_block(input, &mut output);
+    output
+}
+
+/// Lane i gets the noise shifted by i samples
+fn interleaved_noise(channels: usize, frames: usize) -> Vec<f64> {
+    let noise = white_noise();
+    (0..frames * channels)
+        .map(|i| noise[(i / channels + i % channels) % noise.len()])
+        .collect()
+}
+
+fn lane(interleaved: &[f64], channels: usize, lane: usize) -> Vec<f64> {
+    interleaved
+        .iter()
+        .skip(lane)
+        .step_by(channels)
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn detected_backend_is_supported() {
+    assert!(Backend::detect().is_supported());
+    assert!(Backend::Scalar.is_supported());
+}
+
+#[test]
+fn interleaved_bit_for_bit() {
+    // partial and several groups of lanes
+    for channels in 1..=9 {
+        let params = lane_params(channels);
+        let input = interleaved_noise(channels, 1_000);
+        for backend in backends() {
+            let mut process = SimdProcess::with_backend(&params, backend);
+            assert_eq!(process.backend(), backend);
+            let mut output = vec![0.0; input.len()];
+            process.process_interleaved(&input, &mut output);
+            for (i, params) in params.iter().enumerate() {
+                assert_eq!(
+                    lane(&output, channels, i),
+                    scalar(*params, &lane(&input, channels, i)),
+                    "{:?}, lane {} of {}",
+                    backend,
+                    i,
+                    channels
+                );
+            }
+
+            let mut samples = input.clone();
+            process.reset();
+            process.process_interleaved_in_place(&mut samples);
+            assert_eq!(samples, output, "{:?}, in place", backend);
+        }
+    }
+}
+
+#[test]
+fn filter_bank_bit_for_bit() {
+    let input = white_noise();
+    let params = lane_params(6);
+    let expected: Vec<Vec<f64>> = params.iter().map(|p| scalar(*p, &input)).collect();
+    for backend in backends() {
+        let mut process = SimdProcess::with_backend(&params, backend);
+        let inputs = vec![&input[..]; params.len()];
+        let mut outputs = vec![vec![0.0; input.len()]; params.len()];
+        {
+            let mut slices: Vec<&mut [f64]> = outputs.iter_mut().map(|o| &mut o[..]).collect();
+            process.process_planar(&inputs, &mut slices);
+        }
+        assert_eq!(outputs, expected, "{:?}", backend);
+    }
+}
+
+#[test]
+fn state_carries_over_blocks() {
+    let channels = 5;
+    let params = lane_params(channels);
+    let input = interleaved_noise(channels, 1_000);
+    for backend in backends() {
+        let mut process = SimdProcess::with_backend(&params, backend);
+        let mut expected = vec![0.0; input.len()];
+        process.process_interleaved(&input, &mut expected);
+
+        process.reset();
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input
+            .chunks(channels * 7)
+            .zip(output.chunks_mut(channels * 7))
+        {
+            process.process_interleaved(x, y);
+        }
+        assert_eq!(output, expected, "{:?}", backend);
+    }
+}
+
+#[test]
+fn params_per_lane() {
+    let mut process = SimdProcess::new(&lane_params(3));
+    assert_eq!(process.channels(), 3);
+    process.set_params(2, biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    assert_eq!(process.params(2), biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    assert_eq!(process.params(0), lane_params(1)[0]);
+}
+
+#[test]
+#[should_panic(expected = "simd: no lane 3")]
+fn lane_out_of_range() {
+    SimdProcess::new(&lane_params(3)).set_params(3, biquad::Params::default());
+}
+
+#[test]
+fn as_a_processor() {
+    let input = white_noise();
+    let params = lane_params(2);
+    let mut process = SimdProcess::new(&params);
+    let processor: &mut dyn Processor = &mut process;
+    assert_eq!(processor.channels(), 2);
+    assert_eq!(
+        processor.tail(),
+        biquad::Process::new(params[0])
+            .tail()
+            .max(biquad::Process::new(params[1]).tail())
+    );
+    let mut left = vec![0.0; input.len()];
+    let mut right = vec![0.0; input.len()];
+    processor.process_block(&[&input, &input], &mut [&mut left, &mut right]);
+    assert_eq!(left, scalar(params[0], &input));
+    assert_eq!(right, scalar(params[1], &input));
+}
@@ -0,0 +1,186 @@
+//! Parameter smoothing tests
+
+#[macro_use]
+extern crate more_asserts;
+
+use dsp_playground::biquad;
+use dsp_playground::filter;
+use dsp_playground::smoothing::SmoothedProcess;
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+const FS: i32 = 44100;
+
+fn params(fc: f64) -> filter::Params {
+    filter::Params {
+        fc,
+        q: FRAC_1_SQRT_2,
+        gain_db: 0.0,
+    }
+}
+
+/// 1 second of a 50 Hz sine: small steps between samples
+fn sine() -> Vec<f64> {
+    (0..FS as usize)
+        .map(|i| (2.0 * PI * 50.0 * i as f64 / FS as f64).sin())
+        .collect()
+}
+
+fn max_step(ys: &[f64]) -> f64 {
+    ys.windows(2)
+        .fold(0.0f64, |max, pair| max.max((pair[1] - pair[0]).abs()))
+}
+
+#[test]
+fn cutoff_jump_across_decades_is_bounded() {
+    let input = sine();
+    let jump = input.len() / 2;
+    let steady_step = max_step(&input);
+
+    for filter_type in [filter::Type::LowPass, filter::Type::HighPass].iter() {
+        for (from, to) in [
+            (100.0, 10_000.0),
+            (10_000.0, 100.0),
+            (30.0, 15_000.0),
+            (15_000.0, 30.0),
+        ]
+        .iter()
+        {
+            let mut swapped = biquad::Process::new(biquad::Params::from_audio_filter_params(
+                params(*from),
+                *filter_type,
+                FS,
+            ));
+            let mut smoothed = SmoothedProcess::new(params(*from), *filter_type, FS);
+            let mut swapped_output = vec![];
+            let mut smoothed_output = vec![];
+            for (i, x) in input.iter().enumerate() {
+                if i == jump {
+                    swapped.set_params(biquad::Params::from_audio_filter_params(
+                        params(*to),
+                        *filter_type,
+                        FS,
+                    ));
+                    smoothed.set_target(params(*to));
+                }
+                swapped_output.push(swapped.tick(*x));
+                smoothed_output.push(smoothed.tick(*x));
+            }
+
+            // no larger than the steps of the sine itself
+            let smoothed_step = max_step(&smoothed_output[jump - 1..]);
+            assert_lt!(
+                smoothed_step,
+                1.5 * steady_step,
+                "{:?} {} -> {}",
+                filter_type,
+                from,
+                to
+            );
+            if from < to {
+                // opening the filter: the swapped coefficients click
+                assert_gt!(max_step(&swapped_output[jump - 1..]), 20.0 * steady_step);
+            }
+        }
+    }
+}
+
+#[test]
+fn fc_ramps_in_log_domain() {
+    let mut smoothed = SmoothedProcess::new(params(100.0), filter::Type::LowPass, FS);
+    smoothed.set_smoothing(0.01, 1);
+    smoothed.set_target(filter::Params {
+        fc: 10_000.0,
+        q: FRAC_1_SQRT_2 + 2.0,
+        gain_db: 0.0,
+    });
+    assert!(smoothed.is_smoothing());
+
+    // halfway through the 441 samples: the geometric mean
+    for _ in 0..220 {
+        smoothed.tick(0.0);
+    }
+    let current = smoothed.current();
+    assert_lt!((current.fc - 1_000.0).abs(), 20.0);
+    assert_lt!((current.q - FRAC_1_SQRT_2 - 1.0).abs(), 0.01);
+
+    for _ in 220..441 {
+        smoothed.tick(0.0);
+    }
+    assert!(!smoothed.is_smoothing());
+    assert_eq!(smoothed.current(), smoothed.target());
+    assert_eq!(
+        smoothed.params(),
+        biquad::Params::from_audio_filter_params(smoothed.target(), filter::Type::LowPass, FS)
+    );
+}
+
+#[test]
+fn gain_ramps_in_db() {
+    let mut smoothed = SmoothedProcess::new(
+        filter::Params {
+            fc: 1_000.0,
+            q: 1.0,
+            gain_db: -12.0,
+        },
+        filter::Type::Peak,
+        FS,
+    );
+    smoothed.set_smoothing(0.001, 4);
+    smoothed.set_target(filter::Params {
+        fc: 1_000.0,
+        q: 1.0,
+        gain_db: 12.0,
+    });
+
+    // 44 samples: 11 control steps, the first one on the first sample
+    let mut gains = vec![];
+    for _ in 0..44 {
+        smoothed.tick(0.0);
+        gains.push(smoothed.current().gain_db);
+    }
+    assert_lt!((gains[0] + 12.0 - 24.0 / 11.0).abs(), 1e-9);
+    for pair in gains.windows(2) {
+        assert_ge!(pair[1], pair[0]);
+        assert_lt!(pair[1] - pair[0], 24.0 / 11.0 + 1e-9);
+    }
+    assert_eq!(gains[40], 12.0);
+}
+
+#[test]
+fn set_params_immediately_skips_the_ramp() {
+    let mut smoothed = SmoothedProcess::new(params(100.0), filter::Type::LowPass, FS);
+    smoothed.set_target(params(1_000.0));
+    smoothed.set_params_immediately(params(5_000.0));
+    assert!(!smoothed.is_smoothing());
+    assert_eq!(
+        smoothed.params(),
+        biquad::Params::from_audio_filter_params(params(5_000.0), filter::Type::LowPass, FS)
+    );
+
+    // reset finishes the ramp too
+    smoothed.set_target(params(200.0));
+    smoothed.reset();
+    assert_eq!(smoothed.current(), params(200.0));
+}
+
+#[test]
+fn process_block_matches_tick() {
+    let input = sine();
+    let mut block = SmoothedProcess::new(params(100.0), filter::Type::HighPass, FS);
+    let mut per_sample = SmoothedProcess::new(params(100.0), filter::Type::HighPass, FS);
+    block.set_target(params(3_000.0));
+    per_sample.set_target(params(3_000.0));
+
+    let mut output = vec![0.0; input.len()];
+    block.process_block(&input, &mut output);
+    for (x, y) in input.iter().zip(output.iter()) {
+        assert_eq!(per_sample.tick(*x), *y);
+    }
+}
+
+#[test]
+#[should_panic]
+fn control_period_of_0() {
+    let mut smoothed = SmoothedProcess::new(params(100.0), filter::Type::LowPass, FS);
+    smoothed.set_smoothing(0.01, 0);
+}
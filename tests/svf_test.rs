@@ -0,0 +1,197 @@
+//! State variable filter tests
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::filter;
+use dsp_playground::svf;
+use helper::{filter_params, white_noise, FS};
+use std::f64::consts::FRAC_1_SQRT_2;
+
+const ALL_TYPES: [filter::Type; 7] = [
+    filter::Type::LowPass,
+    filter::Type::HighPass,
+    filter::Type::BandPass,
+    filter::Type::Notch,
+    filter::Type::Peak,
+    filter::Type::LowShelf,
+    filter::Type::HighShelf,
+];
+
+#[test]
+fn same_response_as_biquad() {
+    let input = white_noise();
+    let all_params = [
+        filter_params(1_000.0, FRAC_1_SQRT_2, 6.0),
+        filter_params(50.0, 3.0, -9.0),
+        filter_params(12_000.0, 0.5, 12.0),
+        filter_params(5_000.0, 8.0, -24.0),
+    ];
+
+    for filter_type in ALL_TYPES.iter() {
+        for params in all_params.iter() {
+            let mut svf = svf::Process::new(svf::Params::from_audio_filter_params(
+                *params,
+                *filter_type,
+                FS,
+            ));
+            let mut biquad = biquad::Process::new(biquad::Params::from_audio_filter_params(
+                *params,
+                *filter_type,
+                FS,
+            ));
+            for x in input.iter() {
+                assert_lt!(
+                    (svf.tick(*x) - biquad.tick(*x)).abs(),
+                    1e-9,
+                    "{:?} {:?}",
+                    filter_type,
+                    params
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn simultaneous_outputs() {
+    let input = white_noise();
+    let params = filter_params(2_000.0, 2.0, 0.0);
+    let mut all = svf::Process::new(svf::Params::from_audio_filter_params(
+        params,
+        filter::Type::LowPass,
+        FS,
+    ));
+    let mut single: Vec<svf::Process> = [
+        filter::Type::LowPass,
+        filter::Type::HighPass,
+        filter::Type::BandPass,
+        filter::Type::Notch,
+    ]
+    .iter()
+    .map(|t| svf::Process::new(svf::Params::from_audio_filter_params(params, *t, FS)))
+    .collect();
+
+    for x in input.iter() {
+        let outputs = all.tick_outputs(*x);
+        let expected: Vec<f64> = single.iter_mut().map(|svf| svf.tick(*x)).collect();
+        assert_lt!((outputs.low_pass - expected[0]).abs(), 1e-12);
+        assert_lt!((outputs.high_pass - expected[1]).abs(), 1e-12);
+        assert_lt!((outputs.band_pass - expected[2]).abs(), 1e-12);
+        assert_lt!((outputs.notch - expected[3]).abs(), 1e-12);
+        assert_lt!((outputs.peak - (expected[0] - expected[1])).abs(), 1e-12);
+        assert_lt!(
+            (outputs.all_pass - (expected[3] - expected[2])).abs(),
+            1e-12
+        );
+    }
+}
+
+#[test]
+fn all_pass_keeps_the_energy() {
+    let input = white_noise();
+    let mut svf = svf::Process::new(svf::Params::from_audio_filter_params(
+        filter_params(500.0, FRAC_1_SQRT_2, 0.0),
+        filter::Type::LowPass,
+        FS,
+    ));
+    let output: Vec<f64> = input
+        .iter()
+        .map(|x| svf.tick_outputs(*x).all_pass)
+        .collect();
+    let energy = |xs: &[f64]| xs.iter().map(|x| x * x).sum::<f64>();
+    assert_lt!((energy(&output) / energy(&input) - 1.0).abs(), 0.01);
+}
+
+#[test]
+fn stable_under_audio_rate_modulation() {
+    let input = white_noise();
+    // deterministic pseudo random parameters, changing at every sample
+    let mut seed: u32 = 12345;
+    let mut random = move || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        seed as f64 / u32::MAX as f64
+    };
+
+    for filter_type in [
+        filter::Type::LowPass,
+        filter::Type::BandPass,
+        filter::Type::HighPass,
+    ]
+    .iter()
+    {
+        let mut svf = svf::Process::new(svf::Params::default());
+        let mut peak = 0.0f64;
+        for x in input.iter() {
+            // 20 Hz to 20 kHz, q from 0.5 to 20
+            let fc = 20.0 * 1000f64.powf(random());
+            let q = 0.5 * 40f64.powf(random());
+            svf.set_params(svf::Params::from_audio_filter_params(
+                filter_params(fc, q, 0.0),
+                *filter_type,
+                FS,
+            ));
+            let y = svf.tick(*x);
+            assert!(y.is_finite());
+            peak = peak.max(y.abs());
+        }
+        assert_lt!(peak, 30.0, "{:?}", filter_type);
+
+        // no energy left once the input stops
+        svf.set_params(svf::Params::from_audio_filter_params(
+            filter_params(1_000.0, FRAC_1_SQRT_2, 0.0),
+            *filter_type,
+            FS,
+        ));
+        for _ in 0..FS / 10 {
+            svf.tick(0.0);
+        }
+        assert_lt!(svf.tick(0.0).abs(), 1e-9);
+    }
+}
+
+#[test]
+fn process_block_matches_tick() {
+    let input = white_noise();
+    let params = svf::Params::from_audio_filter_params(
+        filter_params(300.0, 1.0, 3.0),
+        filter::Type::LowShelf,
+        FS,
+    );
+    let mut block = svf::Process::new(params);
+    let mut per_sample = svf::Process::new(params);
+    let mut output = vec![0.0; input.len()];
+    block.process_block(&input, &mut output);
+    for (x, y) in input.iter().zip(output.iter()) {
+        assert_eq!(per_sample.tick(*x), *y);
+    }
+
+    block.reset();
+    let mut in_place = input.clone();
+    block.process_in_place(&mut in_place);
+    assert_eq!(in_place, output);
+}
+
+#[test]
+fn invalid_params_are_passthrough_or_clamped() {
+    let passthrough = svf::Params::from_audio_filter_params(
+        filter_params(1_000.0, FRAC_1_SQRT_2, 0.0),
+        filter::Type::LowPass,
+        0,
+    );
+    assert_eq!(passthrough, svf::Params::default());
+    let mut svf = svf::Process::new(passthrough);
+    assert_eq!(svf.tick(0.5), 0.5);
+
+    let clamped = svf::Params::from_audio_filter_params(
+        filter_params(30_000.0, -1.0, 0.0),
+        filter::Type::LowPass,
+        FS,
+    );
+    assert!(clamped.g.is_finite() && clamped.k.is_finite());
+    assert_gt!(clamped.k, 0.0);
+}
@@ -0,0 +1,223 @@
+//! WAV files tests (with the `wav` feature)
+
+#[macro_use]
+extern crate more_asserts;
+
+#[allow(dead_code)]
+mod helper;
+
+use dsp_playground::biquad;
+use dsp_playground::dither::{Dither, Ditherer, NoiseShaping};
+use dsp_playground::error::WavError;
+use dsp_playground::sample::{FloatOfMax1, Quantizer, Scaling};
+use dsp_playground::wav;
+use helper::PATH_WHITE_NOISE;
+
+fn temp_path(name: &str) -> String {
+    format!("tests/assets/temp_wav_{}.wav", name)
+}
+
+/// A ramp from -1 to 1 on the left, a sine on the right
+fn stereo(frames: usize) -> Vec<Vec<f64>> {
+    vec![
+        (0..frames)
+            .map(|n| -1.0 + 2.0 * n as f64 / (frames - 1) as f64)
+            .collect(),
+        (0..frames).map(|n| (n as f64 * 0.01).sin() * 0.9).collect(),
+    ]
+}
+
+fn slices(buffers: &[Vec<f64>]) -> Vec<&[f64]> {
+    buffers.iter().map(|b| &b[..]).collect()
+}
+
+#[test]
+fn read_the_white_noise() {
+    let (spec, buffers) = wav::read(PATH_WHITE_NOISE).unwrap();
+    assert_eq!(
+        spec,
+        wav::Spec {
+            channels: 1,
+            sample_rate: 44100,
+            format: wav::Format::Int16
+        }
+    );
+    let expected: Vec<f64> = helper::audio_file_samples(PATH_WHITE_NOISE)
+        .iter()
+        .map(|s| s.to_f64())
+        .collect();
+    assert_eq!(buffers, vec![expected]);
+}
+
+#[test]
+fn round_trip_of_every_format() {
+    let input = stereo(1_000);
+    for (format, lsb) in [
+        (wav::Format::Int8, 1.0 / 127.0),
+        (wav::Format::Int16, 1.0 / 32767.0),
+        (wav::Format::Int24, 1.0 / 8_388_607.0),
+        (wav::Format::Int32, 1.0 / 2_147_483_647.0),
+        (wav::Format::Float32, f32::EPSILON as f64),
+    ]
+    .iter()
+    {
+        let spec = wav::Spec {
+            channels: 2,
+            sample_rate: 96000,
+            format: *format,
+        };
+        let path = temp_path(&format!("{:?}", format));
+        wav::write(&path, spec, &slices(&input)).unwrap();
+
+        let mut reader = wav::Reader::open(&path).unwrap();
+        assert_eq!(reader.spec(), spec);
+        assert_eq!(reader.frames(), 1_000);
+        let output = reader.read_all().unwrap();
+        for (x, y) in input.iter().flatten().zip(output.iter().flatten()) {
+            assert_le!((x - y).abs(), lsb / 2.0, "{:?}", format);
+        }
+    }
+}
+
+#[test]
+fn blocks() {
+    let input = stereo(1_000);
+    let spec = wav::Spec {
+        channels: 2,
+        sample_rate: 48000,
+        format: wav::Format::Int24,
+    };
+    let path = temp_path("blocks");
+    let mut writer = wav::Writer::create(&path, spec).unwrap();
+    for start in (0..1_000).step_by(300) {
+        let end = (start + 300).min(1_000);
+        writer
+            .write_block(&[&input[0][start..end], &input[1][start..end]])
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let (_, expected) = wav::read(&path).unwrap();
+    let mut reader = wav::Reader::open(&path).unwrap();
+    let mut left = vec![0.0; 300];
+    let mut right = vec![0.0; 300];
+    let mut counts = vec![];
+    let mut output = vec![vec![], vec![]];
+    loop {
+        let count = reader.read_block(&mut [&mut left, &mut right]).unwrap();
+        if count == 0 {
+            break;
+        }
+        counts.push(count);
+        output[0].extend_from_slice(&left[..count]);
+        output[1].extend_from_slice(&right[..count]);
+    }
+    assert_eq!(counts, vec![300, 300, 300, 100]);
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn dithered_writer() {
+    let silence = vec![0.2 / i16::MAX as f64; 10_000];
+    let spec = wav::Spec {
+        channels: 1,
+        sample_rate: 44100,
+        format: wav::Format::Int16,
+    };
+    let path = temp_path("dithered");
+    let mut writer = wav::Writer::create(&path, spec).unwrap();
+    writer.set_quantizer(0, Quantizer::with_tpdf_dither(Scaling::Symmetric, 7));
+    writer.write_block(&[&silence]).unwrap();
+    writer.finalize().unwrap();
+
+    let samples = helper::audio_file_samples(&path);
+    assert!(samples.iter().any(|s| *s != 0));
+    assert_lt!((helper::mean(&samples) - 0.2).abs(), 0.05);
+}
+
+#[test]
+fn noise_shaping_per_channel() {
+    let shaped = || {
+        Quantizer::with_ditherer(
+            Scaling::Symmetric,
+            Ditherer::new(Dither::Tpdf, NoiseShaping::FWeighted, 7),
+        )
+    };
+    let buffers = stereo(10_000);
+    let stereo_spec = wav::Spec {
+        channels: 2,
+        sample_rate: 44100,
+        format: wav::Format::Int16,
+    };
+    let path = temp_path("shaped_stereo");
+    let mut writer = wav::Writer::create(&path, stereo_spec).unwrap();
+    writer.set_quantizer(0, shaped());
+    writer.set_quantizer(1, shaped());
+    writer.write_block(&slices(&buffers)).unwrap();
+    writer.finalize().unwrap();
+    let (_, written) = wav::read(&path).unwrap();
+
+    // each channel as if it was written alone: no error fed back from the other one
+    let mono_spec = wav::Spec {
+        channels: 1,
+        ..stereo_spec
+    };
+    for (channel, buffer) in buffers.iter().enumerate() {
+        let path = temp_path(&format!("shaped_mono_{}", channel));
+        let mut writer = wav::Writer::create(&path, mono_spec).unwrap();
+        writer.set_quantizer(0, shaped());
+        writer.write_block(&[buffer]).unwrap();
+        writer.finalize().unwrap();
+        let (_, alone) = wav::read(&path).unwrap();
+        assert_eq!(written[channel], alone[0]);
+    }
+}
+
+#[test]
+fn process_file_by_blocks() {
+    let path = temp_path("process");
+    let mut process = biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6);
+    wav::process_file(PATH_WHITE_NOISE, &path, &mut process, 100).unwrap();
+
+    let white_noise = helper::audio_file_samples(PATH_WHITE_NOISE);
+    let mut expected = vec![0; white_noise.len()];
+    biquad::Process::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6)
+        .process_block(&white_noise, &mut expected);
+    assert_eq!(helper::audio_file_samples(&path), expected);
+}
+
+#[test]
+fn errors() {
+    let mut stereo_process =
+        biquad::MultichannelProcess::new(biquad::LOWPASS_FC_1000_Q_0_7071_GAIN_6, 2);
+    assert_eq!(stereo_process.channels(), 2);
+    match wav::process_file(
+        PATH_WHITE_NOISE,
+        temp_path("mismatch"),
+        &mut stereo_process,
+        100,
+    ) {
+        Err(WavError::ChannelMismatch {
+            channels: 2,
+            expected: 1,
+        }) => (),
+        other => panic!("{:?}", other),
+    }
+
+    let spec = wav::Spec {
+        channels: 0,
+        sample_rate: 44100,
+        format: wav::Format::Int16,
+    };
+    match wav::Writer::create(temp_path("no_channel"), spec) {
+        Err(WavError::InvalidChannelCount { channels: 0 }) => (),
+        other => panic!("{:?}", other.err()),
+    }
+
+    match wav::read("tests/assets/missing.wav") {
+        Err(error @ WavError::Hound(_)) => {
+            assert!(error.to_string().starts_with("wav: "));
+        }
+        other => panic!("{:?}", other.err()),
+    }
+}
@@ -6,16 +6,20 @@ use vst::plugin::{Info, Plugin};
 
 use dsp_playground::biquad;
 use dsp_playground::filter;
+use dsp_playground::processor::Processor;
+
+const CHANNELS: usize = 2;
 
 #[derive(Default)]
 struct BasicPlugin {
     // note: using options cause I haven't implemented the default yet
-    filter_process: Option<biquad::Process>,
+    filter_process: Option<biquad::MultichannelProcess>,
 }
 
 impl Plugin for BasicPlugin {
     fn init(&mut self) {
-        let params = biquad::Params::from_audio_filter_params(
+        // one filter memory per channel: no bleeding between left and right
+        self.filter_process = Some(biquad::MultichannelProcess::from_audio_filter_params(
             filter::Params {
                 fc: 500.0,
                 q: 10.0,
@@ -23,14 +27,23 @@ impl Plugin for BasicPlugin {
             },
             filter::Type::LowPass,
             44100,
-        );
-        self.filter_process = Some(biquad::Process::new(params));
+            CHANNELS,
+        ));
     }
+
+    fn set_sample_rate(&mut self, rate: f32) {
+        if let Some(process) = self.filter_process.as_mut() {
+            process.set_sample_rate(rate as i32);
+        }
+    }
+
     fn get_info(&self) -> Info {
         Info {
             name: "actondev DSP playground Basic Plugin vst 0.2.0".to_string(),
             vendor: "actondev".to_string(),
             unique_id: 1358, // Used by hosts to differentiate between plugins.
+            inputs: CHANNELS as i32,
+            outputs: CHANNELS as i32,
 
             ..Default::default()
         }
@@ -38,13 +51,11 @@ impl Plugin for BasicPlugin {
 
     fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
         // Option::as_mut(&self) : important :)
-        let process: &mut biquad::Process = self.filter_process.as_mut().unwrap();
-        // For each input and output
-        for (input, output) in buffer.zip() {
-            // For each input sample and output sample in buffer
-            for (in_sample, out_sample) in input.into_iter().zip(output.into_iter()) {
-                // *out_sample = *in_sample * 0.5;
-                *out_sample = process.process(in_sample);
+        let process: &mut biquad::MultichannelProcess = self.filter_process.as_mut().unwrap();
+        // For each input and output, through its own channel
+        for (channel, (input, output)) in buffer.zip().enumerate() {
+            if let Some(channel_process) = process.channel_mut(channel) {
+                channel_process.process_block(input, output);
             }
         }
     }